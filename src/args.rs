@@ -0,0 +1,503 @@
+use crate::assert::Assertion;
+use crate::expect::Expect;
+use crate::framing::Framing;
+use crate::{ColorMode, DumpFormat, InputMode, OutputFormat, Repeat, TextEncoding, Theme, utils};
+use clap::{Args, FromArgMatches, Subcommand};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Everything udd can be asked to do. `udd TARGET [COMMAND]` is short for `udd send`.
+#[derive(Subcommand)]
+pub enum Command {
+    /// Send COMMAND (or stdin) to TARGET
+    Send(SendArgs),
+    /// Send an MQTT command, e.g. `udd mqtt host:1883 connect dev1`
+    Mqtt(SendArgs),
+    /// Bind ADDR and print every incoming datagram
+    Listen(ListenArgs),
+    /// Interactive terminal UI
+    Tui(TuiArgs),
+    /// Run one command per line from a file (`sleep <duration>` pauses, `#` starts a comment)
+    Script(ScriptArgs),
+    /// Run `send: <command>` and `expect: <pattern> [within <duration>]` steps, separated by `;`,
+    /// failing on the first unmet expect
+    Assert(AssertArgs),
+    /// Send a file, then send it again whenever it changes on disk, until interrupted
+    Watch(WatchArgs),
+    /// Re-send the sent datagrams of a `--output json` capture with their original timing
+    Replay(ReplayArgs),
+    /// Read stdin continuously and send each record (see --delimiter) as its own datagram
+    Stream(StreamArgs),
+    /// Pipe stdin to TARGET as datagrams and replies to stdout verbatim, with no decoration
+    Raw(RawArgs),
+    /// Forward datagrams between clients of a local address and TARGET, printing each one
+    Relay(RelayArgs),
+    /// Send sequence-stamped probes to an echo service and report loss and RTT, like ping
+    Ping(PingArgs),
+    /// Flood TARGET with generated datagrams and report the achieved throughput
+    Bench(BenchArgs),
+    /// Broadcast COMMAND to TARGET (e.g. 255.255.255.255:PORT) and list every responder within
+    /// --wait (default 2s)
+    Discover(ProbeArgs),
+    /// Probe --ports on TARGET's host with COMMAND and report open/closed/filtered
+    Scan(ScanArgs),
+    /// Send COMMAND with TTL 1, 2, ... and print the router at each hop (Linux)
+    Trace(ProbeArgs),
+    /// Print a shell completion script
+    Completions { shell: clap_complete::Shell },
+}
+
+/// Flags that go with every subcommand.
+#[derive(Args)]
+struct Global {
+    /// Print the resolved configuration and exit
+    #[arg(long, global = true)]
+    print_config: bool,
+}
+
+/// The parsed command line.
+pub struct Cli {
+    pub command: Command,
+    pub print_config: bool,
+}
+
+impl Cli {
+    /// The clap command, also used to generate completions.
+    pub fn command() -> clap::Command {
+        let command = clap::Command::new("udd")
+            .args_conflicts_with_subcommands(true)
+            .subcommand_negates_reqs(true)
+            .disable_help_subcommand(true);
+        // Derived doc comments set `about`, so ours goes last.
+        Command::augment_subcommands(SendArgs::augment_args(Global::augment_args(command)))
+            .about("UDP client with single-shot CLI and TUI")
+    }
+
+    /// Parse the command line, exiting with clap's usage error if it doesn't fit.
+    pub fn parse() -> Self {
+        let matches = Self::command().get_matches();
+        let global = Global::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+        let command = match matches.subcommand() {
+            Some(_) => Command::from_arg_matches(&matches),
+            None => SendArgs::from_arg_matches(&matches).map(Command::Send),
+        }
+        .unwrap_or_else(|err| err.exit());
+
+        // Flags before a subcommand make clap read its name as TARGET.
+        if let Command::Send(args) = &command
+            && Self::command()
+                .get_subcommands()
+                .any(|sub| sub.get_name() == args.target)
+        {
+            Self::command()
+                .error(
+                    clap::error::ErrorKind::ArgumentConflict,
+                    format!(
+                        "options go after the subcommand: udd {} [OPTIONS] ...",
+                        args.target
+                    ),
+                )
+                .exit();
+        }
+
+        let command = match command {
+            Command::Mqtt(mut args) => {
+                args.payload.mode = InputMode::Mqtt;
+                Command::Mqtt(args)
+            }
+            command => command,
+        };
+        Self {
+            command,
+            print_config: global.print_config,
+        }
+    }
+}
+
+/// The local socket udd sends from.
+#[derive(Args, Clone)]
+pub struct SocketArgs {
+    #[arg(short, long, default_value = "0.0.0.0:0")]
+    pub bind: String,
+    /// Local port to send from; replaces the port in --bind
+    #[arg(long, value_name = "PORT")]
+    pub source_port: Option<u16>,
+    #[command(flatten)]
+    pub reuse: ReuseArgs,
+}
+
+impl SocketArgs {
+    /// `--bind` with `--source-port` applied.
+    pub fn local(&self) -> String {
+        match self.source_port {
+            Some(port) => {
+                let host = self
+                    .bind
+                    .rsplit_once(':')
+                    .map_or(self.bind.as_str(), |(host, _)| host);
+                format!("{}:{}", host, port)
+            }
+            None => self.bind.clone(),
+        }
+    }
+}
+
+#[derive(Args, Clone, Copy)]
+pub struct ReuseArgs {
+    /// Set SO_REUSEADDR before binding, e.g. to rebind a port right after a restart
+    #[arg(long)]
+    pub reuse_addr: bool,
+    /// Set SO_REUSEPORT before binding, so other processes can bind the same port (Unix)
+    #[arg(long)]
+    pub reuse_port: bool,
+}
+
+/// How typed commands become datagrams.
+#[derive(Args, Clone, Copy)]
+pub struct PayloadArgs {
+    #[arg(long, value_enum, default_value_t = InputMode::Auto)]
+    pub mode: InputMode,
+    /// Seed for `fuzz <len>` payloads (defaults to the current time)
+    #[arg(long)]
+    pub seed: Option<u64>,
+}
+
+/// How the CLI prints what it sends and receives.
+#[derive(Args, Clone)]
+pub struct OutputArgs {
+    /// CLI output: human-readable lines, or one JSON object per event
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+    /// Color the CLI's text output; auto colors a terminal unless NO_COLOR is set
+    #[arg(long, value_enum, default_value_t = ColorMode::Auto)]
+    pub color: ColorMode,
+    /// Only print received data and errors
+    #[arg(short, long)]
+    pub quiet: bool,
+    /// Print received datagrams as a full dump instead of a one-line summary
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    pub dump: Option<DumpFormat>,
+    /// Also write every datagram received to its own file in this directory
+    #[arg(long, value_name = "DIR")]
+    pub output_dir: Option<PathBuf>,
+    /// How received text is rendered: utf8 falls back to hex, latin1 maps bytes 1:1, ascii shows high bytes as '.'
+    #[arg(long, value_enum, default_value_t = TextEncoding::Utf8)]
+    pub text_encoding: TextEncoding,
+}
+
+/// What a send waits for afterwards.
+#[derive(Args, Clone)]
+pub struct ReplyArgs {
+    /// After sending, print replies for this long (e.g. 500ms); a Subscribe fails unless every filter is granted
+    #[arg(long, visible_alias = "timeout", value_parser = utils::parse_duration)]
+    pub wait: Option<Duration>,
+    /// Stop waiting after this many replies; fewer within --wait is a failure
+    #[arg(long, value_name = "N", requires = "wait")]
+    pub expect_count: Option<usize>,
+    /// Succeed only if a reply within --wait matches: `text:<s>`, `hex:<prefix>` or an MQTT type like `connack:accepted`
+    #[arg(long, value_name = "PATTERN", requires = "wait")]
+    pub expect: Option<Expect>,
+}
+
+#[derive(Args)]
+pub struct SendArgs {
+    #[arg(value_name = "TARGET")]
+    pub target: String,
+    #[arg(
+        value_name = "COMMAND",
+        trailing_var_arg = true,
+        allow_hyphen_values = true
+    )]
+    pub command: Vec<String>,
+    /// Send each file matching the glob (e.g. 'fixtures/*.bin') as its own datagram, in sorted order
+    #[arg(long, value_name = "GLOB", conflicts_with_all = ["command", "repeat"])]
+    pub send_glob: Option<String>,
+    /// Send the payload N times, or `forever`, pausing --interval (default 1s) between sends
+    #[arg(long, value_name = "N|forever")]
+    pub repeat: Option<Repeat>,
+    /// Delay between consecutive sends, e.g. 250ms or 1s
+    #[arg(long, value_parser = utils::parse_duration)]
+    pub interval: Option<Duration>,
+    /// Append one CSV row per --repeat send (seq, times, rtt, bytes, outcome)
+    #[arg(long, value_name = "PATH", requires = "repeat")]
+    pub stats_csv: Option<PathBuf>,
+    #[command(flatten)]
+    pub reply: ReplyArgs,
+    #[command(flatten)]
+    pub payload: PayloadArgs,
+    #[command(flatten)]
+    pub socket: SocketArgs,
+    #[command(flatten)]
+    pub framing: Framing,
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Args)]
+pub struct ListenArgs {
+    pub addr: String,
+    #[arg(long, value_enum, default_value_t = InputMode::Auto)]
+    pub mode: InputMode,
+    #[command(flatten)]
+    pub reuse: ReuseArgs,
+    #[command(flatten)]
+    pub framing: Framing,
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Args)]
+pub struct TuiArgs {
+    pub target: String,
+    /// Color theme; defaults to `theme` in ~/.config/udd/config, else dark (monochrome with NO_COLOR)
+    #[arg(long, value_enum)]
+    pub theme: Option<Theme>,
+    /// Log the decode error and a full hexdump when a received datagram isn't a valid frame
+    #[arg(long)]
+    pub dump_on_error: bool,
+    /// Append every log line (timestamped) to this file as it happens
+    #[arg(long, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+    /// Keep newlines in pasted input instead of sending each line separately
+    #[arg(long)]
+    pub literal_newlines: bool,
+    /// Don't load or save input history (~/.local/share/udd/history)
+    #[arg(long)]
+    pub no_history: bool,
+    /// Ring the terminal bell when a datagram arrives while the log is scrolled up
+    #[arg(long)]
+    pub bell: bool,
+    /// How long a QoS 1/2 publish waits for its PUBACK before it is shown as lost
+    #[arg(long, default_value = "5s", value_parser = utils::parse_duration)]
+    pub ack_timeout: Duration,
+    /// Drop the oldest log entries past this many
+    #[arg(long, value_name = "N", default_value_t = 50_000)]
+    pub max_log: usize,
+    /// Also drop the oldest log entries once their payloads take up this many megabytes
+    #[arg(long, value_name = "MB", default_value_t = 256)]
+    pub max_log_mb: usize,
+    /// Template for sent/received log lines: {dir} {mode} {bytes} {hex} {mqtt} {preview}
+    #[arg(long, value_name = "TEMPLATE")]
+    pub send_format: Option<String>,
+    /// Destination when toggled to broadcast (Ctrl+B); defaults to 255.255.255.255 on the target port
+    #[arg(long, value_name = "ADDR")]
+    pub broadcast: Option<String>,
+    /// How received text is rendered: utf8 falls back to hex, latin1 maps bytes 1:1, ascii shows high bytes as '.'
+    #[arg(long, value_enum, default_value_t = TextEncoding::Utf8)]
+    pub text_encoding: TextEncoding,
+    /// Seed for `fuzz <len>` payloads (defaults to the current time)
+    #[arg(long)]
+    pub seed: Option<u64>,
+    #[command(flatten)]
+    pub socket: SocketArgs,
+    #[command(flatten)]
+    pub framing: Framing,
+}
+
+#[derive(Args)]
+pub struct ScriptArgs {
+    pub target: String,
+    pub path: PathBuf,
+    #[command(flatten)]
+    pub reply: ReplyArgs,
+    #[command(flatten)]
+    pub payload: PayloadArgs,
+    #[command(flatten)]
+    pub socket: SocketArgs,
+    #[command(flatten)]
+    pub framing: Framing,
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Args)]
+pub struct AssertArgs {
+    pub target: String,
+    #[arg(value_name = "STEPS")]
+    pub assertion: Assertion,
+    /// How long an expect without `within` waits (default 1s)
+    #[arg(long, value_parser = utils::parse_duration)]
+    pub wait: Option<Duration>,
+    #[command(flatten)]
+    pub payload: PayloadArgs,
+    #[command(flatten)]
+    pub socket: SocketArgs,
+    #[command(flatten)]
+    pub framing: Framing,
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Args)]
+pub struct WatchArgs {
+    pub target: String,
+    pub file: PathBuf,
+    #[arg(long, value_enum, default_value_t = InputMode::Auto)]
+    pub mode: InputMode,
+    #[command(flatten)]
+    pub socket: SocketArgs,
+    #[command(flatten)]
+    pub framing: Framing,
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Args)]
+pub struct ReplayArgs {
+    pub target: String,
+    #[arg(value_name = "CAPTURE")]
+    pub capture: PathBuf,
+    /// Playback speed: 2 halves every gap, 0.5 doubles it
+    #[arg(long, default_value_t = 1.0)]
+    pub speed: f64,
+    /// Keep printing replies this long after the last send
+    #[arg(long, value_parser = utils::parse_duration)]
+    pub wait: Option<Duration>,
+    #[arg(long, value_enum, default_value_t = InputMode::Auto)]
+    pub mode: InputMode,
+    #[command(flatten)]
+    pub socket: SocketArgs,
+    #[command(flatten)]
+    pub framing: Framing,
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Args)]
+pub struct StreamArgs {
+    pub target: String,
+    /// Record separator, with escapes like \n, \0 or \x1e
+    #[arg(long, default_value = "\\n")]
+    pub delimiter: String,
+    /// Delay between consecutive sends, e.g. 250ms or 1s
+    #[arg(long, value_parser = utils::parse_duration)]
+    pub interval: Option<Duration>,
+    #[arg(long, value_enum, default_value_t = InputMode::Auto)]
+    pub mode: InputMode,
+    #[command(flatten)]
+    pub socket: SocketArgs,
+    #[command(flatten)]
+    pub framing: Framing,
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Args)]
+pub struct RawArgs {
+    pub target: String,
+    /// Keep copying replies this long after stdin closes
+    #[arg(long, value_parser = utils::parse_duration)]
+    pub wait: Option<Duration>,
+    #[command(flatten)]
+    pub socket: SocketArgs,
+    #[command(flatten)]
+    pub framing: Framing,
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Args)]
+pub struct RelayArgs {
+    pub target: String,
+    /// Local address the clients send to
+    #[arg(long, value_name = "ADDR")]
+    pub listen: String,
+    #[arg(long, value_enum, default_value_t = InputMode::Auto)]
+    pub mode: InputMode,
+    #[command(flatten)]
+    pub socket: SocketArgs,
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Args)]
+pub struct PingArgs {
+    pub target: String,
+    /// Number of probes (default: until interrupted)
+    #[arg(long, value_name = "N")]
+    pub count: Option<u64>,
+    /// Delay between probes (default 1s)
+    #[arg(long, value_parser = utils::parse_duration)]
+    pub interval: Option<Duration>,
+    /// Append one CSV row per probe (seq, times, rtt, bytes, outcome)
+    #[arg(long, value_name = "PATH")]
+    pub stats_csv: Option<PathBuf>,
+    #[command(flatten)]
+    pub socket: SocketArgs,
+    #[command(flatten)]
+    pub framing: Framing,
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Args)]
+pub struct BenchArgs {
+    pub target: String,
+    /// Datagram size
+    #[arg(long, value_name = "BYTES", default_value_t = 512, value_parser = utils::parse_datagram_size)]
+    pub size: usize,
+    /// Target packets per second (default: as fast as possible)
+    #[arg(long)]
+    pub pps: Option<u32>,
+    /// How long to run, e.g. 10s
+    #[arg(long, default_value = "10s", value_parser = utils::parse_duration)]
+    pub duration: Duration,
+    /// Seed for the generated payload (defaults to the current time)
+    #[arg(long)]
+    pub seed: Option<u64>,
+    #[command(flatten)]
+    pub socket: SocketArgs,
+    #[command(flatten)]
+    pub framing: Framing,
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+/// `discover` and `trace`: send one probe (COMMAND, or an empty datagram) and watch what answers.
+#[derive(Args)]
+pub struct ProbeArgs {
+    pub target: String,
+    #[arg(
+        value_name = "COMMAND",
+        trailing_var_arg = true,
+        allow_hyphen_values = true
+    )]
+    pub command: Vec<String>,
+    /// How long to wait for answers
+    #[arg(long, value_parser = utils::parse_duration)]
+    pub wait: Option<Duration>,
+    #[command(flatten)]
+    pub payload: PayloadArgs,
+    #[command(flatten)]
+    pub socket: SocketArgs,
+    #[command(flatten)]
+    pub framing: Framing,
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Args)]
+pub struct ScanArgs {
+    /// Ports to probe, e.g. 5000-5100 or 53,67-69
+    // The full path stops clap treating a `Vec` as one port per value, so parse_ports gets the list.
+    #[arg(long, value_name = "PORTS", value_parser = utils::parse_ports)]
+    pub ports: ::std::vec::Vec<u16>,
+    #[command(flatten)]
+    pub probe: ProbeArgs,
+}
+
+impl Command {
+    /// The `--seed` given to a subcommand that generates payloads.
+    pub fn seed(&self) -> Option<u64> {
+        match self {
+            Command::Send(args) | Command::Mqtt(args) => args.payload.seed,
+            Command::Tui(args) => args.seed,
+            Command::Script(args) => args.payload.seed,
+            Command::Assert(args) => args.payload.seed,
+            Command::Discover(args) | Command::Trace(args) => args.payload.seed,
+            Command::Scan(args) => args.probe.payload.seed,
+            Command::Bench(args) => args.seed,
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use crate::expect::Expect;
+use crate::utils;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// An `udd assert` sequence: `;`-separated steps, each `send: <command>` (any one-shot command,
+/// e.g. `mqtt connect dev1`) or `expect: <pattern> [within <duration>]`. Patterns are `--expect`
+/// patterns, and a packet's detail may follow a space as well as a colon (`connack accepted`).
+#[derive(Clone)]
+pub struct Assertion {
+    pub steps: Vec<Step>,
+}
+
+#[derive(Clone)]
+pub enum Step {
+    Send(String),
+    Expect {
+        pattern: Expect,
+        within: Option<Duration>,
+    },
+}
+
+impl FromStr for Assertion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let steps = s
+            .split(';')
+            .map(str::trim)
+            .filter(|step| !step.is_empty())
+            .enumerate()
+            .map(|(i, step)| parse_step(step).map_err(|err| format!("step {}: {}", i + 1, err)))
+            .collect::<Result<Vec<_>, _>>()?;
+        if steps.is_empty() {
+            return Err("an assertion needs at least one step".into());
+        }
+        Ok(Self { steps })
+    }
+}
+
+fn parse_step(step: &str) -> Result<Step, String> {
+    let (kind, body) = step
+        .split_once(':')
+        .ok_or("expected `send: <command>` or `expect: <pattern>`")?;
+    let body = body.trim();
+    match kind.trim().to_lowercase().as_str() {
+        "send" if body.is_empty() => Err("send needs a command".into()),
+        "send" => Ok(Step::Send(body.into())),
+        "expect" => {
+            let (pattern, within) = match body.rsplit_once(" within ") {
+                Some((pattern, within)) => (pattern.trim(), Some(utils::parse_duration(within)?)),
+                None => (body, None),
+            };
+            let pattern = match pattern.split_once(' ') {
+                Some((packet, detail)) if Expect::is_packet(packet) => {
+                    format!("{}:{}", packet, detail.trim())
+                }
+                _ => pattern.to_string(),
+            };
+            Ok(Step::Expect {
+                pattern: pattern.parse()?,
+                within,
+            })
+        }
+        other => Err(format!("unknown step '{}': use send or expect", other)),
+    }
+}
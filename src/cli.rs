@@ -1,24 +1,724 @@
-use std::io::{Error, ErrorKind};
+use crate::InputMode;
+use crate::args::{Command, ListenArgs, ProbeArgs, ReplyArgs, ScriptArgs, SendArgs, SocketArgs};
+use crate::framing::Framing;
+use clap::ValueEnum;
+use mqtt::{Packet, SubAck, SubAckReturnCode, SubscribeFilter, UdpFrame};
+use output::Printer;
+use std::io::{Error, ErrorKind, IsTerminal, Read};
 use std::net::UdpSocket;
+use std::process::ExitCode;
+use std::time::{Duration, Instant, SystemTime};
 
-pub fn run(args: &crate::Args) -> std::io::Result<()> {
+mod assert;
+mod bench;
+mod discover;
+mod keepalive;
+mod output;
+mod ping;
+mod raw;
+mod relay;
+mod replay;
+mod scan;
+mod session;
+mod stats;
+mod stream;
+mod trace;
+mod watch;
+
+pub fn run(command: &Command) -> std::io::Result<ExitCode> {
+    match command {
+        Command::Send(args) | Command::Mqtt(args) => send(args),
+        Command::Listen(args) => listen(args).map(|_| ExitCode::SUCCESS),
+        Command::Script(args) => run_script(args),
+        Command::Assert(args) => assert::run(args),
+        Command::Watch(args) => watch::run(args),
+        Command::Replay(args) => replay::run(args),
+        Command::Stream(args) => stream::run(args),
+        Command::Raw(args) => raw::run(args).map(|_| ExitCode::SUCCESS),
+        Command::Relay(args) => relay::run(args).map(|_| ExitCode::SUCCESS),
+        Command::Ping(args) => ping::run(args, &Printer::new(&args.output)),
+        Command::Bench(args) => bench::run(args),
+        Command::Discover(args) => {
+            let (mode, probe) = payload(&args.command, args.payload.mode)?;
+            discover::run(args, mode, &probe)
+        }
+        Command::Scan(args) => {
+            let (mode, probe) = probe(&args.probe)?;
+            scan::run(&args.probe, &args.ports, mode, &probe)
+        }
+        Command::Trace(args) => trace::run(args, &probe(args)?.1),
+        Command::Tui(_) | Command::Completions { .. } => unreachable!("handled in main"),
+    }
+}
+
+/// `udd send`: one payload, optionally repeated and followed by a wait for replies.
+fn send(args: &SendArgs) -> std::io::Result<ExitCode> {
+    if let Some(pattern) = &args.send_glob {
+        return send_glob(args, pattern).map(|_| ExitCode::SUCCESS);
+    }
     let command = args.command.join(" ");
     let command = command.trim();
-    if command.is_empty() {
-        return Err(Error::new(
+    // An explicit --mode sends the input as typed, so only auto mode reads `file <path>`.
+    if args.payload.mode == InputMode::Auto
+        && let Some(rest) = command.strip_prefix("file ")
+    {
+        return send_file(args, rest);
+    }
+    let (mut mode, mut payload) = payload(&args.command, args.payload.mode)?;
+
+    let printer = Printer::new(&args.output);
+    if let Some(warning) = crate::tui::format::empty_publish_warning(&payload) {
+        printer.warning(warning);
+    }
+
+    let framing = &args.framing;
+    let mut framed = framing
+        .apply(&payload)
+        .map_err(|err| Error::new(ErrorKind::InvalidInput, err))?;
+
+    let socket = connect(&args.socket, &args.target)?;
+    let Some(repeat) = args.repeat else {
+        let (sent, sent_at) = send_timed(&socket, &framed)?;
+        printer.sent(mode.short_label(), sent, &payload, None, &args.target);
+
+        return wait_for_replies(
+            &args.reply,
+            framing,
+            &printer,
+            &socket,
+            mode,
+            &payload,
+            sent_at,
+        );
+    };
+
+    // Repeated sends report each failure and carry on; the exit code says whether any failed.
+    let interval = args.interval.unwrap_or(Duration::from_secs(1));
+    let mut stats = args
+        .stats_csv
+        .as_deref()
+        .map(stats::StatsCsv::open)
+        .transpose()?;
+    let mut status = ExitCode::SUCCESS;
+    let mut count = 0;
+    while repeat.more(count) {
+        if count > 0 {
+            std::thread::sleep(interval);
+            // A typed command is rebuilt per send, so placeholders and msg_ids are fresh.
+            if !command.is_empty() {
+                (mode, payload) = parse_command(args.payload.mode, command)?;
+                framed = framing
+                    .apply(&payload)
+                    .map_err(|err| Error::new(ErrorKind::InvalidInput, err))?;
+            }
+        }
+        count += 1;
+
+        let sent_time = SystemTime::now();
+        let (outcome, answer) = match send_timed(&socket, &framed) {
+            Ok((sent, sent_at)) => {
+                printer.sent(mode.short_label(), sent, &payload, None, &args.target);
+                match args.reply.wait {
+                    Some(_) => {
+                        let (result, answer, _) = wait_for_answer(
+                            &args.reply,
+                            framing,
+                            &printer,
+                            &socket,
+                            mode,
+                            &payload,
+                            sent_at,
+                        )?;
+                        let outcome = if result == ExitCode::SUCCESS {
+                            "ok"
+                        } else {
+                            "fail"
+                        };
+                        (outcome, answer)
+                    }
+                    None => ("sent", None),
+                }
+            }
+            Err(err) => {
+                printer.error(&format!("send {}: {}", count, err));
+                ("error", None)
+            }
+        };
+        if !matches!(outcome, "ok" | "sent") {
+            status = ExitCode::FAILURE;
+        }
+        if let Some(stats) = &mut stats {
+            let answer = answer.map(|answer| (answer.rtt, answer.bytes));
+            stats.row(count - 1, sent_time, answer, outcome)?;
+        }
+    }
+    Ok(status)
+}
+
+/// The payload for a typed command, or for piped stdin when no command was given.
+fn payload(command: &[String], mode: InputMode) -> std::io::Result<(InputMode, Vec<u8>)> {
+    let command = command.join(" ");
+    let command = command.trim();
+    if !command.is_empty() {
+        parse_command(mode, command)
+    } else if !std::io::stdin().is_terminal() {
+        read_stdin_payload(mode)
+    } else {
+        Err(Error::new(
             ErrorKind::InvalidInput,
-            "CLI requires a command. Example: udd <target> --mode mqtt connect id1",
+            "CLI requires a command. Example: udd <target> mqtt connect id1",
+        ))
+    }
+}
+
+/// The probe for `scan` and `trace`: the command, or an empty datagram, which is a fine default
+/// for most services.
+fn probe(args: &ProbeArgs) -> std::io::Result<(InputMode, Vec<u8>)> {
+    let command = args.command.join(" ");
+    match command.trim() {
+        "" => Ok((args.payload.mode, vec![])),
+        command => parse_command(args.payload.mode, command),
+    }
+}
+
+fn parse_command(mode: InputMode, command: &str) -> std::io::Result<(InputMode, Vec<u8>)> {
+    let (mode, command) = split_mode(mode, command);
+    crate::parse::parse_payload(mode, command)
+        .map_err(|err| Error::new(ErrorKind::InvalidInput, err))
+}
+
+/// Build the payload from piped stdin.
+fn read_stdin_payload(mode: InputMode) -> std::io::Result<(InputMode, Vec<u8>)> {
+    let mut data = vec![];
+    std::io::stdin().read_to_end(&mut data)?;
+    if data.is_empty() {
+        return Err(Error::new(ErrorKind::InvalidInput, "stdin is empty"));
+    }
+    payload_from_bytes(mode, data)
+}
+
+/// Build a payload from bytes read from stdin. Auto mode sends them untouched; the other modes
+/// interpret them like a typed command.
+fn payload_from_bytes(mode: InputMode, data: Vec<u8>) -> std::io::Result<(InputMode, Vec<u8>)> {
+    match (mode, str::from_utf8(&data)) {
+        (InputMode::Auto, Ok(_)) => Ok((InputMode::Text, data)),
+        (InputMode::Auto, Err(_)) => Ok((InputMode::Hex, data)),
+        (InputMode::Text, Err(_)) => Ok((InputMode::Text, data)),
+        (mode, Ok(text)) => crate::parse::parse_payload(mode, text)
+            .map_err(|err| Error::new(ErrorKind::InvalidInput, err)),
+        (_, Err(_)) => Err(Error::new(
+            ErrorKind::InvalidData,
+            "stdin is not valid UTF-8; use --mode auto to send it as-is",
+        )),
+    }
+}
+
+/// Under `--mode auto`, a leading mode keyword (`text hello`, `hex deadbeef`, `mqtt ping`) picks
+/// the mode for that command. An explicit `--mode` sends the command as typed.
+fn split_mode(mode: InputMode, command: &str) -> (InputMode, &str) {
+    if !matches!(mode, InputMode::Auto) {
+        return (mode, command);
+    }
+    let (word, rest) = command.split_once(' ').unwrap_or((command, ""));
+    match InputMode::from_str(word, true) {
+        Ok(mode) if !rest.trim().is_empty() => (mode, rest.trim()),
+        _ => (mode, command),
+    }
+}
+
+/// Send a datagram and note when it left, for measuring the round trip.
+fn send_timed(socket: &UdpSocket, data: &[u8]) -> std::io::Result<(usize, Instant)> {
+    let sent = socket.send(data)?;
+    Ok((sent, Instant::now()))
+}
+
+/// Print replies until `--wait` elapses or `--expect-count` replies arrive; without `--wait`
+/// nothing is awaited. Fails if nothing (or fewer than expected) arrives, or if no reply matches
+/// `--expect`. A sent Subscribe must be answered by a SubAck for the same msg_id that grants
+/// every filter, otherwise the exit code reports failure.
+///
+/// The round trip from `sent_at` is printed for the first reply that answers the send: the
+/// first `--expect` match, else the SubAck to a Subscribe, else simply the first reply.
+fn wait_for_replies(
+    reply: &ReplyArgs,
+    framing: &Framing,
+    printer: &Printer,
+    socket: &UdpSocket,
+    mode: InputMode,
+    sent: &[u8],
+    sent_at: Instant,
+) -> std::io::Result<ExitCode> {
+    wait_for_answer(reply, framing, printer, socket, mode, sent, sent_at).map(|(status, ..)| status)
+}
+
+/// The first reply that answered a send: its round trip and size.
+struct Answer {
+    rtt: Duration,
+    bytes: usize,
+}
+
+/// `wait_for_replies`, also returning the answering reply if one arrived and every reply seen.
+fn wait_for_answer(
+    args: &ReplyArgs,
+    framing: &Framing,
+    printer: &Printer,
+    socket: &UdpSocket,
+    mode: InputMode,
+    sent: &[u8],
+    sent_at: Instant,
+) -> std::io::Result<(ExitCode, Option<Answer>, Vec<Vec<u8>>)> {
+    let Some(wait) = args.wait else {
+        return Ok((ExitCode::SUCCESS, None, vec![]));
+    };
+    let subscribe = match UdpFrame::decode(sent) {
+        Ok(UdpFrame {
+            msg_id,
+            packet: Packet::Subscribe(s),
+        }) => Some((msg_id, s.filters)),
+        _ => None,
+    };
+
+    let deadline = Instant::now() + wait;
+    let mut received = 0;
+    let mut suback_seen = false;
+    let mut matched = false;
+    let mut answer = None;
+    let mut replies = vec![];
+    let mut buffer = [0u8; crate::utils::RECV_BUFFER];
+    loop {
+        if args.expect_count.is_some_and(|count| received >= count)
+            && (args.expect.is_none() || matched)
+        {
+            break;
+        }
+
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        socket.set_read_timeout(Some(remaining))?;
+
+        let n = match socket.recv(&mut buffer) {
+            Ok(n) => n,
+            Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => break,
+            Err(err) if err.kind() == ErrorKind::ConnectionRefused => {
+                printer.error("ICMP: Connection refused (port unreachable)");
+                return Ok((ExitCode::FAILURE, answer, replies));
+            }
+            Err(err) => return Err(err),
+        };
+        let received_at = Instant::now();
+        let data = framing.strip(&buffer[..n]);
+        received += 1;
+        printer.received(mode, data, None);
+        replies.push(data.to_vec());
+
+        if answer.is_none() {
+            let answers = match (&args.expect, &subscribe) {
+                (Some(expect), _) => expect.matches(data),
+                (None, Some((msg_id, _))) => matches!(
+                    UdpFrame::decode(data),
+                    Ok(UdpFrame { msg_id: reply_id, packet: Packet::SubAck(_) }) if reply_id == *msg_id
+                ),
+                (None, None) => true,
+            };
+            if answers {
+                let rtt = received_at - sent_at;
+                printer.rtt(rtt);
+                answer = Some(Answer {
+                    rtt,
+                    bytes: data.len(),
+                });
+            }
+        }
+
+        if let Some(expect) = &args.expect
+            && !matched
+            && expect.matches(data)
+        {
+            matched = true;
+            if args.expect_count.is_none_or(|count| received >= count) {
+                break;
+            }
+        }
+
+        if let Some((msg_id, filters)) = &subscribe
+            && let Ok(UdpFrame {
+                msg_id: reply_id,
+                packet: Packet::SubAck(ack),
+            }) = UdpFrame::decode(data)
+            && reply_id == *msg_id
+        {
+            let errors = suback_errors(filters, &ack);
+            if !errors.is_empty() {
+                for error in errors {
+                    printer.error(&error);
+                }
+                return Ok((ExitCode::FAILURE, answer, replies));
+            }
+            suback_seen = true;
+            if args.expect_count.is_none() && args.expect.is_none() {
+                break;
+            }
+        }
+    }
+
+    if received == 0 {
+        printer.error(&format!("no reply within {:?}", wait));
+        return Ok((ExitCode::FAILURE, answer, replies));
+    }
+    if let Some(count) = args.expect_count
+        && received < count
+    {
+        printer.error(&format!(
+            "got {} of {} replies within {:?}",
+            received, count, wait
         ));
+        return Ok((ExitCode::FAILURE, answer, replies));
+    }
+    if let Some(expect) = &args.expect
+        && !matched
+    {
+        printer.error(&format!("no reply matching '{}' within {:?}", expect, wait));
+        return Ok((ExitCode::FAILURE, answer, replies));
+    }
+    if subscribe.is_some() && !suback_seen {
+        printer.error(&format!("no SUBACK within {:?}", wait));
+        return Ok((ExitCode::FAILURE, answer, replies));
     }
+    Ok((ExitCode::SUCCESS, answer, replies))
+}
 
-    let (mode, payload) = crate::tui::parse_payload(args.mode, command)
-        .map_err(|err| Error::new(ErrorKind::InvalidInput, err))?;
+/// Why a SubAck fails its Subscribe: a code count that doesn't match, or each refused filter.
+fn suback_errors(filters: &[SubscribeFilter], ack: &SubAck) -> Vec<String> {
+    if ack.return_codes.len() != filters.len() {
+        return vec![format!(
+            "SUBACK has {} return codes for {} filters",
+            ack.return_codes.len(),
+            filters.len()
+        )];
+    }
+    filters
+        .iter()
+        .zip(&ack.return_codes)
+        .filter(|(_, code)| matches!(code, SubAckReturnCode::Failure))
+        .map(|(filter, _)| format!("subscription refused: {}", filter.topic))
+        .collect()
+}
+
+/// Receive-only: bind the target address and print every datagram from any sender.
+fn listen(args: &ListenArgs) -> std::io::Result<()> {
+    let socket = crate::utils::bind_udp(&args.addr, args.reuse.reuse_addr, args.reuse.reuse_port)?;
+    let framing = &args.framing;
+    let printer = Printer::new(&args.output);
+    printer.info(&format!("listening on {}", socket.local_addr()?));
+
+    let mut buffer = [0u8; crate::utils::RECV_BUFFER];
+    loop {
+        let (n, from) = socket.recv_from(&mut buffer)?;
+        printer.received(args.mode, framing.strip(&buffer[..n]), Some(from));
+    }
+}
+
+/// Send each command line of a script in order. A line that fails to parse or send aborts the
+/// script; with `--wait`, so does a command whose replies fail the wait checks.
+fn run_script(args: &ScriptArgs) -> std::io::Result<ExitCode> {
+    let path = &args.path;
+    let script = std::fs::read_to_string(path)?;
+    let framing = &args.framing;
+    let printer = Printer::new(&args.output);
+    let socket = connect(&args.socket, &args.target)?;
+    let mut session = session::Session::new(&args.target);
+
+    for (i, line) in script.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let at_line = |err: String| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("{}:{}: {}", path.display(), i + 1, err),
+            )
+        };
+
+        if let Some(delay) = line.strip_prefix("sleep ") {
+            let delay = crate::utils::parse_duration(delay.trim()).map_err(at_line)?;
+            session.idle(&socket, framing, &printer, args.payload.mode, delay)?;
+            continue;
+        }
+
+        let (mode, command) = split_mode(args.payload.mode, line);
+        let (mode, payload) = crate::parse::parse_payload(mode, command).map_err(at_line)?;
+        session.check(&payload).map_err(at_line)?;
+        let framed = framing.apply(&payload).map_err(at_line)?;
+        let (sent, sent_at) = send_timed(&socket, &framed)?;
+        printer.sent(mode.short_label(), sent, &payload, None, &args.target);
+        session.sent(&payload);
+
+        match args.reply.wait {
+            Some(_) => {
+                let (status, _, replies) = wait_for_answer(
+                    &args.reply,
+                    framing,
+                    &printer,
+                    &socket,
+                    mode,
+                    &payload,
+                    sent_at,
+                )?;
+                for reply in &replies {
+                    session.received(reply, &printer);
+                }
+                if status != ExitCode::SUCCESS {
+                    return Ok(status);
+                }
+            }
+            // Without --wait, still show whatever the target pushed in the meantime.
+            None => session.idle(
+                &socket,
+                framing,
+                &printer,
+                args.payload.mode,
+                Duration::ZERO,
+            )?,
+        }
+    }
 
-    let label = mode.short_label();
+    if let Some(summary) = session.summary() {
+        printer.info(&summary);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Print whatever arrives until `deadline`.
+fn print_replies_until(
+    socket: &UdpSocket,
+    framing: &Framing,
+    printer: &Printer,
+    mode: InputMode,
+    deadline: Instant,
+    buffer: &mut [u8],
+) -> std::io::Result<()> {
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Ok(());
+        }
+        socket.set_read_timeout(Some(remaining.max(Duration::from_millis(1))))?;
+        match socket.recv(buffer) {
+            Ok(n) => printer.received(mode, framing.strip(&buffer[..n]), None),
+            Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(err) if err.kind() == ErrorKind::ConnectionRefused => {
+                printer.error("ICMP: Connection refused (port unreachable)");
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// `file <path> [chunk=N] [delay=<duration>]`: send a file, split into `N`-byte datagrams paced
+/// by `delay`. Without `chunk=` the whole file must fit in one datagram.
+fn send_file(args: &SendArgs, command: &str) -> std::io::Result<ExitCode> {
+    let invalid = |msg: String| Error::new(ErrorKind::InvalidInput, msg);
+    let usage = "file <path> [chunk=N] [delay=<duration>]";
+
+    let mut parts = command.split_whitespace();
+    let path = parts.next().ok_or_else(|| invalid(usage.into()))?;
+    let mut chunk = None;
+    let mut delay = None;
+    for part in parts {
+        if let Some(n) = part.strip_prefix("chunk=") {
+            match n.parse::<usize>() {
+                Ok(n) if n > 0 => chunk = Some(n),
+                _ => return Err(invalid(format!("invalid chunk size '{}': {}", n, usage))),
+            }
+        } else if let Some(d) = part.strip_prefix("delay=") {
+            delay = Some(crate::utils::parse_duration(d).map_err(invalid)?);
+        } else {
+            return Err(invalid(format!("unexpected '{}': {}", part, usage)));
+        }
+    }
+
+    let data = std::fs::read(path)?;
+    let chunk = match chunk {
+        Some(chunk) => chunk,
+        None if data.len() > crate::utils::MAX_DATAGRAM => {
+            return Err(invalid(format!(
+                "{} is {} bytes, more than one datagram holds; pass chunk=N",
+                path,
+                data.len()
+            )));
+        }
+        None => data.len().max(1),
+    };
+
+    let framing = &args.framing;
+    let printer = Printer::new(&args.output);
+    let socket = connect(&args.socket, &args.target)?;
+    let chunks: Vec<&[u8]> = if data.is_empty() {
+        vec![&[]]
+    } else {
+        data.chunks(chunk).collect()
+    };
+    let total = chunks.len();
+    let mut sent_at = Instant::now();
+    for (i, part) in chunks.into_iter().enumerate() {
+        if i > 0
+            && let Some(delay) = delay
+        {
+            std::thread::sleep(delay);
+        }
+
+        let framed = framing.apply(part).map_err(invalid)?;
+        let sent;
+        (sent, sent_at) = send_timed(&socket, &framed)?;
+        let origin = match total {
+            1 => path.to_string(),
+            _ => format!("{} (chunk {}/{})", path, i + 1, total),
+        };
+        printer.sent("FILE", sent, part, Some(&origin), &args.target);
+    }
+
+    wait_for_replies(
+        &args.reply,
+        framing,
+        &printer,
+        &socket,
+        InputMode::Hex,
+        &data,
+        sent_at,
+    )
+}
 
-    let socket = UdpSocket::bind(&args.bind)?;
-    socket.connect(&args.target)?;
-    let sent = socket.send(&payload)?;
-    println!("→ [{}] sent {} bytes to {}", label, sent, args.target);
+/// Bind `--bind` with the `--reuse-addr` / `--reuse-port` options.
+fn bind(args: &SocketArgs) -> std::io::Result<UdpSocket> {
+    crate::utils::bind_udp(&args.local(), args.reuse.reuse_addr, args.reuse.reuse_port)
+}
+
+fn connect(args: &SocketArgs, target: &str) -> std::io::Result<UdpSocket> {
+    let target = crate::utils::resolve(target)?;
+    let socket = bind(args)?;
+    socket.connect(target)?;
+    Ok(socket)
+}
+
+fn send_glob(args: &SendArgs, pattern: &str) -> std::io::Result<()> {
+    let paths = crate::utils::expand_glob(pattern)?;
+    if paths.is_empty() {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            format!("no files match '{}'", pattern),
+        ));
+    }
+
+    let framing = &args.framing;
+    let printer = Printer::new(&args.output);
+    let socket = connect(&args.socket, &args.target)?;
+    for (i, path) in paths.iter().enumerate() {
+        if i > 0
+            && let Some(interval) = args.interval
+        {
+            std::thread::sleep(interval);
+        }
+
+        // A bad file is reported but doesn't abort the rest of the batch.
+        let result = std::fs::read(path).and_then(|data| {
+            let framed = framing
+                .apply(&data)
+                .map_err(|err| Error::new(ErrorKind::InvalidInput, err))?;
+            socket.send(&framed).map(|sent| (sent, data))
+        });
+        let origin = path.display().to_string();
+        match result {
+            Ok((sent, data)) => printer.sent("FILE", sent, &data, Some(&origin), &args.target),
+            Err(err) => printer.error(&format!("{}: {}", origin, err)),
+        }
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{Args, FromArgMatches};
+
+    /// Parse one subcommand's flags; `argv[0]` is the program name.
+    fn parse<T: Args + FromArgMatches>(argv: &[&str]) -> T {
+        let matches = T::augment_args(clap::Command::new("udd")).get_matches_from(argv);
+        T::from_arg_matches(&matches).unwrap()
+    }
+
+    fn peer() -> (UdpSocket, String) {
+        let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+        peer.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        let addr = peer.local_addr().unwrap().to_string();
+        (peer, addr)
+    }
+
+    #[test]
+    fn send_glob_sends_matching_files_in_sorted_order() {
+        let dir = std::env::temp_dir().join(format!("udd-send-glob-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("b.bin"), "second").unwrap();
+        std::fs::write(dir.join("a.bin"), "first").unwrap();
+        std::fs::write(dir.join("c.txt"), "skipped").unwrap();
+        let (peer, target) = peer();
+        let pattern = dir.join("*.bin").to_string_lossy().into_owned();
+        let args: SendArgs = parse(&["udd", &target, "--quiet", "--send-glob", &pattern]);
+
+        let result = send_glob(&args, &pattern);
+        std::fs::remove_dir_all(&dir).unwrap();
+        result.unwrap();
+
+        let mut buffer = [0u8; 64];
+        for expected in ["first", "second"] {
+            let n = peer.recv(&mut buffer).unwrap();
+            assert_eq!(&buffer[..n], expected.as_bytes());
+        }
+        peer.set_nonblocking(true).unwrap();
+        assert!(peer.recv(&mut buffer).is_err());
+    }
+
+    #[test]
+    fn explicit_mode_sends_file_prefix_as_typed() {
+        let (peer, target) = peer();
+        let args: SendArgs = parse(&["udd", &target, "--quiet", "--mode", "text", "file", "x.bin"]);
+        assert_eq!(send(&args).unwrap(), ExitCode::SUCCESS);
+
+        let mut buffer = [0u8; 64];
+        let n = peer.recv(&mut buffer).unwrap();
+        assert_eq!(&buffer[..n], b"file x.bin");
+    }
+
+    #[test]
+    fn refused_subscription_fails_and_names_the_filter() {
+        let (peer, target) = peer();
+        let args: SendArgs = parse(&[
+            "udd", &target, "--quiet", "--mode", "mqtt", "--wait", "2s", "sub", "a/b,c/d",
+        ]);
+        let client = std::thread::spawn(move || send(&args));
+
+        let mut buffer = [0u8; 256];
+        let (n, from) = peer.recv_from(&mut buffer).unwrap();
+        let Ok(UdpFrame {
+            msg_id,
+            packet: Packet::Subscribe(subscribe),
+        }) = UdpFrame::decode(&buffer[..n])
+        else {
+            panic!("expected a Subscribe");
+        };
+        let ack = SubAck::new(vec![
+            SubAckReturnCode::SuccessQoS0,
+            SubAckReturnCode::Failure,
+        ]);
+        assert_eq!(
+            suback_errors(&subscribe.filters, &ack),
+            ["subscription refused: c/d"]
+        );
+        peer.send_to(&UdpFrame::new(msg_id, ack).encode(), from)
+            .unwrap();
+
+        assert_eq!(client.join().unwrap().unwrap(), ExitCode::FAILURE);
+    }
+}
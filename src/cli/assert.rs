@@ -0,0 +1,84 @@
+use super::output::Printer;
+use crate::args::AssertArgs;
+use crate::assert::Step;
+use std::io::{Error, ErrorKind};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+/// Run an `udd assert` sequence against TARGET. Sends go out in order; an expect waits up to its
+/// `within` (default `--wait`, else 1s) for a matching reply, printing any others on the way.
+/// The first expect that times out fails the run with a diagnostic.
+pub fn run(args: &AssertArgs) -> std::io::Result<ExitCode> {
+    let framing = &args.framing;
+    let printer = Printer::new(&args.output);
+    let socket = super::connect(&args.socket, &args.target)?;
+    let default_within = args.wait.unwrap_or(Duration::from_secs(1));
+
+    let mut buffer = [0u8; crate::utils::RECV_BUFFER];
+    for (i, step) in args.assertion.steps.iter().enumerate() {
+        let at_step =
+            |err: String| Error::new(ErrorKind::InvalidInput, format!("step {}: {}", i + 1, err));
+        match step {
+            Step::Send(command) => {
+                let (mode, command) = super::split_mode(args.payload.mode, command);
+                let (mode, payload) =
+                    crate::parse::parse_payload(mode, command).map_err(at_step)?;
+                let framed = framing.apply(&payload).map_err(at_step)?;
+                let sent = socket.send(&framed)?;
+                printer.sent(mode.short_label(), sent, &payload, None, &args.target);
+            }
+            Step::Expect { pattern, within } => {
+                let within = within.unwrap_or(default_within);
+                let start = Instant::now();
+                let mut others = 0;
+                let matched = loop {
+                    let remaining = (start + within).saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        break false;
+                    }
+                    socket.set_read_timeout(Some(remaining))?;
+                    match socket.recv(&mut buffer) {
+                        Ok(n) => {
+                            let data = framing.strip(&buffer[..n]);
+                            printer.received(args.payload.mode, data, None);
+                            if pattern.matches(data) {
+                                break true;
+                            }
+                            others += 1;
+                        }
+                        Err(err)
+                            if matches!(
+                                err.kind(),
+                                ErrorKind::WouldBlock | ErrorKind::TimedOut
+                            ) => {}
+                        Err(err) if err.kind() == ErrorKind::ConnectionRefused => {
+                            printer.error("ICMP: Connection refused (port unreachable)");
+                        }
+                        Err(err) => return Err(err),
+                    }
+                };
+
+                if !matched {
+                    printer.error(&format!(
+                        "assertion failed at step {}: expected {} within {:?}, got {} other repl{}",
+                        i + 1,
+                        pattern,
+                        within,
+                        others,
+                        if others == 1 { "y" } else { "ies" }
+                    ));
+                    return Ok(ExitCode::FAILURE);
+                }
+                printer.info(&format!(
+                    "✓ step {}: {} after {:?}",
+                    i + 1,
+                    pattern,
+                    start.elapsed()
+                ));
+            }
+        }
+    }
+
+    printer.info(&format!("all {} steps passed", args.assertion.steps.len()));
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,61 @@
+use super::output::Printer;
+use crate::args::BenchArgs;
+use std::io::{Error, ErrorKind};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+/// Send `--size`-byte datagrams for `--duration`, paced to `--pps` if given (otherwise as fast
+/// as the socket allows), then report the achieved rate. The payload is random bytes from the
+/// fuzz seed with the packet number in the first bytes, so a receiver can spot drops.
+pub fn run(args: &BenchArgs) -> std::io::Result<ExitCode> {
+    let framing = &args.framing;
+    let printer = Printer::new(&args.output);
+    let socket = super::connect(&args.socket, &args.target)?;
+
+    let mut payload = crate::utils::random_bytes(args.size, crate::parse::next_fuzz_seed());
+    let gap = args.pps.map(|pps| Duration::from_secs(1) / pps.max(1));
+    printer.info(&format!(
+        "bench: {} byte datagrams to {} for {:?} at {}",
+        args.size,
+        args.target,
+        args.duration,
+        args.pps
+            .map_or_else(|| "full speed".into(), |pps| format!("{} pps", pps))
+    ));
+
+    let start = Instant::now();
+    let mut packets = 0u64;
+    let mut bytes = 0u64;
+    let mut errors = 0u64;
+    while start.elapsed() < args.duration {
+        if let Some(gap) = gap {
+            let due = start + gap * u32::try_from(packets).unwrap_or(u32::MAX);
+            if due >= start + args.duration {
+                break;
+            }
+            let now = Instant::now();
+            if due > now {
+                std::thread::sleep(due - now);
+            }
+        }
+
+        let seq = packets.to_be_bytes();
+        let stamp = seq.len().min(payload.len());
+        payload[..stamp].copy_from_slice(&seq[..stamp]);
+        let framed = framing
+            .apply(&payload)
+            .map_err(|err| Error::new(ErrorKind::InvalidInput, err))?;
+
+        packets += 1;
+        match socket.send(&framed) {
+            Ok(sent) => bytes += sent as u64,
+            Err(_) => errors += 1,
+        }
+    }
+
+    printer.bench_summary(packets, bytes, errors, start.elapsed());
+    if errors == packets {
+        return Ok(ExitCode::FAILURE);
+    }
+    Ok(ExitCode::SUCCESS)
+}
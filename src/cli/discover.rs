@@ -0,0 +1,55 @@
+use super::output::Printer;
+use crate::args::ProbeArgs;
+use std::io::{Error, ErrorKind};
+use std::net::SocketAddr;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+/// Broadcast `probe` to the target and print the first reply from each responder until the
+/// wait runs out (`--wait`, default 2s). Fails if nobody answers.
+pub fn run(args: &ProbeArgs, mode: crate::InputMode, probe: &[u8]) -> std::io::Result<ExitCode> {
+    let target = crate::utils::resolve(&args.target)?;
+    let framing = &args.framing;
+    let printer = Printer::new(&args.output);
+    let socket = super::bind(&args.socket)?;
+    socket.set_broadcast(true)?;
+
+    let framed = framing
+        .apply(probe)
+        .map_err(|err| Error::new(ErrorKind::InvalidInput, err))?;
+    let sent = socket.send_to(&framed, target)?;
+    printer.sent(mode.short_label(), sent, probe, None, &args.target);
+
+    let wait = args.wait.unwrap_or(Duration::from_secs(2));
+    let deadline = Instant::now() + wait;
+    let mut responders: Vec<SocketAddr> = vec![];
+    let mut buffer = [0u8; crate::utils::RECV_BUFFER];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        socket.set_read_timeout(Some(remaining))?;
+        let (n, from) = match socket.recv_from(&mut buffer) {
+            Ok(received) => received,
+            Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => break,
+            // A stray ICMP error from one host must not end the scan for the others.
+            Err(err) if err.kind() == ErrorKind::ConnectionRefused => continue,
+            Err(err) => return Err(err),
+        };
+        if !responders.contains(&from) {
+            responders.push(from);
+            printer.received(mode, framing.strip(&buffer[..n]), Some(from));
+        }
+    }
+
+    if responders.is_empty() {
+        printer.error(&format!("no responders within {:?}", wait));
+        return Ok(ExitCode::FAILURE);
+    }
+    printer.info(&format!("{} responder(s):", responders.len()));
+    for responder in &responders {
+        printer.info(&format!("  {}", responder));
+    }
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,89 @@
+use super::output::Printer;
+use crate::framing::Framing;
+use crate::parse::next_msg_id;
+use mqtt::{Packet, Ping, UdpFrame};
+use std::io::{Error, ErrorKind};
+use std::net::UdpSocket;
+use std::time::{Duration, Instant};
+
+/// MQTT keep-alive for a CLI session, started by a sent Connect with a non-zero keep-alive.
+///
+/// Any sent packet counts as activity. A PINGREQ goes out once a full keep-alive period passes
+/// without one, and a PINGREQ still unanswered when the next one is due is reported.
+pub struct KeepAlive {
+    target: String,
+    period: Duration,
+    next_due: Instant,
+    outstanding: Option<u16>,
+}
+
+impl KeepAlive {
+    /// The keep-alive started by `sent`, if it is a Connect that asks for one.
+    pub fn from_sent(sent: &[u8], target: &str) -> Option<Self> {
+        match UdpFrame::decode(sent) {
+            Ok(UdpFrame {
+                packet: Packet::Connect(c),
+                ..
+            }) if c.keep_alive > 0 => {
+                let period = Duration::from_secs(u64::from(c.keep_alive));
+                Some(Self {
+                    target: target.into(),
+                    period,
+                    next_due: Instant::now() + period,
+                    outstanding: None,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Note a packet sent by the session itself.
+    pub fn sent(&mut self) {
+        self.next_due = Instant::now() + self.period;
+    }
+
+    /// Note a received datagram; a PINGRESP answers the outstanding PINGREQ.
+    pub fn received(&mut self, data: &[u8]) {
+        if let Ok(UdpFrame {
+            packet: Packet::Pong(_),
+            ..
+        }) = UdpFrame::decode(data)
+        {
+            self.outstanding = None;
+        }
+    }
+
+    /// When the next PINGREQ falls due if nothing else is sent.
+    pub fn next_due(&self) -> Instant {
+        self.next_due
+    }
+
+    /// Send a PINGREQ if one is due.
+    pub fn poll(
+        &mut self,
+        socket: &UdpSocket,
+        framing: &Framing,
+        printer: &Printer,
+    ) -> std::io::Result<()> {
+        if Instant::now() < self.next_due {
+            return Ok(());
+        }
+        if let Some(msg_id) = self.outstanding {
+            printer.warning(&format!(
+                "no PINGRESP to keep-alive ping #{} within {:?}",
+                msg_id, self.period
+            ));
+        }
+
+        let msg_id = next_msg_id();
+        let payload = UdpFrame::new(msg_id, Ping).encode();
+        let framed = framing
+            .apply(&payload)
+            .map_err(|err| Error::new(ErrorKind::InvalidInput, err))?;
+        let sent = socket.send(&framed)?;
+        printer.sent("PING", sent, &payload, Some("keep-alive"), &self.target);
+        self.outstanding = Some(msg_id);
+        self.sent();
+        Ok(())
+    }
+}
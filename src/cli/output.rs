@@ -0,0 +1,414 @@
+use crate::args::OutputArgs;
+use crate::tui::format;
+use crate::{DumpFormat, InputMode, OutputFormat, TextEncoding, json, utils};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, SystemTime};
+
+/// ANSI SGR codes for text output, matching the TUI's colors.
+const SENT: &str = "36";
+const RECEIVED: &str = "32";
+const RELAYED: &str = "35";
+const MQTT: &str = "1;33";
+const ERROR: &str = "31";
+const WARNING: &str = "33";
+const DIM: &str = "2";
+
+/// Prints CLI events either as the human-readable lines or as one JSON object per line.
+#[derive(Clone)]
+pub struct Printer {
+    format: OutputFormat,
+    encoding: TextEncoding,
+    dump: Option<DumpFormat>,
+    quiet: bool,
+    output_dir: Option<PathBuf>,
+    saved: Arc<AtomicU32>,
+    color_out: bool,
+    color_err: bool,
+}
+
+impl Printer {
+    pub fn new(args: &OutputArgs) -> Self {
+        Self {
+            format: args.output,
+            encoding: args.text_encoding,
+            dump: args.dump,
+            quiet: args.quiet,
+            output_dir: args.output_dir.clone(),
+            saved: Arc::default(),
+            color_out: args.color.enabled(&std::io::stdout()),
+            color_err: args.color.enabled(&std::io::stderr()),
+        }
+    }
+
+    fn paint(enabled: bool, code: &str, text: &str) -> String {
+        match enabled {
+            true => format!("\x1b[{}m{}\x1b[0m", code, text),
+            false => text.to_string(),
+        }
+    }
+
+    /// A decoded payload, highlighted when it is an MQTT packet.
+    fn display(&self, mode: InputMode, data: &[u8]) -> String {
+        let display = format::format_for_mode(mode, self.encoding, data);
+        match mqtt::UdpFrame::decode(data) {
+            Ok(_) => Self::paint(self.color_out, MQTT, &display),
+            Err(_) => display.into_owned(),
+        }
+    }
+
+    fn event(&self, kind: &str) -> json::Object {
+        json::Object::new()
+            .str("event", kind)
+            .str("ts", &utils::rfc3339(SystemTime::now()))
+    }
+
+    fn payload(object: json::Object, data: &[u8]) -> json::Object {
+        let hex: String = data.iter().map(|b| format!("{:02x}", b)).collect();
+        let object = object.num("bytes", data.len() as f64).str("hex", &hex);
+        match format::mqtt_json(data) {
+            Some(mqtt) => object.raw("mqtt", &mqtt),
+            None => object,
+        }
+    }
+
+    /// `label` is the input mode (or e.g. `FILE`), `origin` an optional source such as a path.
+    pub fn sent(&self, label: &str, sent: usize, data: &[u8], origin: Option<&str>, to: &str) {
+        if self.quiet {
+            return;
+        }
+        match self.format {
+            OutputFormat::Text => {
+                let line = match origin {
+                    Some(origin) => format!(
+                        "→ [{}] sent {} bytes from {} to {}",
+                        label, sent, origin, to
+                    ),
+                    None => format!("→ [{}] sent {} bytes to {}", label, sent, to),
+                };
+                println!("{}", Self::paint(self.color_out, SENT, &line));
+            }
+            OutputFormat::Json => {
+                let mut object = self
+                    .event("sent")
+                    .str("mode", label)
+                    .str("to", to)
+                    .num("sent", sent as f64);
+                if let Some(origin) = origin {
+                    object = object.str("origin", origin);
+                }
+                println!("{}", Self::payload(object, data).finish());
+            }
+        }
+    }
+
+    /// Print a received datagram, and save it when `--output-dir` is set.
+    pub fn received(&self, mode: InputMode, data: &[u8], from: Option<SocketAddr>) {
+        if let Err(err) = self.save(data) {
+            self.error(&format!("could not save received datagram: {}", err));
+        }
+
+        match self.format {
+            OutputFormat::Text if self.dump == Some(DumpFormat::Hex) => {
+                let header = match from {
+                    Some(from) => format!("← {} bytes from {}:", data.len(), from),
+                    None => format!("← {} bytes:", data.len()),
+                };
+                println!("{}", Self::paint(self.color_out, RECEIVED, &header));
+                for line in utils::hexdump(data) {
+                    println!("    {}", Self::paint(self.color_out, DIM, &line));
+                }
+            }
+            OutputFormat::Text => {
+                let header = match from {
+                    Some(from) => format!("← {} bytes from {}:", data.len(), from),
+                    None => format!("← {} bytes:", data.len()),
+                };
+                println!(
+                    "{} {}",
+                    Self::paint(self.color_out, RECEIVED, &header),
+                    self.display(mode, data)
+                );
+            }
+            OutputFormat::Json => {
+                let mut object = self.event("received");
+                if let Some(from) = from {
+                    object = object.str("from", &from.to_string());
+                }
+                println!("{}", Self::payload(object, data).finish());
+            }
+        }
+    }
+
+    /// Write `data` to `recv-<n>-<HHMMSS.mmm>.bin` in the output directory.
+    fn save(&self, data: &[u8]) -> std::io::Result<()> {
+        let Some(dir) = &self.output_dir else {
+            return Ok(());
+        };
+        std::fs::create_dir_all(dir)?;
+        let n = self.saved.fetch_add(1, Ordering::Relaxed) + 1;
+        let stamp = utils::timestamp(SystemTime::now()).replace(':', "");
+        std::fs::write(dir.join(format!("recv-{:04}-{}.bin", n, stamp)), data)
+    }
+
+    /// A datagram forwarded by `udd relay`.
+    pub fn relayed(&self, mode: InputMode, data: &[u8], from: SocketAddr, to: SocketAddr) {
+        match self.format {
+            OutputFormat::Text => {
+                let header = format!("⇄ {} bytes {} -> {}:", data.len(), from, to);
+                println!(
+                    "{} {}",
+                    Self::paint(self.color_out, RELAYED, &header),
+                    self.display(mode, data)
+                );
+            }
+            OutputFormat::Json => {
+                let object = self
+                    .event("relayed")
+                    .str("from", &from.to_string())
+                    .str("to", &to.to_string());
+                println!("{}", Self::payload(object, data).finish());
+            }
+        }
+    }
+
+    /// One row of a `udd scan` table.
+    pub fn scanned(
+        &self,
+        port: u16,
+        state: super::scan::PortState,
+        mode: InputMode,
+        reply: Option<&[u8]>,
+    ) {
+        match self.format {
+            OutputFormat::Text => match reply {
+                Some(reply) => println!(
+                    "{:>5}  {:<8}  {}",
+                    port,
+                    state.label(),
+                    format::format_for_mode(mode, self.encoding, reply)
+                ),
+                None => println!("{:>5}  {}", port, state.label()),
+            },
+            OutputFormat::Json => {
+                let object = self
+                    .event("scanned")
+                    .num("port", port)
+                    .str("state", state.label());
+                let object = match reply {
+                    Some(reply) => Self::payload(object, reply),
+                    None => object,
+                };
+                println!("{}", object.finish());
+            }
+        }
+    }
+
+    /// One `udd trace` hop.
+    pub fn hop(&self, ttl: u32, hop: &super::trace::Hop) {
+        use super::trace::Hop;
+
+        match (self.format, hop) {
+            (OutputFormat::Text, Hop::Router(addr, rtt) | Hop::Target(addr, rtt)) => {
+                println!("{:>2}  {:<15}  {}", ttl, addr, millis(*rtt))
+            }
+            (OutputFormat::Text, Hop::Silent) => println!("{:>2}  *", ttl),
+            (OutputFormat::Json, Hop::Router(addr, rtt) | Hop::Target(addr, rtt)) => println!(
+                "{}",
+                self.event("hop")
+                    .num("ttl", ttl)
+                    .str("addr", &addr.to_string())
+                    .num("rtt_us", rtt.as_micros() as f64)
+                    .bool("target", matches!(hop, Hop::Target(..)))
+                    .finish()
+            ),
+            (OutputFormat::Json, Hop::Silent) => println!(
+                "{}",
+                self.event("hop")
+                    .num("ttl", ttl)
+                    .bool("silent", true)
+                    .finish()
+            ),
+        }
+    }
+
+    /// Time from a send to the reply that answered it, with microsecond resolution.
+    pub fn rtt(&self, rtt: Duration) {
+        if self.quiet {
+            return;
+        }
+        match self.format {
+            OutputFormat::Text => {
+                let line = format!("  rtt {}", millis(rtt));
+                println!("{}", Self::paint(self.color_out, DIM, &line));
+            }
+            OutputFormat::Json => println!(
+                "{}",
+                self.event("rtt")
+                    .num("rtt_us", rtt.as_micros() as f64)
+                    .finish()
+            ),
+        }
+    }
+
+    /// One `udd ping` probe: its echo and RTT, or `None` when it was lost.
+    pub fn probe(&self, seq: u32, bytes: usize, rtt: Option<Duration>) {
+        match (self.format, rtt) {
+            (OutputFormat::Text, Some(rtt)) => {
+                let line = format!("← {} bytes: seq={} rtt {}", bytes, seq, millis(rtt));
+                println!("{}", Self::paint(self.color_out, RECEIVED, &line));
+            }
+            (OutputFormat::Text, None) => {
+                let line = format!("✗ seq={}: no reply", seq);
+                eprintln!("{}", Self::paint(self.color_err, ERROR, &line));
+            }
+            (OutputFormat::Json, Some(rtt)) => println!(
+                "{}",
+                self.event("probe")
+                    .num("seq", seq)
+                    .num("bytes", bytes as f64)
+                    .num("rtt_us", rtt.as_micros() as f64)
+                    .finish()
+            ),
+            (OutputFormat::Json, None) => println!(
+                "{}",
+                self.event("probe")
+                    .num("seq", seq)
+                    .bool("lost", true)
+                    .finish()
+            ),
+        }
+    }
+
+    /// Loss and min/avg/max/stddev RTT over a `udd ping` run.
+    pub fn ping_summary(&self, target: &str, sent: u32, rtts: &[Duration]) {
+        let received = rtts.len();
+        let loss = match sent {
+            0 => 0.0,
+            _ => 100.0 * (sent as f64 - received as f64) / sent as f64,
+        };
+        let micros: Vec<f64> = rtts.iter().map(|rtt| rtt.as_micros() as f64).collect();
+        let min = micros.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = micros.iter().copied().fold(0.0, f64::max);
+        let avg = micros.iter().sum::<f64>() / received.max(1) as f64;
+        let variance =
+            micros.iter().map(|us| (us - avg).powi(2)).sum::<f64>() / received.max(1) as f64;
+        let stddev = variance.sqrt();
+
+        match self.format {
+            OutputFormat::Text => {
+                println!("--- {} ping statistics ---", target);
+                println!(
+                    "{} probes sent, {} received, {:.1}% loss",
+                    sent, received, loss
+                );
+                if received > 0 {
+                    println!(
+                        "rtt min/avg/max/stddev = {:.3}/{:.3}/{:.3}/{:.3} ms",
+                        min / 1000.0,
+                        avg / 1000.0,
+                        max / 1000.0,
+                        stddev / 1000.0
+                    );
+                }
+            }
+            OutputFormat::Json => {
+                let mut object = self
+                    .event("ping_summary")
+                    .str("to", target)
+                    .num("sent", sent)
+                    .num("received", received as f64)
+                    .num("loss_pct", loss);
+                if received > 0 {
+                    object = object
+                        .num("min_us", min)
+                        .num("avg_us", avg)
+                        .num("max_us", max)
+                        .num("stddev_us", stddev);
+                }
+                println!("{}", object.finish());
+            }
+        }
+    }
+
+    /// Totals for a `udd bench` run.
+    pub fn bench_summary(&self, packets: u64, bytes: u64, errors: u64, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64().max(f64::EPSILON);
+        let sent = packets - errors;
+        let pps = sent as f64 / secs;
+        let bits_per_sec = bytes as f64 * 8.0 / secs;
+
+        match self.format {
+            OutputFormat::Text => {
+                println!(
+                    "{} packets in {:.2}s: {} sent, {} send errors",
+                    packets, secs, sent, errors
+                );
+                println!(
+                    "{:.0} pps, {:.2} Mbit/s ({} bytes)",
+                    pps,
+                    bits_per_sec / 1e6,
+                    bytes
+                );
+            }
+            OutputFormat::Json => println!(
+                "{}",
+                self.event("bench_summary")
+                    .num("packets", packets as f64)
+                    .num("sent", sent as f64)
+                    .num("errors", errors as f64)
+                    .num("bytes", bytes as f64)
+                    .num("seconds", secs)
+                    .num("pps", pps)
+                    .num("bits_per_sec", bits_per_sec)
+                    .finish()
+            ),
+        }
+    }
+
+    pub fn error(&self, message: &str) {
+        match self.format {
+            OutputFormat::Text => {
+                let line = format!("✗ {}", message);
+                eprintln!("{}", Self::paint(self.color_err, ERROR, &line));
+            }
+            OutputFormat::Json => {
+                println!("{}", self.event("error").str("message", message).finish())
+            }
+        }
+    }
+
+    pub fn warning(&self, message: &str) {
+        if self.quiet {
+            return;
+        }
+        match self.format {
+            OutputFormat::Text => {
+                let line = format!("⚠ {}", message);
+                eprintln!("{}", Self::paint(self.color_err, WARNING, &line));
+            }
+            OutputFormat::Json => {
+                println!("{}", self.event("warning").str("message", message).finish())
+            }
+        }
+    }
+
+    pub fn info(&self, message: &str) {
+        if self.quiet {
+            return;
+        }
+        match self.format {
+            OutputFormat::Text => println!("{}", message),
+            OutputFormat::Json => {
+                println!("{}", self.event("info").str("message", message).finish())
+            }
+        }
+    }
+}
+
+/// Milliseconds with microsecond resolution, e.g. `0.250 ms`.
+fn millis(duration: Duration) -> String {
+    format!("{:.3} ms", duration.as_micros() as f64 / 1000.0)
+}
@@ -0,0 +1,85 @@
+use super::output::Printer;
+use crate::args::PingArgs;
+use std::io::ErrorKind;
+use std::process::ExitCode;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Probe prefix; the big-endian sequence number follows. Replies must echo the probe.
+const MAGIC: &[u8; 4] = b"UDDP";
+
+/// Send `--count` sequence-stamped probes, one per `--interval` (default 1s), and report the
+/// loss and RTT spread. Each probe waits for its echo until the next one is due; anything
+/// arriving later counts as lost. Fails only if every probe was lost.
+pub fn run(args: &PingArgs, printer: &Printer) -> std::io::Result<ExitCode> {
+    let interval = args.interval.unwrap_or(Duration::from_secs(1));
+    let framing = &args.framing;
+    let socket = super::connect(&args.socket, &args.target)?;
+    let mut stats = args
+        .stats_csv
+        .as_deref()
+        .map(super::stats::StatsCsv::open)
+        .transpose()?;
+
+    let mut rtts = vec![];
+    let mut sent = 0u32;
+    let mut buffer = [0u8; crate::utils::RECV_BUFFER];
+    while args.count.is_none_or(|count| u64::from(sent) < count) {
+        let seq = sent;
+        sent += 1;
+
+        let probe = [MAGIC.as_slice(), &seq.to_be_bytes()].concat();
+        let framed = framing
+            .apply(&probe)
+            .map_err(|err| std::io::Error::new(ErrorKind::InvalidInput, err))?;
+        let sent_time = SystemTime::now();
+        let sent_at = Instant::now();
+        let deadline = sent_at + interval;
+        if let Err(err) = socket.send(&framed) {
+            printer.error(&format!("seq={}: {}", seq, err));
+            if let Some(stats) = &mut stats {
+                stats.row(seq.into(), sent_time, None, "error")?;
+            }
+            std::thread::sleep(deadline.saturating_duration_since(Instant::now()));
+            continue;
+        }
+
+        let mut rtt = None;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+            socket.set_read_timeout(Some(remaining))?;
+            let n = match socket.recv(&mut buffer) {
+                Ok(n) => n,
+                Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    break;
+                }
+                // ICMP unreachable for this probe; keep pacing so the loss is counted.
+                Err(err) if err.kind() == ErrorKind::ConnectionRefused => continue,
+                Err(err) => return Err(err),
+            };
+            let elapsed = sent_at.elapsed();
+            if rtt.is_none() && framing.strip(&buffer[..n]) == probe.as_slice() {
+                rtt = Some((elapsed, n));
+                printer.probe(seq, n, Some(elapsed));
+                // Stay in the loop so the probes keep their pace.
+            }
+        }
+
+        match rtt {
+            Some((rtt, _)) => rtts.push(rtt),
+            None => printer.probe(seq, 0, None),
+        }
+        if let Some(stats) = &mut stats {
+            let outcome = if rtt.is_some() { "ok" } else { "lost" };
+            stats.row(seq.into(), sent_time, rtt, outcome)?;
+        }
+    }
+
+    printer.ping_summary(&args.target, sent, &rtts);
+    if rtts.is_empty() && sent > 0 {
+        return Ok(ExitCode::FAILURE);
+    }
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,55 @@
+use super::output::Printer;
+use crate::args::RawArgs;
+use std::io::{Error, ErrorKind, Read, Write};
+
+/// Netcat-style passthrough: every read from stdin is sent as one datagram and every reply is
+/// written to stdout byte for byte. After stdin closes, replies are still copied for `--wait`.
+/// Nothing but the payloads reaches stdout; errors go to stderr.
+pub fn run(args: &RawArgs) -> std::io::Result<()> {
+    let framing = &args.framing;
+    let socket = super::connect(&args.socket, &args.target)?;
+
+    let receiver = socket.try_clone()?;
+    let strip = framing.clone();
+    let printer = Printer::new(&args.output);
+    std::thread::spawn(move || {
+        let mut buffer = [0u8; crate::utils::RECV_BUFFER];
+        let mut stdout = std::io::stdout().lock();
+        loop {
+            let result = receiver.recv(&mut buffer).and_then(|n| {
+                stdout.write_all(strip.strip(&buffer[..n]))?;
+                stdout.flush()
+            });
+            match result {
+                Ok(()) => {}
+                Err(err) if err.kind() == ErrorKind::ConnectionRefused => {
+                    printer.error("ICMP: Connection refused (port unreachable)");
+                }
+                Err(err) => {
+                    printer.error(&err.to_string());
+                    return;
+                }
+            }
+        }
+    });
+
+    let mut stdin = std::io::stdin().lock();
+    let mut buffer = [0u8; crate::utils::MAX_DATAGRAM];
+    loop {
+        let n = match stdin.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        let framed = framing
+            .apply(&buffer[..n])
+            .map_err(|err| Error::new(ErrorKind::InvalidInput, err))?;
+        socket.send(&framed)?;
+    }
+
+    if let Some(wait) = args.wait {
+        std::thread::sleep(wait);
+    }
+    Ok(())
+}
@@ -0,0 +1,142 @@
+use super::output::Printer;
+use crate::args::RelayArgs;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::io::{Error, ErrorKind};
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// How long a client's upstream socket is kept with no traffic either way.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A client's socket towards the target, shared with the thread carrying replies back.
+struct Upstream {
+    socket: UdpSocket,
+    /// Milliseconds after the relay started that the client or target last sent something.
+    last_seen: Arc<AtomicU64>,
+    /// Set by the return path when it gives up, so the next datagram opens a fresh socket.
+    closed: Arc<AtomicBool>,
+}
+
+/// Forward datagrams between clients of `listen` and the target, printing each one.
+///
+/// Every client gets its own upstream socket, so the target's replies go back to the client
+/// that caused them. A socket is closed after [`IDLE_TIMEOUT`] without traffic. Datagrams are
+/// passed through untouched.
+pub fn run(args: &RelayArgs) -> std::io::Result<()> {
+    if args.socket.source_port.is_some() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "--source-port can't be used with relay: every client gets its own upstream socket",
+        ));
+    }
+    let target = crate::utils::resolve(&args.target)?;
+    let downstream = crate::utils::bind_udp(
+        &args.listen,
+        args.socket.reuse.reuse_addr,
+        args.socket.reuse.reuse_port,
+    )?;
+    let printer = Printer::new(&args.output);
+    printer.info(&format!(
+        "relaying {} <-> {}",
+        downstream.local_addr()?,
+        target
+    ));
+
+    let start = Instant::now();
+    let mut upstreams: HashMap<SocketAddr, Upstream> = HashMap::new();
+    let mut buffer = [0u8; crate::utils::RECV_BUFFER];
+    loop {
+        let (n, client) = downstream.recv_from(&mut buffer)?;
+        upstreams.retain(|_, upstream| !upstream.closed.load(Ordering::Relaxed));
+        let upstream = match upstreams.entry(client) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let socket = super::bind(&args.socket)?;
+                socket.connect(target)?;
+                socket.set_read_timeout(Some(IDLE_TIMEOUT / 4))?;
+                let upstream = Upstream {
+                    socket,
+                    last_seen: Arc::new(AtomicU64::new(0)),
+                    closed: Arc::new(AtomicBool::new(false)),
+                };
+                spawn_return_path(
+                    &upstream,
+                    downstream.try_clone()?,
+                    client,
+                    target,
+                    start,
+                    printer.clone(),
+                    args.mode,
+                )?;
+                entry.insert(upstream)
+            }
+        };
+        upstream
+            .last_seen
+            .store(start.elapsed().as_millis() as u64, Ordering::Relaxed);
+
+        let data = &buffer[..n];
+        printer.relayed(args.mode, data, client, target);
+        if let Err(err) = upstream.socket.send(data) {
+            printer.error(&format!("{} -> {}: {}", client, target, err));
+        }
+    }
+}
+
+/// Carry the target's replies back to `client` until the upstream socket fails or has been
+/// idle for [`IDLE_TIMEOUT`].
+fn spawn_return_path(
+    upstream: &Upstream,
+    downstream: UdpSocket,
+    client: SocketAddr,
+    target: SocketAddr,
+    start: Instant,
+    printer: Printer,
+    mode: crate::InputMode,
+) -> std::io::Result<()> {
+    let socket = upstream.socket.try_clone()?;
+    let last_seen = upstream.last_seen.clone();
+    let closed = upstream.closed.clone();
+    std::thread::spawn(move || {
+        let mut buffer = [0u8; crate::utils::RECV_BUFFER];
+        loop {
+            let n = match socket.recv(&mut buffer) {
+                Ok(n) => n,
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    let idle = (start.elapsed().as_millis() as u64)
+                        .saturating_sub(last_seen.load(Ordering::Relaxed));
+                    if Duration::from_millis(idle) >= IDLE_TIMEOUT {
+                        closed.store(true, Ordering::Relaxed);
+                        return;
+                    }
+                    continue;
+                }
+                // ICMP from the target; the client retrying will show the same error again.
+                Err(err) if err.kind() == std::io::ErrorKind::ConnectionRefused => {
+                    printer.error(&format!("{} -> {}: {}", client, target, err));
+                    continue;
+                }
+                Err(err) => {
+                    printer.error(&format!("{} <- {}: {}", client, target, err));
+                    closed.store(true, Ordering::Relaxed);
+                    return;
+                }
+            };
+            last_seen.store(start.elapsed().as_millis() as u64, Ordering::Relaxed);
+            let data = &buffer[..n];
+            printer.relayed(mode, data, target, client);
+            if let Err(err) = downstream.send_to(data, client) {
+                printer.error(&format!("{} <- {}: {}", client, target, err));
+            }
+        }
+    });
+    Ok(())
+}
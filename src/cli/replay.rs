@@ -0,0 +1,91 @@
+use super::output::Printer;
+use crate::args::ReplayArgs;
+use crate::{json, utils};
+use std::io::{BufRead, BufReader, Error, ErrorKind};
+use std::process::ExitCode;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Re-send every `sent` event of a `--output json` capture to TARGET, keeping the original gaps
+/// between them (divided by `--speed`). Replies are printed as they arrive, and for `--wait`
+/// after the last send.
+pub fn run(args: &ReplayArgs) -> std::io::Result<ExitCode> {
+    let path = &args.capture;
+    if !(args.speed > 0.0 && args.speed.is_finite()) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "--speed must be a positive number",
+        ));
+    }
+    let sends = load(path)?;
+    if sends.is_empty() {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("{}: no sent events to replay", path.display()),
+        ));
+    }
+
+    let framing = &args.framing;
+    let printer = Printer::new(&args.output);
+    let socket = super::connect(&args.socket, &args.target)?;
+    printer.info(&format!(
+        "replaying {} datagram(s) from {} at {}x",
+        sends.len(),
+        path.display(),
+        args.speed
+    ));
+
+    let start = Instant::now();
+    let first = sends[0].0;
+    let mut buffer = [0u8; crate::utils::RECV_BUFFER];
+    for (at, payload) in &sends {
+        let offset = at.duration_since(first).unwrap_or_default();
+        // A tiny --speed can stretch a gap past what a Duration or Instant can hold.
+        let due = Duration::try_from_secs_f64(offset.as_secs_f64() / args.speed)
+            .ok()
+            .and_then(|gap| start.checked_add(gap))
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("--speed {} stretches the capture too far", args.speed),
+                )
+            })?;
+        super::print_replies_until(&socket, framing, &printer, args.mode, due, &mut buffer)?;
+
+        let framed = framing
+            .apply(payload)
+            .map_err(|err| Error::new(ErrorKind::InvalidInput, err))?;
+        let sent = socket.send(&framed)?;
+        printer.sent("REPLAY", sent, payload, None, &args.target);
+    }
+
+    let deadline = Instant::now() + args.wait.unwrap_or_default();
+    super::print_replies_until(&socket, framing, &printer, args.mode, deadline, &mut buffer)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// The send time and payload of each `sent` event, in file order.
+fn load(path: &std::path::Path) -> std::io::Result<Vec<(SystemTime, Vec<u8>)>> {
+    let file = std::fs::File::open(path)
+        .map_err(|err| Error::new(err.kind(), format!("{}: {}", path.display(), err)))?;
+
+    let mut sends = vec![];
+    for (n, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if json::str_field(&line, "event").as_deref() != Some("sent") {
+            continue;
+        }
+        let at_line = |err: String| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("{}:{}: {}", path.display(), n + 1, err),
+            )
+        };
+        let ts = json::str_field(&line, "ts").ok_or_else(|| at_line("missing ts".into()))?;
+        let hex = json::str_field(&line, "hex").ok_or_else(|| at_line("missing hex".into()))?;
+        sends.push((
+            utils::parse_rfc3339(&ts).map_err(at_line)?,
+            utils::parse_hex(&hex).map_err(at_line)?,
+        ));
+    }
+    Ok(sends)
+}
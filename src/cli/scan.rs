@@ -0,0 +1,152 @@
+use super::output::Printer;
+use crate::InputMode;
+use crate::args::ProbeArgs;
+use std::io::{Error, ErrorKind};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+/// Ports probed at once; each needs its own connected socket to see ICMP errors.
+const BATCH: usize = 256;
+
+/// How a port answered the probe.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PortState {
+    /// A datagram came back.
+    Open,
+    /// ICMP port unreachable.
+    Closed,
+    /// Silence: dropped by a firewall, or a service that ignored the probe.
+    Filtered,
+}
+
+impl PortState {
+    pub fn label(self) -> &'static str {
+        match self {
+            PortState::Open => "open",
+            PortState::Closed => "closed",
+            PortState::Filtered => "filtered",
+        }
+    }
+}
+
+/// Send `probe` to every port of the target host and classify each by its reply, ICMP error or
+/// silence within `--wait` (default 1s). The port in TARGET, if any, is ignored.
+pub fn run(
+    args: &ProbeArgs,
+    ports: &[u16],
+    mode: InputMode,
+    probe: &[u8],
+) -> std::io::Result<ExitCode> {
+    if args.socket.source_port.is_some() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "--source-port can't be used with scan: every port is probed from its own socket",
+        ));
+    }
+    let host = match args.target.rsplit_once(':') {
+        Some((host, port)) if port.parse::<u16>().is_ok() => host,
+        _ => args.target.as_str(),
+    };
+    let mut addr = crate::utils::resolve(&format!("{}:0", host))?;
+    let framing = &args.framing;
+    let printer = Printer::new(&args.output);
+    let framed = framing
+        .apply(probe)
+        .map_err(|err| Error::new(ErrorKind::InvalidInput, err))?;
+    let wait = args.wait.unwrap_or(Duration::from_secs(1));
+
+    printer.info(&format!(
+        "scanning {} port(s) on {}",
+        ports.len(),
+        addr.ip()
+    ));
+    let mut open = 0;
+    let mut buffer = [0u8; crate::utils::RECV_BUFFER];
+    for batch in ports.chunks(BATCH) {
+        let mut probes = vec![];
+        for &port in batch {
+            addr.set_port(port);
+            let socket = super::bind(&args.socket)?;
+            socket.connect(addr)?;
+            socket.set_nonblocking(true)?;
+            let state = match socket.send(&framed) {
+                Ok(_) => None,
+                Err(err) if err.kind() == ErrorKind::ConnectionRefused => Some(PortState::Closed),
+                Err(err) => return Err(err),
+            };
+            probes.push((port, socket, state, None));
+        }
+
+        let deadline = Instant::now() + wait;
+        while Instant::now() < deadline && probes.iter().any(|(_, _, state, _)| state.is_none()) {
+            for (_, socket, state, reply) in probes.iter_mut().filter(|p| p.2.is_none()) {
+                match socket.recv(&mut buffer) {
+                    Ok(n) => {
+                        *state = Some(PortState::Open);
+                        *reply = Some(framing.strip(&buffer[..n]).to_vec());
+                    }
+                    Err(err) if err.kind() == ErrorKind::ConnectionRefused => {
+                        *state = Some(PortState::Closed);
+                    }
+                    Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+                    Err(err) => return Err(err),
+                }
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        for (port, _, state, reply) in probes {
+            let state = state.unwrap_or(PortState::Filtered);
+            if state == PortState::Open {
+                open += 1;
+            }
+            printer.scanned(port, state, mode, reply.as_deref());
+        }
+    }
+
+    printer.info(&format!("{} of {} port(s) open", open, ports.len()));
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::ScanArgs;
+    use clap::{Args, FromArgMatches};
+    use std::net::UdpSocket;
+
+    #[test]
+    fn source_port_is_refused_before_probing() {
+        let first = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let second = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let ports = format!(
+            "{},{}",
+            first.local_addr().unwrap().port(),
+            second.local_addr().unwrap().port()
+        );
+        let command = ScanArgs::augment_args(clap::Command::new("scan"));
+        let matches = command.get_matches_from([
+            "scan",
+            "127.0.0.1",
+            "--ports",
+            &ports,
+            "--source-port",
+            "40123",
+            "--quiet",
+        ]);
+        let args = ScanArgs::from_arg_matches(&matches).unwrap();
+
+        let err = run(&args.probe, &args.ports, InputMode::Text, b"probe").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert!(
+            err.to_string()
+                .starts_with("--source-port can't be used with scan")
+        );
+
+        let mut buffer = [0u8; 16];
+        for peer in [&first, &second] {
+            peer.set_nonblocking(true).unwrap();
+            assert!(peer.recv(&mut buffer).is_err());
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use super::keepalive::KeepAlive;
+use super::output::Printer;
+use crate::InputMode;
+use crate::framing::Framing;
+use mqtt::{ConnectReturnCode, Packet, UdpFrame};
+use std::io::ErrorKind;
+use std::net::UdpSocket;
+use std::time::{Duration, Instant};
+
+/// Replies handled once the deadline has passed, so a target that never stops sending can't
+/// hold a script up.
+const MAX_DRAIN: usize = 256;
+
+/// MQTT connection state as seen from the packets a `--script` sends and receives.
+enum Connection {
+    Disconnected,
+    /// Connect sent, no ConnAck yet.
+    Connecting,
+    Connected,
+}
+
+/// The MQTT session a `--script` drives: whether it is connected, what it subscribed to and
+/// its keep-alive. Publish and Subscribe are refused until a Connect has been sent.
+pub struct Session {
+    target: String,
+    connection: Connection,
+    subscriptions: Vec<String>,
+    keepalive: Option<KeepAlive>,
+}
+
+impl Session {
+    pub fn new(target: &str) -> Self {
+        Self {
+            target: target.into(),
+            connection: Connection::Disconnected,
+            subscriptions: vec![],
+            keepalive: None,
+        }
+    }
+
+    /// Why `payload` can't be sent in the current state, if it can't.
+    pub fn check(&self, payload: &[u8]) -> Result<(), String> {
+        let Ok(frame) = UdpFrame::decode(payload) else {
+            return Ok(());
+        };
+        match (&frame.packet, &self.connection) {
+            (Packet::Publish(_) | Packet::Subscribe(_), Connection::Disconnected) => {
+                Err("not connected; send `mqtt connect <client_id>` first".into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Track a packet the script sent.
+    pub fn sent(&mut self, payload: &[u8]) {
+        if let Some(keepalive) = &mut self.keepalive {
+            keepalive.sent();
+        }
+        let Ok(frame) = UdpFrame::decode(payload) else {
+            return;
+        };
+        match &frame.packet {
+            Packet::Connect(_) => {
+                self.connection = Connection::Connecting;
+                self.subscriptions.clear();
+                self.keepalive = KeepAlive::from_sent(payload, &self.target);
+            }
+            Packet::Subscribe(s) => {
+                for filter in &s.filters {
+                    if !self.subscriptions.contains(&filter.topic) {
+                        self.subscriptions.push(filter.topic.clone());
+                    }
+                }
+            }
+            Packet::Disconnect(_) => {
+                self.connection = Connection::Disconnected;
+                self.keepalive = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// Track a reply: a ConnAck settles the connection and a PINGRESP feeds the keep-alive.
+    pub fn received(&mut self, data: &[u8], printer: &Printer) {
+        if let Some(keepalive) = &mut self.keepalive {
+            keepalive.received(data);
+        }
+        let Ok(UdpFrame {
+            packet: Packet::ConnAck(ack),
+            ..
+        }) = UdpFrame::decode(data)
+        else {
+            return;
+        };
+        if matches!(ack.return_code, ConnectReturnCode::Accepted) {
+            self.connection = Connection::Connected;
+        } else {
+            printer.error(&format!("connection refused: {:?}", ack.return_code));
+            self.connection = Connection::Disconnected;
+            self.keepalive = None;
+        }
+    }
+
+    /// Wait for `duration` (zero just drains what has arrived, up to [`MAX_DRAIN`] replies),
+    /// printing replies such as incoming PUBLISHes and sending keep-alive PINGREQs as they fall due.
+    pub fn idle(
+        &mut self,
+        socket: &UdpSocket,
+        framing: &Framing,
+        printer: &Printer,
+        mode: InputMode,
+        duration: Duration,
+    ) -> std::io::Result<()> {
+        let deadline = Instant::now() + duration;
+        let mut buffer = [0u8; crate::utils::RECV_BUFFER];
+        let mut drained = 0;
+        loop {
+            if let Some(keepalive) = &mut self.keepalive {
+                keepalive.poll(socket, framing, printer)?;
+            }
+
+            let now = Instant::now();
+            let until = match &self.keepalive {
+                Some(keepalive) => deadline.min(keepalive.next_due()),
+                None => deadline,
+            };
+            let timeout = until.saturating_duration_since(now);
+            socket.set_read_timeout(Some(timeout.max(Duration::from_millis(1))))?;
+            match socket.recv(&mut buffer) {
+                Ok(n) => {
+                    let data = framing.strip(&buffer[..n]);
+                    printer.received(mode, data, None);
+                    self.received(data, printer);
+                    if Instant::now() < deadline {
+                        continue;
+                    }
+                    drained += 1;
+                    if drained < MAX_DRAIN {
+                        continue;
+                    }
+                    return Ok(());
+                }
+                Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+                Err(err) if err.kind() == ErrorKind::ConnectionRefused => {
+                    printer.error("ICMP: Connection refused (port unreachable)");
+                }
+                Err(err) => return Err(err),
+            }
+            if Instant::now() >= deadline {
+                return Ok(());
+            }
+        }
+    }
+
+    /// One-line summary for the end of a script.
+    pub fn summary(&self) -> Option<String> {
+        let state = match self.connection {
+            Connection::Disconnected if self.subscriptions.is_empty() => return None,
+            Connection::Disconnected => "disconnected",
+            Connection::Connecting => "connecting (no CONNACK)",
+            Connection::Connected => "connected",
+        };
+        match self.subscriptions.is_empty() {
+            true => Some(format!("session {}", state)),
+            false => Some(format!(
+                "session {}, subscribed to {}",
+                state,
+                self.subscriptions.join(", ")
+            )),
+        }
+    }
+}
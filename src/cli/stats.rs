@@ -0,0 +1,48 @@
+use crate::utils;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+/// Per-probe rows for `--stats-csv`, appended so long runs can be graphed across restarts.
+pub struct StatsCsv {
+    file: File,
+}
+
+impl StatsCsv {
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        if file.metadata()?.len() == 0 {
+            writeln!(file, "seq,send_time,recv_time,rtt_us,bytes,outcome")?;
+        }
+        Ok(Self { file })
+    }
+
+    /// `answer` is the reply's round trip and size; `outcome` is e.g. `ok`, `lost` or `error`.
+    pub fn row(
+        &mut self,
+        seq: u64,
+        sent: SystemTime,
+        answer: Option<(Duration, usize)>,
+        outcome: &str,
+    ) -> std::io::Result<()> {
+        let (recv_time, rtt, bytes) = match answer {
+            Some((rtt, bytes)) => (
+                utils::rfc3339(sent + rtt),
+                rtt.as_micros().to_string(),
+                bytes.to_string(),
+            ),
+            None => (String::new(), String::new(), String::new()),
+        };
+        writeln!(
+            self.file,
+            "{},{},{},{},{},{}",
+            seq,
+            utils::rfc3339(sent),
+            recv_time,
+            rtt,
+            bytes,
+            outcome
+        )
+    }
+}
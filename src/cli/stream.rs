@@ -0,0 +1,79 @@
+use super::output::Printer;
+use crate::args::StreamArgs;
+use std::io::{Error, ErrorKind, Read};
+use std::process::ExitCode;
+
+/// Send one datagram per stdin record, as records arrive. Records are split on `--delimiter`
+/// (a newline by default, which also drops a trailing `\r`) and interpreted like piped stdin
+/// in the selected mode. Empty records are skipped; a bad record is reported and skipped.
+pub fn run(args: &StreamArgs) -> std::io::Result<ExitCode> {
+    let delimiter = crate::utils::parse_text_with_escapes(&args.delimiter);
+    if delimiter.is_empty() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "--delimiter must not be empty",
+        ));
+    }
+
+    let framing = &args.framing;
+    let printer = Printer::new(&args.output);
+    let socket = super::connect(&args.socket, &args.target)?;
+
+    let mut status = ExitCode::SUCCESS;
+    let mut record = 0;
+    let mut send = |data: &[u8]| {
+        let data = match delimiter.as_slice() {
+            b"\n" => data.strip_suffix(b"\r").unwrap_or(data),
+            _ => data,
+        };
+        if data.is_empty() {
+            return;
+        }
+        if record > 0
+            && let Some(interval) = args.interval
+        {
+            std::thread::sleep(interval);
+        }
+        record += 1;
+
+        let result =
+            super::payload_from_bytes(args.mode, data.to_vec()).and_then(|(mode, payload)| {
+                let framed = framing
+                    .apply(&payload)
+                    .map_err(|err| Error::new(ErrorKind::InvalidInput, err))?;
+                let sent = socket.send(&framed)?;
+                printer.sent(mode.short_label(), sent, &payload, None, &args.target);
+                Ok(())
+            });
+        if let Err(err) = result {
+            printer.error(&format!("record {}: {}", record, err));
+            status = ExitCode::FAILURE;
+        }
+    };
+
+    let mut stdin = std::io::stdin().lock();
+    let mut pending = vec![];
+    let mut buffer = [0u8; 8192];
+    loop {
+        let n = match stdin.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        pending.extend_from_slice(&buffer[..n]);
+        while let Some(end) = find(&pending, &delimiter) {
+            send(&pending[..end]);
+            pending.drain(..end + delimiter.len());
+        }
+    }
+    send(&pending);
+
+    Ok(status)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
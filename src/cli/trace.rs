@@ -0,0 +1,237 @@
+use super::output::Printer;
+use crate::args::ProbeArgs;
+use std::io::{Error, ErrorKind};
+use std::net::IpAddr;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+/// Hops tried before giving up on reaching the target.
+const MAX_HOPS: u32 = 30;
+
+/// What came back for one TTL.
+pub enum Hop {
+    /// A router reported ICMP time exceeded.
+    Router(IpAddr, Duration),
+    /// The target itself answered, with a reply or an ICMP port unreachable.
+    Target(IpAddr, Duration),
+    /// Nothing within the wait.
+    Silent,
+}
+
+/// Send the command (or an empty datagram) with TTL 1, 2, ... and print which router drops each
+/// probe, until the target answers or `MAX_HOPS` is reached. Each hop waits `--wait`
+/// (default 1s).
+pub fn run(args: &ProbeArgs, probe: &[u8]) -> std::io::Result<ExitCode> {
+    let framing = &args.framing;
+    let printer = Printer::new(&args.output);
+    let socket = super::connect(&args.socket, &args.target)?;
+    let target = socket.peer_addr()?.ip();
+    if !target.is_ipv4() {
+        return Err(Error::new(
+            ErrorKind::Unsupported,
+            "trace only supports IPv4 targets",
+        ));
+    }
+    errqueue::enable(&socket)?;
+    socket.set_nonblocking(true)?;
+
+    let framed = framing
+        .apply(probe)
+        .map_err(|err| Error::new(ErrorKind::InvalidInput, err))?;
+    let wait = args.wait.unwrap_or(Duration::from_secs(1));
+    printer.info(&format!(
+        "trace to {} ({}), {} hops max",
+        args.target, target, MAX_HOPS
+    ));
+
+    let mut buffer = [0u8; crate::utils::RECV_BUFFER];
+    for ttl in 1..=MAX_HOPS {
+        // An ICMP error for an earlier probe that arrived after its wait isn't this hop's.
+        while errqueue::next(&socket)?.is_some() {}
+        socket.take_error()?;
+
+        socket.set_ttl(ttl)?;
+        let sent_at = Instant::now();
+        socket.send(&framed)?;
+
+        let hop_for = |icmp: errqueue::Icmp| match icmp.time_exceeded {
+            true => Hop::Router(icmp.from, sent_at.elapsed()),
+            false => Hop::Target(icmp.from, sent_at.elapsed()),
+        };
+        let mut hop = Hop::Silent;
+        while sent_at.elapsed() < wait {
+            if let Some(icmp) = errqueue::next(&socket)? {
+                hop = hop_for(icmp);
+                break;
+            }
+            match socket.recv(&mut buffer) {
+                Ok(_) => {
+                    hop = Hop::Target(target, sent_at.elapsed());
+                    break;
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                    std::thread::sleep(Duration::from_millis(5));
+                }
+                // An ICMP error that arrived since the queue was checked is also reported here;
+                // its details are in the queue. Without an entry there the error is our own.
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        ErrorKind::ConnectionRefused
+                            | ErrorKind::HostUnreachable
+                            | ErrorKind::NetworkUnreachable
+                    ) =>
+                {
+                    match errqueue::next(&socket)? {
+                        Some(icmp) => {
+                            hop = hop_for(icmp);
+                            break;
+                        }
+                        None => return Err(err),
+                    }
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        let reached = matches!(hop, Hop::Target(..));
+        printer.hop(ttl, &hop);
+        if reached {
+            return Ok(ExitCode::SUCCESS);
+        }
+    }
+
+    printer.error(&format!("{} not reached within {} hops", target, MAX_HOPS));
+    Ok(ExitCode::FAILURE)
+}
+
+/// ICMP errors for a UDP socket, read from the Linux socket error queue (`IP_RECVERR`).
+#[cfg(target_os = "linux")]
+mod errqueue {
+    use std::net::{IpAddr, Ipv4Addr, UdpSocket};
+    use std::os::fd::AsRawFd;
+
+    const ICMP_DEST_UNREACH: u8 = 3;
+    const ICMP_TIME_EXCEEDED: u8 = 11;
+
+    pub struct Icmp {
+        pub from: IpAddr,
+        pub time_exceeded: bool,
+    }
+
+    pub fn enable(socket: &UdpSocket) -> std::io::Result<()> {
+        let on: libc::c_int = 1;
+        // SAFETY: a valid fd and a c_int option value of the declared size.
+        let result = unsafe {
+            libc::setsockopt(
+                socket.as_raw_fd(),
+                libc::IPPROTO_IP,
+                libc::IP_RECVERR,
+                (&on as *const libc::c_int).cast(),
+                size_of::<libc::c_int>() as libc::socklen_t,
+            )
+        };
+        match result {
+            0 => Ok(()),
+            _ => Err(std::io::Error::last_os_error()),
+        }
+    }
+
+    /// The next queued time exceeded or destination unreachable error, if any.
+    pub fn next(socket: &UdpSocket) -> std::io::Result<Option<Icmp>> {
+        loop {
+            let mut data = [0u8; 512];
+            // u64 elements keep the buffer aligned for the cmsghdr headers written into it.
+            let mut control = [0u64; 64];
+            let mut iov = libc::iovec {
+                iov_base: data.as_mut_ptr().cast(),
+                iov_len: data.len(),
+            };
+            // SAFETY: msghdr is plain data; the zeroed fields are valid "no buffer" values.
+            let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+            msg.msg_iov = &mut iov;
+            msg.msg_iovlen = 1;
+            msg.msg_control = control.as_mut_ptr().cast();
+            msg.msg_controllen = size_of_val(&control) as _;
+
+            // SAFETY: msg points at buffers that outlive the call.
+            let n = unsafe {
+                libc::recvmsg(
+                    socket.as_raw_fd(),
+                    &mut msg,
+                    libc::MSG_ERRQUEUE | libc::MSG_DONTWAIT,
+                )
+            };
+            if n < 0 {
+                let err = std::io::Error::last_os_error();
+                return match err.kind() {
+                    std::io::ErrorKind::WouldBlock => Ok(None),
+                    _ => Err(err),
+                };
+            }
+
+            // SAFETY: the CMSG macros walk the aligned control buffer the kernel just filled,
+            // and an IP_RECVERR message carries a sock_extended_err followed by the offender
+            // address. The payload has no alignment guarantee, so it is read unaligned.
+            unsafe {
+                let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+                while !cmsg.is_null() {
+                    if (*cmsg).cmsg_level == libc::IPPROTO_IP
+                        && (*cmsg).cmsg_type == libc::IP_RECVERR
+                    {
+                        let data = libc::CMSG_DATA(cmsg);
+                        let err = (data as *const libc::sock_extended_err).read_unaligned();
+                        let offender = (data.add(size_of::<libc::sock_extended_err>())
+                            as *const libc::sockaddr_in)
+                            .read_unaligned();
+                        if err.ee_origin == libc::SO_EE_ORIGIN_ICMP
+                            && offender.sin_family == libc::AF_INET as libc::sa_family_t
+                        {
+                            let from =
+                                IpAddr::V4(Ipv4Addr::from(u32::from_be(offender.sin_addr.s_addr)));
+                            match err.ee_type {
+                                ICMP_TIME_EXCEEDED => {
+                                    return Ok(Some(Icmp {
+                                        from,
+                                        time_exceeded: true,
+                                    }));
+                                }
+                                // Port unreachable from the target, or a router that can't
+                                // forward at all; either way the trace ends here.
+                                ICMP_DEST_UNREACH => {
+                                    return Ok(Some(Icmp {
+                                        from,
+                                        time_exceeded: false,
+                                    }));
+                                }
+                                _ => {}
+                            }
+                        }
+                    }
+                    cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod errqueue {
+    use std::net::{IpAddr, UdpSocket};
+
+    pub struct Icmp {
+        pub from: IpAddr,
+        pub time_exceeded: bool,
+    }
+
+    pub fn enable(_: &UdpSocket) -> std::io::Result<()> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "trace needs the Linux socket error queue",
+        ))
+    }
+
+    pub fn next(_: &UdpSocket) -> std::io::Result<Option<Icmp>> {
+        Ok(None)
+    }
+}
@@ -0,0 +1,76 @@
+use super::output::Printer;
+use crate::args::WatchArgs;
+use crate::framing::Framing;
+use std::io::{Error, ErrorKind};
+use std::net::UdpSocket;
+use std::path::Path;
+use std::process::ExitCode;
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often the file is checked for changes.
+const POLL: Duration = Duration::from_millis(200);
+/// How long a changed file must stay unchanged before it is sent, so a save in progress isn't.
+const SETTLE: Duration = Duration::from_millis(300);
+
+/// Send the file at `path`, then send it again every time it changes on disk, until
+/// interrupted. Changes are spotted by polling its modification time and size, and a burst of
+/// writes is sent once, after it settles. Replies are printed as they arrive.
+pub fn run(args: &WatchArgs) -> std::io::Result<ExitCode> {
+    let path = &args.file;
+    let framing = &args.framing;
+    let printer = Printer::new(&args.output);
+    let socket = super::connect(&args.socket, &args.target)?;
+    printer.info(&format!("watching {}", path.display()));
+
+    let mut seen = Some(version(path)?);
+    let mut sent = None;
+    let mut changed_at: Option<Instant> = None;
+    let mut buffer = [0u8; crate::utils::RECV_BUFFER];
+    loop {
+        if seen.is_some() && seen != sent && changed_at.is_none_or(|at| at.elapsed() >= SETTLE) {
+            if let Err(err) = send(args, &socket, framing, &printer, path) {
+                printer.error(&format!("{}: {}", path.display(), err));
+            }
+            sent = seen;
+        }
+
+        let deadline = Instant::now() + POLL;
+        super::print_replies_until(&socket, framing, &printer, args.mode, deadline, &mut buffer)?;
+
+        // A missing file is usually an editor replacing it; keep polling until it's back.
+        let current = version(path).ok();
+        if current != seen {
+            seen = current;
+            changed_at = Some(Instant::now());
+        }
+    }
+}
+
+/// What identifies one saved version of the file.
+fn version(path: &Path) -> std::io::Result<(SystemTime, u64)> {
+    let metadata = std::fs::metadata(path)?;
+    Ok((metadata.modified()?, metadata.len()))
+}
+
+fn send(
+    args: &WatchArgs,
+    socket: &UdpSocket,
+    framing: &Framing,
+    printer: &Printer,
+    path: &Path,
+) -> std::io::Result<()> {
+    let data = std::fs::read(path)?;
+    if data.len() > crate::utils::MAX_DATAGRAM {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("{} bytes, more than one datagram holds", data.len()),
+        ));
+    }
+    let framed = framing
+        .apply(&data)
+        .map_err(|err| Error::new(ErrorKind::InvalidInput, err))?;
+    let sent = socket.send(&framed)?;
+    let origin = path.display().to_string();
+    printer.sent("FILE", sent, &data, Some(&origin), &args.target);
+    Ok(())
+}
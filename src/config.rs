@@ -0,0 +1,511 @@
+use crate::Theme;
+use crate::args::{
+    Command, OutputArgs, PayloadArgs, ReplyArgs, ScanArgs, SendArgs, SocketArgs, TuiArgs,
+};
+use crate::framing::Framing;
+use clap::ValueEnum;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// TUI settings from the config file.
+#[derive(Default)]
+pub struct Config {
+    /// Used unless `--theme` is given.
+    pub theme: Option<Theme>,
+    /// Macros: name and the lines of its steps.
+    pub macros: Vec<(String, Vec<String>)>,
+    /// Colors for PUBLISH topics, by topic filter (`+` matches a level).
+    pub topic_colors: Vec<(String, ratatui::style::Color)>,
+    /// Vim-style modal keys, from `keys = vim`.
+    pub vim: bool,
+    /// Width of the inspector/session column.
+    pub detail_width: Option<u16>,
+    /// Height of the input box.
+    pub input_height: Option<u16>,
+}
+
+/// Read the TUI settings from `~/.config/udd/config` (under `$XDG_CONFIG_HOME` if set). Each
+/// line is `key = value`; `#` at the start of a line or after whitespace starts a comment. A
+/// macro is one `macro <name> = <step>` line per step, in order, with the step taken as written,
+/// and `topic_color <filter> = <color>` colors PUBLISH entries for matching topics.
+pub fn load_file() -> io::Result<Config> {
+    let mut config = Config::default();
+    let Some(path) = path() else {
+        return Ok(config);
+    };
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(config),
+        Err(err) => return Err(err),
+    };
+
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let invalid = |message: String| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}:{}: {}", path.display(), number + 1, message),
+            )
+        };
+        let (key, value) = line
+            .split_once('=')
+            .map(|(k, v)| (k.trim(), v))
+            .ok_or_else(|| invalid(format!("expected `key = value`, got `{}`", line)))?;
+        if let Some(name) = key.strip_prefix("macro ") {
+            let name = name.trim();
+            let value = value.trim();
+            match config.macros.iter_mut().find(|(n, _)| n == name) {
+                Some((_, steps)) => steps.push(value.to_string()),
+                None => config
+                    .macros
+                    .push((name.to_string(), vec![value.to_string()])),
+            }
+            continue;
+        }
+        let value = strip_comment(value);
+        if let Some(topic) = key.strip_prefix("topic_color ") {
+            let color = value
+                .parse()
+                .map_err(|_| invalid(format!("unknown color `{}`", value)))?;
+            config.topic_colors.push((topic.trim().to_string(), color));
+            continue;
+        }
+        match key {
+            "theme" => {
+                let theme = Theme::from_str(value, true)
+                    .map_err(|_| invalid(format!("unknown theme `{}`", value)))?;
+                config.theme = Some(theme);
+            }
+            "keys" => {
+                config.vim = match value {
+                    "vim" => true,
+                    "default" => false,
+                    _ => {
+                        return Err(invalid(format!(
+                            "`keys` is vim or default, got `{}`",
+                            value
+                        )));
+                    }
+                }
+            }
+            "detail_width" | "input_height" => {
+                let size = value
+                    .parse()
+                    .map_err(|_| invalid(format!("`{}` must be a number, got `{}`", key, value)))?;
+                match key {
+                    "detail_width" => config.detail_width = Some(size),
+                    _ => config.input_height = Some(size),
+                }
+            }
+            _ => return Err(invalid(format!("unknown setting `{}`", key))),
+        }
+    }
+    Ok(config)
+}
+
+/// `value` without a trailing comment: a `#` that follows whitespace.
+fn strip_comment(value: &str) -> &str {
+    let end = value
+        .char_indices()
+        .find(|&(i, c)| c == '#' && value[..i].ends_with(char::is_whitespace))
+        .map_or(value.len(), |(i, _)| i);
+    value[..end].trim()
+}
+
+/// Replace macro `name` in the config file with `steps`, or remove it when `steps` is empty.
+/// Every other line is kept as it was.
+pub fn save_macro(name: &str, steps: &[String]) -> io::Result<()> {
+    let key = format!("macro {}", name);
+    let lines: Vec<String> = steps
+        .iter()
+        .map(|step| format!("{} = {}", key, step))
+        .collect();
+    rewrite(&key, &lines)
+}
+
+/// Set `key = value` in the config file, keeping every other line as it was.
+pub fn save_setting(key: &str, value: &str) -> io::Result<()> {
+    rewrite(key, &[format!("{} = {}", key, value)])
+}
+
+/// Drop every `key = ...` line from the config file and append `lines`.
+fn rewrite(key: &str, lines: &[String]) -> io::Result<()> {
+    let path = path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no $HOME"))?;
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err),
+    };
+
+    let matches = |line: &str| {
+        line.split_once('=')
+            .is_some_and(|(k, _)| k.split_whitespace().eq(key.split_whitespace()))
+    };
+    let mut out: String = contents
+        .lines()
+        .filter(|line| !matches(line))
+        .flat_map(|line| [line, "\n"])
+        .collect();
+    for line in lines {
+        out.push_str(line);
+        out.push('\n');
+    }
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(&path, out)
+}
+
+fn path() -> Option<PathBuf> {
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config.join("udd").join("config"))
+}
+
+/// The resolved settings of `command` as `(name, value)` pairs, for `--print-config`.
+pub fn describe(command: &Command, config: &Config) -> Vec<(&'static str, String)> {
+    let mut rows = vec![];
+    match command {
+        Command::Send(args) | Command::Mqtt(args) => {
+            rows.extend(socket(&args.socket, &args.target));
+            rows.extend(payload(&args.payload));
+            rows.extend(send(args));
+            rows.extend(reply(&args.reply));
+            rows.extend(framing(&args.framing));
+            rows.extend(output(&args.output));
+        }
+        Command::Listen(args) => {
+            rows.push(("listen", args.addr.clone()));
+            rows.push(("reuse", reuse(args.reuse.reuse_addr, args.reuse.reuse_port)));
+            rows.push(("mode", args.mode.short_label().into()));
+            rows.extend(framing(&args.framing));
+            rows.extend(output(&args.output));
+        }
+        Command::Tui(args) => return describe_tui(args, config),
+        Command::Script(args) => {
+            rows.extend(socket(&args.socket, &args.target));
+            rows.push(("script", args.path.display().to_string()));
+            rows.extend(payload(&args.payload));
+            rows.extend(reply(&args.reply));
+            rows.extend(framing(&args.framing));
+            rows.extend(output(&args.output));
+        }
+        Command::Assert(args) => {
+            rows.extend(socket(&args.socket, &args.target));
+            rows.push(("assert", format!("{} step(s)", args.assertion.steps.len())));
+            rows.push(("wait", duration(args.wait)));
+            rows.extend(payload(&args.payload));
+            rows.extend(framing(&args.framing));
+            rows.extend(output(&args.output));
+        }
+        Command::Watch(args) => {
+            rows.extend(socket(&args.socket, &args.target));
+            rows.push(("watch", args.file.display().to_string()));
+            rows.push(("mode", args.mode.short_label().into()));
+            rows.extend(framing(&args.framing));
+            rows.extend(output(&args.output));
+        }
+        Command::Replay(args) => {
+            rows.extend(socket(&args.socket, &args.target));
+            rows.push((
+                "replay",
+                format!("{} at {}x", args.capture.display(), args.speed),
+            ));
+            rows.push(("wait", duration(args.wait)));
+            rows.push(("mode", args.mode.short_label().into()));
+            rows.extend(framing(&args.framing));
+            rows.extend(output(&args.output));
+        }
+        Command::Stream(args) => {
+            rows.extend(socket(&args.socket, &args.target));
+            rows.push(("delimiter", args.delimiter.clone()));
+            rows.push(("interval", duration(args.interval)));
+            rows.push(("mode", args.mode.short_label().into()));
+            rows.extend(framing(&args.framing));
+            rows.extend(output(&args.output));
+        }
+        Command::Raw(args) => {
+            rows.extend(socket(&args.socket, &args.target));
+            rows.push(("wait", duration(args.wait)));
+            rows.extend(framing(&args.framing));
+            rows.extend(output(&args.output));
+        }
+        Command::Relay(args) => {
+            rows.extend(socket(&args.socket, &args.target));
+            rows.push(("relay", args.listen.clone()));
+            rows.push(("mode", args.mode.short_label().into()));
+            rows.extend(output(&args.output));
+        }
+        Command::Ping(args) => {
+            rows.extend(socket(&args.socket, &args.target));
+            rows.push(("ping count", or_none(args.count.map(|n| n.to_string()))));
+            rows.push(("interval", duration(args.interval)));
+            rows.push(("stats csv", or_none(args.stats_csv.as_deref().map(display))));
+            rows.extend(framing(&args.framing));
+            rows.extend(output(&args.output));
+        }
+        Command::Bench(args) => {
+            rows.extend(socket(&args.socket, &args.target));
+            rows.push((
+                "bench",
+                format!(
+                    "{} bytes, {}, {:?}",
+                    args.size,
+                    args.pps
+                        .map_or_else(|| "full speed".into(), |pps| format!("{} pps", pps)),
+                    args.duration
+                ),
+            ));
+            rows.push(("seed", or_none(args.seed.map(|s| s.to_string()))));
+            rows.extend(framing(&args.framing));
+            rows.extend(output(&args.output));
+        }
+        Command::Discover(probe)
+        | Command::Trace(probe)
+        | Command::Scan(ScanArgs { probe, .. }) => {
+            rows.extend(socket(&probe.socket, &probe.target));
+            if let Command::Scan(args) = command {
+                rows.push(("scan", format!("{} port(s)", args.ports.len())));
+            }
+            rows.push(("wait", duration(probe.wait)));
+            rows.extend(payload(&probe.payload));
+            rows.extend(framing(&probe.framing));
+            rows.extend(output(&probe.output));
+        }
+        Command::Completions { .. } => {}
+    }
+    rows
+}
+
+/// The resolved TUI settings as `(name, value)` pairs, for `--print-config` and `:config`.
+pub fn describe_tui(args: &TuiArgs, config: &Config) -> Vec<(&'static str, String)> {
+    let mut rows = socket(&args.socket, &args.target);
+    rows.extend([
+        ("mode", crate::InputMode::Auto.short_label().into()),
+        ("text encoding", value_name(&args.text_encoding)),
+        (
+            "theme",
+            value_name(&args.theme.or(config.theme).unwrap_or_else(Theme::detect)),
+        ),
+        (
+            "broadcast",
+            args.broadcast
+                .clone()
+                .unwrap_or_else(|| "255.255.255.255:<target port>".into()),
+        ),
+    ]);
+    rows.extend(framing(&args.framing));
+    rows.extend([
+        ("seed", or_none(args.seed.map(|s| s.to_string()))),
+        ("dump on error", args.dump_on_error.to_string()),
+        ("split pasted lines", (!args.literal_newlines).to_string()),
+        ("history", (!args.no_history).to_string()),
+        ("bell", args.bell.to_string()),
+        ("ack timeout", format!("{:?}", args.ack_timeout)),
+        ("keys", if config.vim { "vim" } else { "default" }.into()),
+        (
+            "max log",
+            format!("{} entries, {} MB", args.max_log, args.max_log_mb),
+        ),
+        (
+            "detail width",
+            or_none(config.detail_width.map(|w| w.to_string())),
+        ),
+        (
+            "input height",
+            or_none(config.input_height.map(|h| h.to_string())),
+        ),
+        (
+            "macros",
+            or_none((!config.macros.is_empty()).then(|| {
+                config
+                    .macros
+                    .iter()
+                    .map(|(n, _)| n.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            })),
+        ),
+        (
+            "topic colors",
+            or_none((!config.topic_colors.is_empty()).then(|| {
+                config
+                    .topic_colors
+                    .iter()
+                    .map(|(topic, color)| format!("{}={}", topic, color))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            })),
+        ),
+        ("send format", or_none(args.send_format.clone())),
+        ("log file", or_none(args.log_file.as_deref().map(display))),
+    ]);
+    rows
+}
+
+fn socket(args: &SocketArgs, target: &str) -> Vec<(&'static str, String)> {
+    vec![
+        ("bind", args.local()),
+        ("reuse", reuse(args.reuse.reuse_addr, args.reuse.reuse_port)),
+        ("target", target.to_string()),
+    ]
+}
+
+fn reuse(reuse_addr: bool, reuse_port: bool) -> String {
+    match (reuse_addr, reuse_port) {
+        (false, false) => "-".into(),
+        (true, false) => "SO_REUSEADDR".into(),
+        (false, true) => "SO_REUSEPORT".into(),
+        (true, true) => "SO_REUSEADDR, SO_REUSEPORT".into(),
+    }
+}
+
+fn payload(args: &PayloadArgs) -> Vec<(&'static str, String)> {
+    vec![
+        ("mode", args.mode.short_label().into()),
+        ("seed", or_none(args.seed.map(|s| s.to_string()))),
+    ]
+}
+
+fn send(args: &SendArgs) -> Vec<(&'static str, String)> {
+    vec![
+        ("repeat", or_none(args.repeat.map(|r| r.to_string()))),
+        ("interval", duration(args.interval)),
+        ("stats csv", or_none(args.stats_csv.as_deref().map(display))),
+        ("send glob", or_none(args.send_glob.clone())),
+    ]
+}
+
+fn reply(args: &ReplyArgs) -> Vec<(&'static str, String)> {
+    vec![
+        ("wait", duration(args.wait)),
+        (
+            "expect count",
+            or_none(args.expect_count.map(|n| n.to_string())),
+        ),
+        (
+            "expect",
+            or_none(args.expect.as_ref().map(|e| e.to_string())),
+        ),
+    ]
+}
+
+fn framing(framing: &Framing) -> Vec<(&'static str, String)> {
+    vec![
+        ("length prefix", framing.length_prefix.width().to_string()),
+        (
+            "strip length prefix",
+            framing.strip_length_prefix.to_string(),
+        ),
+        (
+            "pad to",
+            or_none(framing.pad_to.map(|size| {
+                let strict = if framing.pad_strict { ", strict" } else { "" };
+                format!("{} (0x{:02x}{})", size, framing.pad_byte, strict)
+            })),
+        ),
+    ]
+}
+
+fn output(args: &OutputArgs) -> Vec<(&'static str, String)> {
+    vec![
+        ("output", value_name(&args.output)),
+        ("color", value_name(&args.color)),
+        ("quiet", args.quiet.to_string()),
+        ("dump", or_none(args.dump.as_ref().map(value_name))),
+        (
+            "output dir",
+            or_none(args.output_dir.as_deref().map(display)),
+        ),
+        ("text encoding", value_name(&args.text_encoding)),
+    ]
+}
+
+fn or_none(value: Option<String>) -> String {
+    value.unwrap_or_else(|| "-".into())
+}
+
+fn duration(value: Option<std::time::Duration>) -> String {
+    or_none(value.map(|d| format!("{:?}", d)))
+}
+
+fn display(path: &Path) -> String {
+    path.display().to_string()
+}
+
+/// The name clap accepts for an enum flag value.
+fn value_name(value: &impl clap::ValueEnum) -> String {
+    value
+        .to_possible_value()
+        .map_or_else(|| "-".into(), |v| v.get_name().to_string())
+}
+
+pub fn render(settings: &[(&str, String)]) -> Vec<String> {
+    let width = settings.iter().map(|(k, _)| k.len()).max().unwrap_or(0);
+    settings
+        .iter()
+        .map(|(k, v)| format!("{:<width$}  {}", k, v, width = width))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{FromArgMatches, Subcommand};
+
+    /// The `--print-config` lines for `udd <argv>`.
+    fn print_config(argv: &[&str]) -> Vec<String> {
+        let command = Command::augment_subcommands(clap::Command::new("udd"));
+        let matches = command.get_matches_from(std::iter::once(&"udd").chain(argv));
+        let command = Command::from_arg_matches(&matches).unwrap();
+        render(&describe(&command, &Config::default()))
+    }
+
+    /// The value shown for `key`.
+    fn value<'a>(lines: &'a [String], key: &str) -> &'a str {
+        lines
+            .iter()
+            .find_map(|line| {
+                let rest = line.strip_prefix(key)?;
+                rest.starts_with("  ").then(|| rest.trim_start())
+            })
+            .unwrap_or_else(|| panic!("no {} row in {:?}", key, lines))
+    }
+
+    #[test]
+    fn rendered_config_has_the_key_fields() {
+        let send = print_config(&[
+            "send",
+            "127.0.0.1:9",
+            "--bind",
+            "127.0.0.1:0",
+            "--source-port",
+            "4000",
+            "--mode",
+            "hex",
+            "--wait",
+            "500ms",
+            "--pad-to",
+            "16",
+        ]);
+        assert_eq!(value(&send, "bind"), "127.0.0.1:4000");
+        assert_eq!(value(&send, "target"), "127.0.0.1:9");
+        assert_eq!(value(&send, "mode"), "HEX");
+        assert_eq!(value(&send, "wait"), "500ms");
+        assert_eq!(value(&send, "pad to"), "16 (0x00)");
+        assert_eq!(value(&send, "repeat"), "-");
+
+        let tui = print_config(&["tui", "127.0.0.1:9", "--ack-timeout", "2s", "--reuse-port"]);
+        assert_eq!(value(&tui, "bind"), "0.0.0.0:0");
+        assert_eq!(value(&tui, "target"), "127.0.0.1:9");
+        assert_eq!(value(&tui, "reuse"), "SO_REUSEPORT");
+        assert_eq!(value(&tui, "ack timeout"), "2s");
+        assert_eq!(value(&tui, "keys"), "default");
+    }
+}
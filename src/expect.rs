@@ -0,0 +1,138 @@
+use crate::utils;
+use mqtt::{ConnectReturnCode, Packet, SubAckReturnCode, UdpFrame};
+use std::fmt;
+use std::str::FromStr;
+
+/// A reply pattern for `--expect`.
+///
+/// `hex:<bytes>` matches a reply starting with those bytes, `text:<s>` (or any pattern that is
+/// not an MQTT packet type) one containing the text, and `<packet>[:<detail>]` a decoded MQTT
+/// frame, e.g. `pong`, `connack:accepted`, `suback:granted` or `publish:sensors/temp`.
+#[derive(Clone)]
+pub enum Expect {
+    Text(String),
+    HexPrefix(Vec<u8>),
+    Mqtt {
+        packet: &'static str,
+        detail: Option<String>,
+    },
+}
+
+const PACKETS: [&str; 9] = [
+    "connect",
+    "connack",
+    "publish",
+    "puback",
+    "subscribe",
+    "suback",
+    "ping",
+    "pong",
+    "disconnect",
+];
+
+impl FromStr for Expect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (head, rest) = match s.split_once(':') {
+            Some((head, rest)) => (head, Some(rest)),
+            None => (s, None),
+        };
+
+        match head.to_lowercase().as_str() {
+            "hex" => utils::parse_hex(rest.unwrap_or_default()).map(Expect::HexPrefix),
+            "text" => Ok(Expect::Text(rest.unwrap_or_default().into())),
+            name => match PACKETS.iter().find(|&&packet| packet == name) {
+                Some(packet) => Ok(Expect::Mqtt {
+                    packet,
+                    detail: rest.map(str::to_string),
+                }),
+                None if s.is_empty() => Err("expect pattern must not be empty".into()),
+                None => Ok(Expect::Text(s.into())),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Expect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expect::Text(text) => write!(f, "text:{}", text),
+            Expect::HexPrefix(bytes) => {
+                f.write_str("hex:")?;
+                bytes.iter().try_for_each(|b| write!(f, "{:02x}", b))
+            }
+            Expect::Mqtt {
+                packet,
+                detail: Some(detail),
+            } => write!(f, "{}:{}", packet, detail),
+            Expect::Mqtt {
+                packet,
+                detail: None,
+            } => f.write_str(packet),
+        }
+    }
+}
+
+impl Expect {
+    /// Whether `name` is an MQTT packet type a pattern can name.
+    pub fn is_packet(name: &str) -> bool {
+        PACKETS.contains(&name.to_lowercase().as_str())
+    }
+
+    pub fn matches(&self, data: &[u8]) -> bool {
+        match self {
+            Expect::Text(text) => String::from_utf8_lossy(data).contains(text.as_str()),
+            Expect::HexPrefix(prefix) => data.starts_with(prefix),
+            Expect::Mqtt { packet, detail } => match UdpFrame::decode(data) {
+                Ok(frame) => {
+                    packet_name(&frame.packet) == *packet
+                        && detail
+                            .as_deref()
+                            .is_none_or(|detail| detail_matches(&frame.packet, detail))
+                }
+                Err(_) => false,
+            },
+        }
+    }
+}
+
+fn packet_name(packet: &Packet) -> &'static str {
+    match packet {
+        Packet::Connect(_) => "connect",
+        Packet::ConnAck(_) => "connack",
+        Packet::Publish(_) => "publish",
+        Packet::PubAck(_) => "puback",
+        Packet::Subscribe(_) => "subscribe",
+        Packet::SubAck(_) => "suback",
+        Packet::Ping(_) => "ping",
+        Packet::Pong(_) => "pong",
+        Packet::Disconnect(_) => "disconnect",
+    }
+}
+
+/// `connack:accepted|rejected|<return code>`, `suback:granted|refused`, `publish:<topic>` and
+/// `connect:<client id>`. Keywords are case-insensitive; topics and client ids are not.
+fn detail_matches(packet: &Packet, detail: &str) -> bool {
+    match packet {
+        Packet::ConnAck(c) => match detail.to_lowercase().as_str() {
+            "accepted" => matches!(c.return_code, ConnectReturnCode::Accepted),
+            "rejected" => !matches!(c.return_code, ConnectReturnCode::Accepted),
+            code => format!("{:?}", c.return_code).eq_ignore_ascii_case(code),
+        },
+        Packet::SubAck(s) => {
+            let refused = s
+                .return_codes
+                .iter()
+                .any(|code| matches!(code, SubAckReturnCode::Failure));
+            match detail.to_lowercase().as_str() {
+                "granted" => !refused,
+                "refused" => refused,
+                _ => false,
+            }
+        }
+        Packet::Publish(p) => p.topic == detail,
+        Packet::Connect(c) => c.client_id == detail,
+        _ => false,
+    }
+}
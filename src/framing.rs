@@ -0,0 +1,103 @@
+use crate::{LengthPrefix, utils};
+
+/// Transformations applied to every outgoing datagram after encoding, and undone on receive.
+#[derive(Clone, clap::Args)]
+pub struct Framing {
+    /// Prepend a big-endian length header of this many bytes to every outgoing datagram
+    #[arg(long, value_enum, default_value_t = LengthPrefix::None)]
+    pub length_prefix: LengthPrefix,
+    /// Strip the --length-prefix header from received datagrams
+    #[arg(long)]
+    pub strip_length_prefix: bool,
+    /// Pad every outgoing datagram up to this many bytes
+    #[arg(long, value_name = "BYTES")]
+    pub pad_to: Option<usize>,
+    /// Byte used by --pad-to, e.g. 0xff
+    #[arg(long, default_value = "0x00", value_parser = utils::parse_byte)]
+    pub pad_byte: u8,
+    /// Refuse to send datagrams already longer than --pad-to instead of sending them unchanged
+    #[arg(long)]
+    pub pad_strict: bool,
+}
+
+impl Framing {
+    /// Prefix the payload with its length, then pad the whole datagram up to `pad_to`.
+    pub fn apply(&self, payload: &[u8]) -> Result<Vec<u8>, String> {
+        let mut data = self.length_prefix.apply(payload)?;
+
+        if let Some(size) = self.pad_to {
+            if data.len() > size && self.pad_strict {
+                return Err(format!(
+                    "datagram is {} bytes, over --pad-to {}",
+                    data.len(),
+                    size
+                ));
+            }
+            if data.len() < size {
+                data.resize(size, self.pad_byte);
+            }
+        }
+
+        Ok(data)
+    }
+
+    pub fn strip<'a>(&self, data: &'a [u8]) -> &'a [u8] {
+        match self.strip_length_prefix {
+            true => self.length_prefix.strip(data),
+            false => data,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn framing(length_prefix: LengthPrefix) -> Framing {
+        Framing {
+            length_prefix,
+            strip_length_prefix: true,
+            pad_to: None,
+            pad_byte: 0,
+            pad_strict: false,
+        }
+    }
+
+    #[test]
+    fn length_prefix_is_big_endian() {
+        let two = framing(LengthPrefix::U16).apply(b"hello").unwrap();
+        assert_eq!(two, b"\x00\x05hello");
+        let four = framing(LengthPrefix::U32).apply(b"hello").unwrap();
+        assert_eq!(four, b"\x00\x00\x00\x05hello");
+        assert_eq!(
+            framing(LengthPrefix::None).apply(b"hello").unwrap(),
+            b"hello"
+        );
+
+        assert_eq!(framing(LengthPrefix::U16).strip(&two), b"hello");
+        assert!(framing(LengthPrefix::U16).apply(&[0; 65536]).is_err());
+    }
+
+    #[test]
+    fn pad_to_fills_up_to_the_size() {
+        let mut padded = Framing {
+            pad_to: Some(16),
+            ..framing(LengthPrefix::None)
+        };
+        assert_eq!(
+            padded.apply(b"hello").unwrap(),
+            b"hello\0\0\0\0\0\0\0\0\0\0\0"
+        );
+
+        padded.pad_byte = 0xff;
+        let data = padded.apply(b"hello").unwrap();
+        assert_eq!(data.len(), 16);
+        assert!(data[5..].iter().all(|&b| b == 0xff));
+
+        let long = [1; 20];
+        assert_eq!(padded.apply(&long).unwrap(), long);
+        padded.pad_strict = true;
+        assert!(padded.apply(&long).is_err());
+        assert_eq!(padded.apply(&long[..16]).unwrap(), long[..16]);
+    }
+}
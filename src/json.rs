@@ -0,0 +1,115 @@
+/// Minimal JSON object writer, enough for one-line event records.
+pub struct Object {
+    buf: String,
+}
+
+impl Object {
+    pub fn new() -> Self {
+        Self {
+            buf: String::from("{"),
+        }
+    }
+
+    fn key(&mut self, key: &str) {
+        if self.buf.len() > 1 {
+            self.buf.push(',');
+        }
+        push_str(&mut self.buf, key);
+        self.buf.push(':');
+    }
+
+    pub fn str(mut self, key: &str, value: &str) -> Self {
+        self.key(key);
+        push_str(&mut self.buf, value);
+        self
+    }
+
+    pub fn num(mut self, key: &str, value: impl Into<f64>) -> Self {
+        self.key(key);
+        self.buf.push_str(&value.into().to_string());
+        self
+    }
+
+    pub fn bool(mut self, key: &str, value: bool) -> Self {
+        self.key(key);
+        self.buf.push_str(if value { "true" } else { "false" });
+        self
+    }
+
+    pub fn strs<'a>(mut self, key: &str, values: impl IntoIterator<Item = &'a str>) -> Self {
+        self.key(key);
+        self.buf.push('[');
+        for (i, value) in values.into_iter().enumerate() {
+            if i > 0 {
+                self.buf.push(',');
+            }
+            push_str(&mut self.buf, value);
+        }
+        self.buf.push(']');
+        self
+    }
+
+    /// Insert an already-serialized JSON value.
+    pub fn raw(mut self, key: &str, json: &str) -> Self {
+        self.key(key);
+        self.buf.push_str(json);
+        self
+    }
+
+    pub fn finish(mut self) -> String {
+        self.buf.push('}');
+        self.buf
+    }
+}
+
+/// Read back the string field `key` from a line written by [`Object`]. The first match at the
+/// top level of the line wins, so fields written before a `raw` value are found reliably.
+pub fn str_field(line: &str, key: &str) -> Option<String> {
+    let mut needle = String::new();
+    push_str(&mut needle, key);
+    needle.push_str(":\"");
+
+    let mut from = 0;
+    let start = loop {
+        let at = from + line[from..].find(&needle)?;
+        if at > 0 && matches!(line.as_bytes()[at - 1], b'{' | b',') {
+            break at + needle.len();
+        }
+        from = at + 1;
+    };
+
+    let mut value = String::new();
+    let mut chars = line[start..].chars();
+    loop {
+        match chars.next()? {
+            '"' => return Some(value),
+            '\\' => match chars.next()? {
+                'n' => value.push('\n'),
+                'r' => value.push('\r'),
+                't' => value.push('\t'),
+                'u' => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    value.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+                }
+                c => value.push(c),
+            },
+            c => value.push(c),
+        }
+    }
+}
+
+fn push_str(buf: &mut String, s: &str) {
+    buf.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => buf.push_str("\\\""),
+            '\\' => buf.push_str("\\\\"),
+            '\n' => buf.push_str("\\n"),
+            '\r' => buf.push_str("\\r"),
+            '\t' => buf.push_str("\\t"),
+            c if (c as u32) < 0x20 => buf.push_str(&format!("\\u{:04x}", c as u32)),
+            c => buf.push(c),
+        }
+    }
+    buf.push('"');
+}
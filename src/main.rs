@@ -1,24 +1,16 @@
+mod args;
+mod assert;
 mod cli;
+mod config;
+mod expect;
+mod framing;
+mod json;
+mod parse;
 mod tui;
 mod utils;
 
-#[derive(clap::Parser)]
-#[command(name = "udd", about = "UDP client with single-shot CLI and TUI")]
-struct Args {
-    target: String,
-    #[arg(short, long, default_value = "0.0.0.0:0")]
-    bind: String,
-    #[arg(long)]
-    tui: bool,
-    #[arg(long, value_enum, default_value_t = InputMode::Auto)]
-    mode: InputMode,
-    #[arg(
-        value_name = "COMMAND",
-        trailing_var_arg = true,
-        allow_hyphen_values = true
-    )]
-    command: Vec<String>,
-}
+use args::{Cli, Command};
+use std::process::ExitCode;
 
 #[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
 pub(crate) enum InputMode {
@@ -26,6 +18,8 @@ pub(crate) enum InputMode {
     Text,
     Hex,
     Mqtt,
+    #[value(alias = "b64")]
+    Base64,
 }
 
 impl InputMode {
@@ -35,14 +29,188 @@ impl InputMode {
             InputMode::Text => "TXT",
             InputMode::Hex => "HEX",
             InputMode::Mqtt => "MQTT",
+            InputMode::Base64 => "B64",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum TextEncoding {
+    Utf8,
+    Latin1,
+    Ascii,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum ColorMode {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorMode {
+    /// Whether output written to `stream` should be colored.
+    pub(crate) fn enabled(self, stream: &impl std::io::IsTerminal) -> bool {
+        match self {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            ColorMode::Auto => {
+                stream.is_terminal() && std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum Theme {
+    /// For dark terminal backgrounds
+    Dark,
+    /// For light terminal backgrounds
+    Light,
+    /// Bright, bold colors and inverted badges
+    HighContrast,
+    /// No colors, only bold/dim/underline
+    Monochrome,
+}
+
+impl Theme {
+    /// The theme when none is configured.
+    pub(crate) fn detect() -> Self {
+        match std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+            true => Theme::Monochrome,
+            false => Theme::Dark,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum DumpFormat {
+    /// Offset, hex bytes and ASCII column, 16 bytes per line
+    Hex,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Repeat {
+    Times(u64),
+    Forever,
+}
+
+impl Repeat {
+    /// Whether another send should follow the `sent` sends already made.
+    pub(crate) fn more(self, sent: u64) -> bool {
+        match self {
+            Repeat::Times(n) => sent < n,
+            Repeat::Forever => true,
         }
     }
 }
 
-fn main() -> std::io::Result<()> {
-    let args: Args = clap::Parser::parse();
-    match args.tui {
-        true => tui::run(&args),
-        false => cli::run(&args),
+impl std::str::FromStr for Repeat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("forever") {
+            return Ok(Repeat::Forever);
+        }
+        match s.parse::<u64>() {
+            Ok(0) => Err("repeat count must be at least 1".into()),
+            Ok(n) => Ok(Repeat::Times(n)),
+            Err(_) => Err(format!("expected a count or 'forever', got '{}'", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for Repeat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Repeat::Times(n) => write!(f, "{}", n),
+            Repeat::Forever => f.write_str("forever"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum LengthPrefix {
+    #[value(name = "0")]
+    None,
+    #[value(name = "2")]
+    U16,
+    #[value(name = "4")]
+    U32,
+}
+
+impl LengthPrefix {
+    pub(crate) fn width(self) -> usize {
+        match self {
+            LengthPrefix::None => 0,
+            LengthPrefix::U16 => 2,
+            LengthPrefix::U32 => 4,
+        }
+    }
+
+    /// Prepend the payload length as a big-endian header.
+    pub(crate) fn apply(self, payload: &[u8]) -> Result<Vec<u8>, String> {
+        let header = match self {
+            LengthPrefix::None => vec![],
+            LengthPrefix::U16 => u16::try_from(payload.len())
+                .map_err(|_| "payload too long for a 2-byte length prefix")?
+                .to_be_bytes()
+                .to_vec(),
+            LengthPrefix::U32 => u32::try_from(payload.len())
+                .map_err(|_| "payload too long for a 4-byte length prefix")?
+                .to_be_bytes()
+                .to_vec(),
+        };
+        Ok([header.as_slice(), payload].concat())
+    }
+
+    pub(crate) fn strip(self, data: &[u8]) -> &[u8] {
+        data.get(self.width()..).unwrap_or(data)
+    }
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(status) => status,
+        Err(err) => {
+            eprintln!("udd: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> std::io::Result<ExitCode> {
+    let cli = Cli::parse();
+    if let Command::Completions { shell } = cli.command {
+        clap_complete::generate(shell, &mut Cli::command(), "udd", &mut std::io::stdout());
+        return Ok(ExitCode::SUCCESS);
+    }
+    // The config file only holds TUI settings.
+    let config = match cli.command {
+        Command::Tui(_) => config::load_file()?,
+        _ => config::Config::default(),
+    };
+    parse::seed_fuzz(cli.command.seed().unwrap_or_else(|| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default()
+    }));
+    if cli.print_config {
+        for line in config::render(&config::describe(&cli.command, &config)) {
+            println!("{}", line);
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    match cli.command {
+        Command::Tui(args) => tui::run(&args, config).map(|_| ExitCode::SUCCESS),
+        command => cli::run(&command),
     }
 }
@@ -0,0 +1,332 @@
+use crate::{InputMode, utils};
+use mqtt::{
+    ConnAck, Connect, ConnectReturnCode, Disconnect, Packet, Ping, Pong, PubAck, Publish, QoS,
+    SubAck, SubAckReturnCode, Subscribe, SubscribeFilter, UdpFrame,
+};
+use std::sync::atomic::{AtomicU16, AtomicU64, Ordering};
+
+static MSG_ID_COUNTER: AtomicU16 = AtomicU16::new(1);
+static FUZZ_SEED: AtomicU64 = AtomicU64::new(0);
+static SEND_SEQ: AtomicU64 = AtomicU64::new(0);
+
+pub(crate) fn next_msg_id() -> u16 {
+    MSG_ID_COUNTER.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Seed the `fuzz` command. Every fuzz payload advances the seed, so a run is reproducible.
+pub(crate) fn seed_fuzz(seed: u64) {
+    FUZZ_SEED.store(seed, Ordering::Relaxed);
+}
+
+pub(crate) fn next_fuzz_seed() -> u64 {
+    FUZZ_SEED.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Build a datagram from typed input. Shared by the CLI and the TUI so both accept the same
+/// syntax.
+pub(crate) fn parse_payload(mode: InputMode, input: &str) -> Result<(InputMode, Vec<u8>), String> {
+    match mode {
+        InputMode::Auto => {
+            // `fuzz <len>` only when followed by a size, so text like `fuzz me` is still text.
+            if let Some(len) = input
+                .strip_prefix("fuzz ")
+                .and_then(|len| utils::parse_datagram_size(len).ok())
+            {
+                return Ok((InputMode::Hex, utils::random_bytes(len, next_fuzz_seed())));
+            }
+            if let Some(bytes) = input
+                .strip_prefix("b64 ")
+                .and_then(|data| utils::parse_base64(data).ok())
+            {
+                return Ok((InputMode::Base64, bytes));
+            }
+            if let Ok(frame) = parse_mqtt_command(input) {
+                return Ok((InputMode::Mqtt, frame.encode()));
+            }
+            if let Ok(hex) = utils::parse_hex(input) {
+                return Ok((InputMode::Hex, hex));
+            }
+            Ok((InputMode::Text, parse_text(input)))
+        }
+        InputMode::Mqtt => parse_mqtt_command(input).map(|frame| (InputMode::Mqtt, frame.encode())),
+        InputMode::Hex => utils::parse_hex(input).map(|hex| (InputMode::Hex, hex)),
+        InputMode::Text => Ok((InputMode::Text, parse_text(input))),
+        InputMode::Base64 => utils::parse_base64(input).map(|bytes| (InputMode::Base64, bytes)),
+    }
+}
+
+/// Text with escapes, after expanding the per-send placeholders.
+fn parse_text(input: &str) -> Vec<u8> {
+    utils::parse_text_with_escapes(&expand_placeholders(input))
+}
+
+/// Expand `{seq}` (a counter that advances with every templated send), `{ts}` (unix seconds),
+/// `{ts_ms}` (unix milliseconds) and `{rand:N}` (N random hex digits, following `--seed`).
+/// Anything else in braces is left as typed.
+fn expand_placeholders(input: &str) -> String {
+    if !input.contains('{') {
+        return input.to_string();
+    }
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    let mut seq = None;
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let Some(len) = rest[start..].find('}') else {
+            rest = &rest[start..];
+            break;
+        };
+        let name = &rest[start + 1..start + len];
+        match name {
+            "seq" => {
+                let seq = *seq.get_or_insert_with(|| SEND_SEQ.fetch_add(1, Ordering::Relaxed));
+                out.push_str(&seq.to_string());
+            }
+            "ts" => out.push_str(&now.as_secs().to_string()),
+            "ts_ms" => out.push_str(&now.as_millis().to_string()),
+            _ => match name
+                .strip_prefix("rand:")
+                .and_then(|n| utils::parse_datagram_size(n).ok())
+            {
+                Some(digits) => {
+                    let bytes = utils::random_bytes(digits.div_ceil(2), next_fuzz_seed());
+                    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+                    out.push_str(&hex[..digits]);
+                }
+                None => out.push_str(&rest[start..=start + len]),
+            },
+        }
+        rest = &rest[start + len + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Parse MQTT command syntax into a UdpFrame
+pub fn parse_mqtt_command(input: &str) -> mqtt::Result<UdpFrame, String> {
+    let input = input.trim();
+    let (cmd, rest) = input.split_once(' ').unwrap_or((input, ""));
+    let rest = rest.trim();
+
+    let packet: Packet = match cmd.to_lowercase().as_str() {
+        "connect" => {
+            // connect <client_id> [keepalive=N] [user=X] [pass=X]
+            let mut parts = rest.split_whitespace();
+
+            let client_id = parts.next().unwrap_or("id1");
+            let mut conn = Connect::new(client_id);
+
+            for part in parts {
+                if let Some((k, v)) = part.split_once('=') {
+                    match k {
+                        "keepalive" | "ka" => {
+                            conn.keep_alive = v.parse().map_err(|_| "invalid keepalive")?;
+                        }
+                        "user" => conn.username = Some(v.to_string()),
+                        "pass" => conn.password = Some(v.as_bytes().to_vec()),
+                        "clean" => conn.clean_session = v == "true" || v == "1",
+                        _ => return Err(format!("unknown option: {}", k)),
+                    }
+                }
+            }
+            conn.into()
+        }
+
+        "pub" | "publish" => {
+            // pub <topic> [payload] [qos=0|1|2] [retain]
+            // An empty payload is allowed: it clears a retained message.
+            let (topic, remainder) = rest.split_once(' ').unwrap_or((rest, ""));
+            if topic.is_empty() {
+                return Err(
+                    "publish topic must not be empty: pub|publish <topic> [payload] [qos=0|1|2] [retain]"
+                        .into(),
+                );
+            }
+            let mut pub_pkt = Publish::new(topic, "");
+            let mut payload_parts = vec![];
+
+            for part in remainder.split_whitespace() {
+                if let Some((k, v)) = part.split_once('=') {
+                    match k {
+                        "qos" => {
+                            pub_pkt = pub_pkt.with_qos(match v {
+                                "0" => QoS::AtMostOnce,
+                                "1" => QoS::AtLeastOnce,
+                                "2" => QoS::ExactlyOnce,
+                                _ => return Err("qos must be 0, 1, or 2".into()),
+                            });
+                        }
+                        _ => payload_parts.push(part),
+                    }
+                } else if part == "retain" {
+                    pub_pkt = pub_pkt.with_retain(true);
+                } else {
+                    payload_parts.push(part);
+                }
+            }
+
+            pub_pkt.payload = payload_parts.join(" ").into_bytes();
+            pub_pkt.into()
+        }
+
+        "sub" | "subscribe" => {
+            // sub <topic> [qos=0|1|2]
+            // sub <topic1>,<topic2> [qos=0|1|2]
+            let mut qos = QoS::AtMostOnce;
+            let mut topics = vec![];
+
+            for part in rest.split_whitespace() {
+                if let Some((k, v)) = part.split_once('=') {
+                    if k == "qos" {
+                        qos = match v {
+                            "0" => QoS::AtMostOnce,
+                            "1" => QoS::AtLeastOnce,
+                            "2" => QoS::ExactlyOnce,
+                            _ => return Err("qos must be 0, 1, or 2".into()),
+                        };
+                    }
+                } else {
+                    topics.extend(part.split(',').map(|s| s.to_string()));
+                }
+            }
+
+            if topics.is_empty() {
+                return Err("subscribe requires at least one topic".into());
+            }
+
+            let filters = topics
+                .into_iter()
+                .map(|t| SubscribeFilter::new(t, qos))
+                .collect();
+            Subscribe::new(filters).into()
+        }
+
+        "ping" => Ping.into(),
+        "disconnect" | "disc" => Disconnect.into(),
+        "puback" => PubAck.into(),
+
+        "connack" => {
+            // connack [accepted|rejected] [session=true|false]
+            let mut code = ConnectReturnCode::Accepted;
+            let mut session = false;
+
+            for part in rest.split_whitespace() {
+                match part {
+                    "accepted" => code = ConnectReturnCode::Accepted,
+                    "rejected" | "unauthorized" => code = ConnectReturnCode::NotAuthorized,
+                    "unavailable" => code = ConnectReturnCode::ServerUnavailable,
+                    _ if part.starts_with("session=") => {
+                        session = part.ends_with("true") || part.ends_with("1");
+                    }
+                    // uqtt's ConnAck has no field for a reason string, so don't drop it silently.
+                    _ if part.starts_with("reason=") => {
+                        return Err("connack reason strings are not supported by the codec".into());
+                    }
+                    _ => {}
+                }
+            }
+            ConnAck {
+                session_present: session,
+                return_code: code,
+            }
+            .into()
+        }
+
+        "suback" => {
+            // suback 0 1 2 or suback fail
+            let codes: mqtt::Result<Vec<_>, _> = rest
+                .split_whitespace()
+                .map(|s| match s {
+                    "0" => Ok(SubAckReturnCode::SuccessQoS0),
+                    "1" => Ok(SubAckReturnCode::SuccessQoS1),
+                    "2" => Ok(SubAckReturnCode::SuccessQoS2),
+                    "fail" | "failure" => Ok(SubAckReturnCode::Failure),
+                    _ => Err(format!("invalid suback code: {}", s)),
+                })
+                .collect();
+            SubAck::new(codes?).into()
+        }
+
+        "pingresp" | "pong" => Pong.into(),
+
+        _ => return Err(format!("unknown command: {}", cmd)),
+    };
+
+    Ok(UdpFrame::new(next_msg_id(), packet))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connack_refuses_a_reason_string() {
+        let Ok(frame) = parse_mqtt_command("connack rejected session=1") else {
+            panic!("connack without a reason should parse");
+        };
+        assert!(matches!(
+            frame.packet,
+            Packet::ConnAck(ConnAck {
+                session_present: true,
+                return_code: ConnectReturnCode::NotAuthorized,
+            })
+        ));
+
+        assert_eq!(
+            parse_mqtt_command("connack rejected reason=\"bad token\"").err(),
+            Some("connack reason strings are not supported by the codec".to_string())
+        );
+    }
+
+    #[test]
+    fn publish_needs_a_topic() {
+        for input in ["pub", "publish", "pub  "] {
+            let err = parse_mqtt_command(input).err();
+            assert!(
+                err.as_deref()
+                    .is_some_and(|e| e.starts_with("publish topic must not be empty")),
+                "{}: {:?}",
+                input,
+                err
+            );
+        }
+        assert!(matches!(
+            parse_mqtt_command("pub a/b 1").map(|frame| frame.packet),
+            Ok(Packet::Publish(p)) if p.topic == "a/b" && p.payload[..] == *b"1"
+        ));
+    }
+
+    #[test]
+    fn auto_fuzz_needs_a_size() {
+        let Ok((InputMode::Hex, data)) = parse_payload(InputMode::Auto, "fuzz 8") else {
+            panic!("expected a fuzz payload");
+        };
+        assert_eq!(data.len(), 8);
+        assert!(matches!(
+            parse_payload(InputMode::Auto, "fuzz me"),
+            Ok((InputMode::Text, data)) if data == b"fuzz me"
+        ));
+    }
+
+    #[test]
+    fn auto_b64_falls_back_to_text() {
+        assert!(matches!(
+            parse_payload(InputMode::Auto, "b64 aGVsbG8="),
+            Ok((InputMode::Base64, data)) if data == b"hello"
+        ));
+        for input in ["b64 hello", "b64 not base64!"] {
+            assert!(
+                matches!(
+                    parse_payload(InputMode::Auto, input),
+                    Ok((InputMode::Text, ref data)) if data == input.as_bytes()
+                ),
+                "{}",
+                input
+            );
+        }
+    }
+}
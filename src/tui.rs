@@ -1,94 +1,310 @@
-use crate::{Args, utils};
+use crate::args::TuiArgs;
+use crate::config::Config;
+use crate::{config, json, utils};
+use clap::ValueEnum;
 use crossterm::event::{
-    DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, MouseEventKind,
+    DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture, Event,
+    KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEventKind,
 };
 use crossterm::terminal::{
     EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode,
 };
 use crossterm::{event, execute};
+use mqtt::{Packet, QoS, UdpFrame};
 use ratatui::backend::CrosstermBackend;
-use ratatui::layout::{Constraint, Direction, Layout, Rect};
-use ratatui::prelude::{Color, Line, Span, Style, Stylize};
+use ratatui::layout::{Constraint, Direction, Layout, Margin, Position, Rect};
+use ratatui::prelude::{Line, Span, Style, Stylize};
 use ratatui::widgets::{
     Block, Borders, List, ListItem, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState,
+    Sparkline,
 };
 use ratatui::{Frame, Terminal};
+use std::collections::{HashMap, VecDeque};
+use std::fs::{File, OpenOptions};
 use std::io;
-use std::io::{ErrorKind, stdout};
-use std::net::UdpSocket;
-use std::sync::atomic::{AtomicU16, Ordering};
+use std::io::{ErrorKind, Write, stdout};
+use std::net::{SocketAddr, UdpSocket};
 use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
-use std::time::Duration;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime};
+use unicode_width::UnicodeWidthStr;
 
-use crate::InputMode;
+use crate::framing::Framing;
+use crate::parse::parse_payload;
+use crate::{InputMode, TextEncoding, Theme};
 
-mod format;
-mod parse;
+mod complete;
+mod diff;
+mod fields;
+pub(crate) mod format;
+mod help;
+mod hexedit;
+mod history;
+mod input;
+mod macros;
+mod rate;
+mod search;
+mod select;
+mod session;
+mod show;
+mod status;
+mod theme;
+mod timers;
 
-static MSG_ID_COUNTER: AtomicU16 = AtomicU16::new(1);
+/// The tallest the input box can be made with Ctrl+Up, borders included.
+const MAX_INPUT_HEIGHT: u16 = 12;
 
-fn next_msg_id() -> u16 {
-    MSG_ID_COUNTER.fetch_add(1, Ordering::Relaxed)
-}
+/// Sent msg_ids remembered for round trip times before old ones are dropped.
+const MAX_IN_FLIGHT: usize = 1024;
 
 struct LogEntry {
     display: String,
     style: Style,
     payload: Option<(InputMode, Vec<u8>)>, // Original mode + data for replay
+    time: SystemTime,
+    /// The split view's column; `None` spans both.
+    side: Option<Side>,
+    /// The mode the preview was re-rendered in with Alt+V, if not the payload's own.
+    view: Option<InputMode>,
+    /// The line a sent payload was typed as, and its mode, for Alt+E to load back.
+    source: Option<(InputMode, String)>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Sent,
+    Received,
+}
+
+/// A sent QoS 1/2 publish waiting for its PUBACK: its log entry and the style to restore.
+struct Unacked {
+    msg_id: u16,
+    index: usize,
+    sent: SystemTime,
+    style: Style,
 }
 
+/// A received datagram, its source when not the target, when it arrived, and the round trip
+/// from the send it answers.
+type Held = (Vec<u8>, Option<SocketAddr>, SystemTime, Option<Duration>);
+
 struct App {
     tx: Sender<NetCommand>,
     rx: Receiver<NetEvent>,
-    input: String,
+    network_thread: Option<JoinHandle<()>>,
+    net_alive: bool,
+    net: NetConfig,
+    local_addr: Option<SocketAddr>,
+    /// The target as resolved by the network thread.
+    peer_addr: Option<SocketAddr>,
+    settings: Vec<(&'static str, String)>,
+    send_strategy: SendStrategy,
+    mqtt: session::MqttSession,
+    /// When each msg_id was last sent, until a frame with the same msg_id comes back.
+    in_flight: HashMap<u16, Instant>,
+    /// QoS 1/2 publishes sent and not yet acknowledged, drawn as warnings until they are.
+    unacked: Vec<Unacked>,
+    /// `--ack-timeout`: how long a publish waits for its PUBACK before it is drawn as lost.
+    ack_timeout: Duration,
+    /// Show the MQTT session panel.
+    session_panel: bool,
+    dump_on_error: bool,
+    split_newlines: bool,
+    text_encoding: TextEncoding,
+    line_format: Option<String>,
+    log_file: Option<File>,
+    input: input::InputLine,
     input_mode: InputMode,
-    log: Vec<LogEntry>,
+    history: history::History,
+    /// The history entry shown in the input while walking it with Up/Down, and the line that
+    /// was being typed before the walk started.
+    history_cursor: Option<(usize, String)>,
+    macros: macros::Macros,
+    /// `topic_color` settings: PUBLISH entries for these topic filters get these colors.
+    topic_colors: Vec<(String, ratatui::style::Color)>,
+    timers: timers::Timers,
+    log: VecDeque<LogEntry>,
+    /// Bytes of payload held by `log`, for `--max-log-mb`.
+    log_bytes: usize,
+    max_log: usize,
+    max_log_bytes: usize,
     log_area: Rect,
     scroll_offset: usize,
+    /// Keep the newest entry in view. Scrolling up turns this off, scrolling to the bottom back on.
+    follow: bool,
+    /// Entries logged below the view since following stopped.
+    unseen: usize,
+    /// `--bell`: ring once for the first datagram to arrive out of view.
+    bell: bool,
+    rang: bool,
+    /// The log entry shown in the inspector pane; `None` follows the newest payload.
+    selected: Option<usize>,
+    /// Shift+Tab: keys move the selection and Enter resends it, instead of editing the input.
+    /// With vim keys this is normal mode.
+    log_focus: bool,
+    /// `keys = vim`: Esc leaves the input for normal mode instead of quitting.
+    vim: bool,
+    /// The first key of a two-key normal mode command, `gg` or `yy`.
+    pending: Option<char>,
+    /// Text being dragged over with the mouse; copied when the button is released.
+    drag: Option<select::Selection>,
+    inspector: bool,
+    search: Option<search::Search>,
+    /// Received datagrams held back from the log while the display is paused.
+    paused: Option<Vec<Held>>,
+    timestamps: Timestamps,
+    help: bool,
+    /// The Ctrl+E compose popup. Its buffer is kept when it closes.
+    compose: Option<hexedit::HexEditor>,
+    compose_draft: Vec<u8>,
+    /// The inspector's MQTT field tree: what's collapsed and the field under the cursor.
+    fields: fields::Tree,
+    /// Up to two entries marked with Alt+M for Alt+D to compare.
+    marks: Vec<usize>,
+    diff: Option<diff::Diff>,
+    /// Entries starred with Alt+B, in log order.
+    bookmarks: Vec<usize>,
+    /// `:bookmark only`: the log shows just the bookmarked entries.
+    bookmarks_only: bool,
+    /// `:show`: the log lists only sent, received, error or one type of MQTT entries.
+    show: Option<show::Show>,
+    theme: theme::Palette,
+    traffic: rate::Traffic,
+    /// Plot bytes rather than packets per second.
+    rate_bytes: bool,
+    /// Show sent and received entries in separate columns.
+    split: bool,
+    /// F6: wrap long entries over several rows instead of cutting them at the pane edge.
+    wrap: bool,
+    /// Columns the log is scrolled right by, to reach the tail of entries too wide to show.
+    hscroll: usize,
+    /// Width of the side column set with Ctrl+Left/Right; `None` sizes it to what's open.
+    detail_width: Option<u16>,
+    /// Height of the input box, borders included, set with Ctrl+Up/Down.
+    input_height: u16,
+    /// Alt+Enter: Enter adds a line to the input and Ctrl+S sends it all as one payload.
+    multiline: bool,
+    counters: status::Counters,
     running: bool,
 }
 
 enum NetCommand {
-    Send { mode: InputMode, input: String },
+    /// Typed input; `to` is an `@host:port` override for this one datagram. `source` is the
+    /// line as typed, prefix included.
+    Send {
+        mode: InputMode,
+        input: String,
+        to: Option<String>,
+        source: String,
+    },
+    SendRaw {
+        mode: InputMode,
+        data: Vec<u8>,
+    },
+    SetStrategy(SendStrategy),
     Shutdown,
 }
 
+/// What the log shows before each entry.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Timestamps {
+    Off,
+    /// Wall-clock time of day.
+    Absolute,
+    /// Time since the previous entry.
+    Relative,
+}
+
+impl Timestamps {
+    fn next(self) -> Self {
+        match self {
+            Timestamps::Off => Timestamps::Absolute,
+            Timestamps::Absolute => Timestamps::Relative,
+            Timestamps::Relative => Timestamps::Off,
+        }
+    }
+}
+
+/// How the network thread addresses outgoing datagrams.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SendStrategy {
+    /// `send_to` the target from the main socket.
+    Connected,
+    /// `send_to` the broadcast address from a separate broadcast-enabled socket.
+    Broadcast,
+}
+
 enum NetEvent {
+    /// `to` is set when the datagram went somewhere other than the target; `source` when it
+    /// was typed rather than replayed. `at` is when it left the socket.
     Sent {
         mode: InputMode,
         data: Vec<u8>,
         sent: usize,
+        to: Option<SocketAddr>,
+        source: Option<(InputMode, String)>,
+        at: Instant,
+    },
+    /// The socket's local address and the target it resolved to.
+    Bound {
+        local: SocketAddr,
+        peer: SocketAddr,
+    },
+    /// `from` is set when the datagram came from somewhere other than the target. `at` is when
+    /// the socket handed it over.
+    Received {
+        data: Vec<u8>,
+        from: Option<SocketAddr>,
+        at: Instant,
     },
-    Received(Vec<u8>),
     Error(String),
 }
 
-pub(crate) fn parse_payload(mode: InputMode, input: &str) -> Result<(InputMode, Vec<u8>), String> {
-    match mode {
-        InputMode::Auto => {
-            if let Ok(frame) = parse::parse_mqtt_command(input) {
-                return Ok((InputMode::Mqtt, frame.encode()));
-            }
-            if let Ok(hex) = utils::parse_hex(input) {
-                return Ok((InputMode::Hex, hex));
-            }
-            Ok((InputMode::Text, utils::parse_text_with_escapes(input)))
-        }
-        InputMode::Mqtt => {
-            parse::parse_mqtt_command(input).map(|frame| (InputMode::Mqtt, frame.encode()))
+/// Everything the network thread needs to (re)create its socket and frame datagrams.
+#[derive(Clone)]
+struct NetConfig {
+    bind: String,
+    reuse_addr: bool,
+    reuse_port: bool,
+    target: String,
+    broadcast: String,
+    framing: Framing,
+}
+
+impl NetConfig {
+    fn from_args(args: &TuiArgs) -> Self {
+        Self {
+            bind: args.socket.local(),
+            reuse_addr: args.socket.reuse.reuse_addr,
+            reuse_port: args.socket.reuse.reuse_port,
+            target: args.target.clone(),
+            broadcast: args
+                .broadcast
+                .clone()
+                .unwrap_or_else(|| default_broadcast(&args.target)),
+            framing: args.framing.clone(),
         }
-        InputMode::Hex => utils::parse_hex(input).map(|hex| (InputMode::Hex, hex)),
-        InputMode::Text => Ok((InputMode::Text, utils::parse_text_with_escapes(input))),
     }
 }
 
-fn run_network_thread(
-    bind: String,
-    target: String,
-    rx_cmd: Receiver<NetCommand>,
-    tx_evt: Sender<NetEvent>,
-) {
-    let socket = match UdpSocket::bind(&bind) {
+/// The limited broadcast address on the target's port.
+fn default_broadcast(target: &str) -> String {
+    let port = target.rsplit_once(':').map_or("0", |(_, port)| port);
+    format!("255.255.255.255:{}", port)
+}
+
+fn spawn_network_thread(
+    config: &NetConfig,
+) -> (Sender<NetCommand>, Receiver<NetEvent>, JoinHandle<()>) {
+    let (tx_cmd, rx_cmd) = mpsc::channel::<NetCommand>();
+    let (tx_evt, rx_evt) = mpsc::channel::<NetEvent>();
+    let config = config.clone();
+    let handle = std::thread::spawn(move || run_network_thread(config, rx_cmd, tx_evt));
+    (tx_cmd, rx_evt, handle)
+}
+
+fn run_network_thread(config: NetConfig, rx_cmd: Receiver<NetCommand>, tx_evt: Sender<NetEvent>) {
+    let socket = match utils::bind_udp(&config.bind, config.reuse_addr, config.reuse_port) {
         Ok(socket) => socket,
         Err(err) => {
             let _ = tx_evt.send(NetEvent::Error(format!("Bind failed: {}", err)));
@@ -96,9 +312,19 @@ fn run_network_thread(
         }
     };
 
-    if let Err(err) = socket.connect(&target) {
-        let _ = tx_evt.send(NetEvent::Error(format!("Connect failed: {}", err)));
-        return;
+    let target = match utils::resolve(&config.target) {
+        Ok(target) => target,
+        Err(err) => {
+            let _ = tx_evt.send(NetEvent::Error(err.to_string()));
+            return;
+        }
+    };
+
+    if let Ok(local) = socket.local_addr() {
+        let _ = tx_evt.send(NetEvent::Bound {
+            local,
+            peer: target,
+        });
     }
 
     if let Err(err) = socket.set_nonblocking(true) {
@@ -109,27 +335,79 @@ fn run_network_thread(
         return;
     }
 
-    let mut buffer = [0u8; 4096];
+    let mut strategy = SendStrategy::Connected;
+    let mut broadcast_socket: Option<UdpSocket> = None;
+    let mut buffer = [0u8; utils::RECV_BUFFER];
     loop {
         loop {
-            let (mode, data) = match rx_cmd.try_recv() {
-                Ok(NetCommand::Send { mode, input }) => match parse_payload(mode, &input) {
-                    Ok(data) => data,
-                    Err(err) => {
-                        if tx_evt.send(NetEvent::Error(err)).is_err() {
-                            return;
+            let (mode, data, to, source) = match rx_cmd.try_recv() {
+                Ok(NetCommand::Send {
+                    mode,
+                    input,
+                    to,
+                    source,
+                }) => {
+                    let prepared = to
+                        .as_deref()
+                        .map(utils::resolve)
+                        .transpose()
+                        .map_err(|err| err.to_string())
+                        .and_then(|to| {
+                            parse_payload(mode, &input)
+                                .map(|(parsed, data)| (parsed, data, to, Some((mode, source))))
+                        });
+                    match prepared {
+                        Ok(prepared) => prepared,
+                        Err(err) => {
+                            if tx_evt.send(NetEvent::Error(err)).is_err() {
+                                return;
+                            }
+                            continue;
                         }
-                        continue;
                     }
-                },
+                }
+                Ok(NetCommand::SendRaw { mode, data }) => (mode, data, None, None),
+                Ok(NetCommand::SetStrategy(next)) => {
+                    strategy = next;
+                    continue;
+                }
                 Ok(NetCommand::Shutdown) => return,
                 Err(TryRecvError::Empty) => break,
                 Err(TryRecvError::Disconnected) => return,
             };
 
-            match socket.send(&data) {
+            let framed = match config.framing.apply(&data) {
+                Ok(framed) => framed,
+                Err(err) => {
+                    if tx_evt.send(NetEvent::Error(err)).is_err() {
+                        return;
+                    }
+                    continue;
+                }
+            };
+
+            // An `@host:port` override wins over the send strategy.
+            let result = match (to, strategy) {
+                (Some(to), _) => socket.send_to(&framed, to),
+                (None, SendStrategy::Connected) => socket.send_to(&framed, target),
+                (None, SendStrategy::Broadcast) => {
+                    open_broadcast_socket(&mut broadcast_socket, &config)
+                        .and_then(|socket| socket.send_to(&framed, &config.broadcast))
+                }
+            };
+
+            let at = Instant::now();
+            match result {
                 Ok(sent) => {
-                    if tx_evt.send(NetEvent::Sent { mode, data, sent }).is_err() {
+                    let event = NetEvent::Sent {
+                        mode,
+                        data,
+                        sent,
+                        to,
+                        source,
+                        at,
+                    };
+                    if tx_evt.send(event).is_err() {
                         return;
                     }
                 }
@@ -144,33 +422,15 @@ fn run_network_thread(
             };
         }
 
-        match socket.recv(&mut buffer) {
-            Ok(n) => {
-                if tx_evt
-                    .send(NetEvent::Received(buffer[..n].to_vec()))
-                    .is_err()
-                {
-                    return;
-                }
-            }
-            Err(err) if err.kind() == ErrorKind::WouldBlock => {}
-            Err(err) if err.kind() == ErrorKind::ConnectionRefused => {
-                if tx_evt
-                    .send(NetEvent::Error(
-                        "ICMP: Connection refused (port unreachable)".to_string(),
-                    ))
-                    .is_err()
-                {
-                    return;
-                }
-            }
-            Err(err) => {
-                if tx_evt
-                    .send(NetEvent::Error(format!("Receive failed: {}", err)))
-                    .is_err()
-                {
-                    return;
-                }
+        let result = socket.recv_from(&mut buffer);
+        if !forward_recv(result, &buffer, target, &config, &tx_evt) {
+            return;
+        }
+
+        if let Some(broadcast) = &broadcast_socket {
+            let result = broadcast.recv_from(&mut buffer);
+            if !forward_recv(result, &buffer, target, &config, &tx_evt) {
+                return;
             }
         }
 
@@ -178,229 +438,2558 @@ fn run_network_thread(
     }
 }
 
+fn open_broadcast_socket<'a>(
+    slot: &'a mut Option<UdpSocket>,
+    config: &NetConfig,
+) -> io::Result<&'a UdpSocket> {
+    if let Some(socket) = slot {
+        return Ok(socket);
+    }
+
+    // Same interface as the main socket, but its own ephemeral port and never connected.
+    let mut addr: SocketAddr = config
+        .bind
+        .parse()
+        .unwrap_or_else(|_| SocketAddr::from(([0, 0, 0, 0], 0)));
+    addr.set_port(0);
+
+    let socket = UdpSocket::bind(addr)?;
+    socket.set_broadcast(true)?;
+    socket.set_nonblocking(true)?;
+    Ok(slot.insert(socket))
+}
+
+/// Report the outcome of a non-blocking receive. Returns false once the UI has gone away.
+fn forward_recv(
+    result: io::Result<(usize, SocketAddr)>,
+    buffer: &[u8],
+    target: SocketAddr,
+    config: &NetConfig,
+    tx_evt: &Sender<NetEvent>,
+) -> bool {
+    let event = match result {
+        Ok((n, from)) => NetEvent::Received {
+            data: config.framing.strip(&buffer[..n]).to_vec(),
+            from: (from != target).then_some(from),
+            at: Instant::now(),
+        },
+        Err(err) if err.kind() == ErrorKind::WouldBlock => return true,
+        Err(err) if err.kind() == ErrorKind::ConnectionRefused => {
+            NetEvent::Error("ICMP: Connection refused (port unreachable)".to_string())
+        }
+        Err(err) => NetEvent::Error(format!("Receive failed: {}", err)),
+    };
+    tx_evt.send(event).is_ok()
+}
+
 impl App {
-    fn new(tx: Sender<NetCommand>, rx: Receiver<NetEvent>) -> Self {
-        Self {
+    fn new(args: &TuiArgs, config: Config) -> Self {
+        let net = NetConfig::from_args(args);
+        let (tx, rx, handle) = spawn_network_thread(&net);
+        let theme = theme::Palette::new(args.theme.or(config.theme).unwrap_or_else(Theme::detect));
+        let (macros, macro_errors) = macros::Macros::load(&config.macros);
+        let mut app = Self {
             tx,
             rx,
-            input: String::new(),
+            network_thread: Some(handle),
+            net_alive: true,
+            net,
+            local_addr: None,
+            peer_addr: None,
+            settings: config::describe_tui(args, &config),
+            send_strategy: SendStrategy::Connected,
+            mqtt: session::MqttSession::new(),
+            in_flight: HashMap::new(),
+            unacked: vec![],
+            ack_timeout: args.ack_timeout,
+            session_panel: false,
+            dump_on_error: args.dump_on_error,
+            split_newlines: !args.literal_newlines,
+            text_encoding: args.text_encoding,
+            line_format: args.send_format.clone(),
+            log_file: None,
+            input: input::InputLine::default(),
             input_mode: InputMode::Auto,
-            log: vec![LogEntry {
-                display: "Ready. Tab=mode, Enter=send, Esc=quit".into(),
-                style: Style::default().dim(),
+            history: history::History::in_memory(),
+            history_cursor: None,
+            macros,
+            topic_colors: config.topic_colors,
+            timers: timers::Timers::default(),
+            log: VecDeque::from([LogEntry {
+                display: "Ready. ?=help, Tab=mode, Enter=send, Up/Down=history, F2=inspector, Ctrl+B=broadcast, Esc=quit".into(),
+                style: theme.hint,
                 payload: None,
-            }],
+                time: SystemTime::now(),
+                side: None,
+                view: None,
+                source: None,
+            }]),
+            log_bytes: 0,
+            max_log: args.max_log.max(1),
+            max_log_bytes: args.max_log_mb.saturating_mul(1 << 20),
             log_area: Rect::default(),
             scroll_offset: 0,
+            follow: true,
+            unseen: 0,
+            bell: args.bell,
+            rang: false,
+            selected: None,
+            log_focus: config.vim,
+            vim: config.vim,
+            pending: None,
+            drag: None,
+            inspector: false,
+            search: None,
+            paused: None,
+            timestamps: Timestamps::Off,
+            help: false,
+            compose: None,
+            fields: fields::Tree::default(),
+            marks: vec![],
+            bookmarks: vec![],
+            bookmarks_only: false,
+            show: None,
+            diff: None,
+            compose_draft: vec![],
+            theme,
+            traffic: rate::Traffic::new(60),
+            rate_bytes: false,
+            split: false,
+            wrap: false,
+            hscroll: 0,
+            detail_width: config.detail_width,
+            input_height: config.input_height.unwrap_or(3).clamp(3, MAX_INPUT_HEIGHT),
+            multiline: false,
+            counters: status::Counters::new(),
             running: true,
+        };
+
+        if let Some(path) = &args.log_file {
+            match OpenOptions::new().create(true).append(true).open(path) {
+                Ok(file) => app.log_file = Some(file),
+                Err(err) => app.log_error(format!("Log file {}: {}", path.display(), err)),
+            }
+        }
+
+        for err in macro_errors {
+            app.log_error(err);
         }
+
+        if !args.no_history {
+            match history::History::load() {
+                Ok(history) => app.history = history,
+                Err(err) => app.log_error(format!("History not saved: {}", err)),
+            }
+        }
+
+        app
     }
 
     fn log_error(&mut self, msg: impl Into<String>) {
-        self.log_msg(
-            format!("✗ {}", msg.into()),
-            Style::default().fg(Color::Red),
-            None,
-        );
+        self.log_msg(format!("✗ {}", msg.into()), self.theme.error, None);
         self.input.clear();
     }
 
     fn log_msg(&mut self, display: String, style: Style, payload: Option<(InputMode, Vec<u8>)>) {
-        self.log.push(LogEntry {
+        self.log_msg_at(SystemTime::now(), None, display, style, payload);
+    }
+
+    fn log_msg_at(
+        &mut self,
+        time: SystemTime,
+        side: Option<Side>,
+        display: String,
+        style: Style,
+        payload: Option<(InputMode, Vec<u8>)>,
+    ) {
+        let tee_result = match &mut self.log_file {
+            Some(file) => writeln!(file, "{} {}", utils::timestamp(time), display),
+            None => Ok(()),
+        };
+
+        let size = payload.as_ref().map_or(0, |(_, data)| data.len());
+        self.make_room(size);
+        self.log_bytes += size;
+
+        let index = self.log.len();
+        if let Some(search) = &mut self.search {
+            search.push(index, &display);
+        }
+        self.log.push_back(LogEntry {
             display,
             style,
             payload,
+            time,
+            side,
+            view: None,
+            source: None,
         });
+        if let Some(show) = &mut self.show {
+            show.push(index, &self.log[index], &self.theme);
+        }
 
-        let visible = self.log_area.height.saturating_sub(2) as usize;
-        if self.log.len() > visible {
-            self.scroll_offset = self.log.len() - visible;
+        if !self.follow && self.row_entry(self.row_count().saturating_sub(1)) == Some(index) {
+            self.unseen += 1;
+            if self.bell && !self.rang && side == Some(Side::Received) {
+                self.rang = true;
+                let mut out = stdout();
+                let _ = out.write_all(b"\x07").and_then(|()| out.flush());
+            }
+        }
+
+        if let Err(err) = tee_result {
+            self.log_file = None;
+            self.log_msg(
+                format!("✗ Log file write failed, tee disabled: {}", err),
+                self.theme.error,
+                None,
+            );
         }
     }
 
     fn send(&mut self) {
-        let input = std::mem::take(&mut self.input);
+        let input = self.input.take();
+        self.history_cursor = None;
 
         if input.is_empty() {
             return;
         }
 
-        let mode = self.input_mode;
+        if let Some(command) = input.strip_prefix(':')
+            && !command.starts_with(':')
+        {
+            self.remember(&input);
+            self.run_command(command.trim());
+            return;
+        }
 
-        if let Err(err) = self.tx.send(NetCommand::Send { mode, input }) {
-            self.log_error(format!("Network thread unavailable: {}", err));
-            self.running = false;
+        if self.multiline || !self.split_newlines {
+            self.dispatch(input);
             return;
         }
+
+        for line in input.lines().filter(|line| !line.trim().is_empty()) {
+            if !self.net_alive {
+                break;
+            }
+            self.dispatch(line.to_string());
+        }
     }
 
-    fn on_sent(&mut self, mode: InputMode, data: Vec<u8>, n: usize) {
-        let display = format::format_for_mode(mode, &data);
+    /// Send one line of input. A leading `@host:port` sends just this datagram elsewhere (any
+    /// other `@word` is sent as typed), and a leading `::` sends a literal `:`.
+    fn dispatch(&mut self, input: String) {
+        self.remember(&input);
+        self.macros.record(self.input_mode, &input, Instant::now());
+        self.send_line(self.input_mode, input);
+    }
 
-        self.log_msg(
-            format!("→ [{}] {} bytes: {}", mode.short_label(), n, display),
-            Style::default().fg(Color::Cyan),
-            Some((mode, data)),
-        );
+    fn send_line(&mut self, mode: InputMode, input: String) {
+        let source = input.clone();
+        let input = match input.strip_prefix("::") {
+            Some(rest) => format!(":{}", rest),
+            None => input,
+        };
+        let (to, input) = match input
+            .strip_prefix('@')
+            .and_then(|rest| rest.split_once(' '))
+            .filter(|(to, _)| utils::is_address(to))
+        {
+            Some((to, rest)) => (Some(to.to_string()), rest.trim_start().to_string()),
+            None => (None, input),
+        };
+        self.submit(NetCommand::Send {
+            mode,
+            input,
+            to,
+            source,
+        });
     }
 
-    fn drain_net_events(&mut self) {
-        loop {
-            match self.rx.try_recv() {
-                Ok(NetEvent::Sent { mode, data, sent }) => self.on_sent(mode, data, sent),
-                Ok(NetEvent::Received(raw)) => {
-                    let mode = self.input_mode;
-                    let display = format::format_for_mode(mode, &raw);
+    /// Step back to the previous input submitted in the current mode.
+    fn history_up(&mut self) {
+        let before = self.history_cursor.as_ref().map(|(index, _)| *index);
+        let Some(index) = self.history.previous(self.input_mode, before) else {
+            return;
+        };
+        let draft = match self.history_cursor.take() {
+            Some((_, draft)) => draft,
+            None => self.input.take(),
+        };
+        self.input.set(self.history.entries()[index].1.clone());
+        self.history_cursor = Some((index, draft));
+    }
+
+    /// Step forward again, ending on the line that was being typed.
+    fn history_down(&mut self) {
+        let Some((index, draft)) = self.history_cursor.take() else {
+            return;
+        };
+        match self.history.next(self.input_mode, index) {
+            Some(next) => {
+                self.input.set(self.history.entries()[next].1.clone());
+                self.history_cursor = Some((next, draft));
+            }
+            None => self.input.set(draft),
+        }
+    }
+
+    fn remember(&mut self, input: &str) {
+        if let Err(err) = self.history.push(self.input_mode, input) {
+            self.log_error(format!("History write failed, no longer saving: {}", err));
+        }
+    }
+
+    fn submit(&mut self, command: NetCommand) {
+        if let Err(err) = self.tx.send(command) {
+            self.log_error(format!("Network thread unavailable: {}", err));
+            self.on_network_died();
+        }
+    }
+
+    fn run_command(&mut self, command: &str) {
+        let (command, arg) = command
+            .split_once(' ')
+            .map_or((command, ""), |(command, arg)| (command, arg.trim()));
+        match command {
+            "target" | "bind" if arg.is_empty() => {
+                self.log_error(format!("usage: :{} <host:port>", command))
+            }
+            "target" => self.retarget(arg),
+            "macro" => self.macro_command(arg),
+            "every" => self.every(arg),
+            "timer" => self.timer_command(arg),
+            "bookmark" => self.bookmark_command(arg),
+            "timers" => {
+                let now = Instant::now();
+                let lines: Vec<String> =
+                    self.timers.list().iter().map(|t| t.describe(now)).collect();
+                if lines.is_empty() {
                     self.log_msg(
-                        format!("← {} bytes: {}", raw.len(), display),
-                        Style::default().fg(Color::Green),
-                        Some((mode, raw)),
+                        "No timers; :every <interval> <input> starts one".into(),
+                        self.theme.info,
+                        None,
                     );
                 }
-                Ok(NetEvent::Error(err)) => {
-                    self.log_msg(format!("✗ {}", err), Style::default().fg(Color::Red), None);
+                for line in lines {
+                    self.log_msg(line, self.theme.info, None);
                 }
-                Err(TryRecvError::Empty) => break,
-                Err(TryRecvError::Disconnected) => {
-                    self.log_error("Network thread disconnected");
-                    self.running = false;
-                    break;
+            }
+            "bind" => self.rebind(arg),
+            "config" => {
+                for line in config::render(&self.effective_config()) {
+                    self.log_msg(line, self.theme.info, None);
+                }
+            }
+            "help" => self.help = true,
+            "history" => {
+                let entries = self.history.entries();
+                let recent = &entries[entries.len().saturating_sub(20)..];
+                let lines: Vec<String> = recent
+                    .iter()
+                    .map(|(mode, input)| format!("  [{}] {}", mode.short_label(), input))
+                    .collect();
+                for line in lines {
+                    self.log_msg(line, self.theme.info, None);
                 }
             }
+            "mode" => match InputMode::from_str(arg, true) {
+                Ok(mode) => {
+                    self.history_cursor = None;
+                    self.input_mode = mode;
+                }
+                Err(_) => self.log_error("usage: :mode auto|text|hex|mqtt|base64"),
+            },
+            "filter" => self.filter(arg),
+            "clear" => self.clear_log(),
+            "save" => self.save_log(if arg.is_empty() { "udd-log.txt" } else { arg }),
+            "export" => self.export(arg),
+            "show" => self.show_command(arg),
+            "quit" | "q" => self.running = false,
+            _ => self.log_error(format!("unknown command: :{}", command)),
         }
     }
 
-    fn cycle_mode(&mut self) {
-        self.input_mode = match self.input_mode {
-            InputMode::Auto => InputMode::Text,
-            InputMode::Text => InputMode::Hex,
-            InputMode::Hex => InputMode::Mqtt,
-            InputMode::Mqtt => InputMode::Auto,
-        };
-    }
-
-    fn scroll(&mut self, delta: i16) {
-        let visible = self.log_area.height.saturating_sub(2) as usize;
-        let max_scroll = self.log.len().saturating_sub(visible);
+    /// Drop the oldest entries until one more with `size` bytes of payload fits under
+    /// `--max-log` and `--max-log-mb`, shifting every index into the log to match.
+    fn make_room(&mut self, size: usize) {
+        let mut evicted = 0;
+        while (self.log.len() >= self.max_log || self.log_bytes + size > self.max_log_bytes)
+            && let Some(entry) = self.log.pop_front()
+        {
+            self.log_bytes -= entry.payload.map_or(0, |(_, data)| data.len());
+            evicted += 1;
+        }
+        if evicted == 0 {
+            return;
+        }
 
-        if delta < 0 {
-            self.scroll_offset = self
-                .scroll_offset
-                .saturating_sub(delta.unsigned_abs() as usize);
-        } else {
-            self.scroll_offset = (self.scroll_offset + delta as usize).min(max_scroll);
+        // The view stays on the same entries: scroll rows count filtered entries only.
+        let rows = self
+            .filtered()
+            .map_or(evicted, |rows| rows.partition_point(|&i| i < evicted));
+        self.scroll_offset = self.scroll_offset.saturating_sub(rows);
+        self.selected = self.selected.and_then(|i| i.checked_sub(evicted));
+        for indices in [&mut self.marks, &mut self.bookmarks] {
+            indices.retain(|&i| i >= evicted);
+            indices.iter_mut().for_each(|i| *i -= evicted);
+        }
+        self.unacked.retain(|u| u.index >= evicted);
+        self.unacked.iter_mut().for_each(|u| u.index -= evicted);
+        if let Some(search) = &mut self.search {
+            search.evict(evicted);
+        }
+        if let Some(show) = &mut self.show {
+            show.evict(evicted);
         }
     }
-}
 
-pub fn run(args: &Args) -> io::Result<()> {
-    let (tx_cmd, rx_cmd) = mpsc::channel::<NetCommand>();
-    let (tx_evt, rx_evt) = mpsc::channel::<NetEvent>();
-    let bind = args.bind.clone();
-    let target = args.target.clone();
-    let network_thread =
-        std::thread::spawn(move || run_network_thread(bind, target, rx_cmd, tx_evt));
+    /// `:filter <query>` shows only the entries matching `query`, like `/` then Tab; `:filter`
+    /// alone shows everything again.
+    fn filter(&mut self, query: &str) {
+        if query.is_empty() {
+            self.search = None;
+            return;
+        }
+        let mut search = search::Search::new();
+        search.query.set(query.to_string());
+        search.editing = false;
+        search.filter = true;
+        search.update(self.log.iter().map(|e| e.display.as_str()));
+        if let Some(err) = search.error() {
+            self.log_error(format!("bad filter: {}", err));
+            return;
+        }
+        self.search = Some(search);
+        self.follow = true;
+    }
 
-    let mut app = App::new(tx_cmd, rx_evt);
+    /// `:show <what>` lists only sent, received or error entries, or MQTT frames of a type, or
+    /// several of those at once; `:show` or `:show all` lists everything again.
+    fn show_command(&mut self, arg: &str) {
+        if arg.is_empty() || arg == "all" {
+            self.show = None;
+            self.follow = true;
+            return;
+        }
+        match show::Show::parse(arg) {
+            Ok(mut show) => {
+                show.update(self.log.iter(), &self.theme);
+                self.show = Some(show);
+                self.follow = true;
+            }
+            Err(err) => self.log_error(format!(
+                "{}; :show sent|received|errors|<mqtt type>|all",
+                err
+            )),
+        }
+    }
 
-    enable_raw_mode()?;
-    execute!(stdout(), EnterAlternateScreen, EnableMouseCapture)?;
-    let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
+    /// `:clear` empties the log. Counters, history and the MQTT session are kept.
+    fn clear_log(&mut self) {
+        self.log.clear();
+        self.log_bytes = 0;
+        self.selected = None;
+        self.marks.clear();
+        self.bookmarks.clear();
+        self.unacked.clear();
+        self.scroll_offset = 0;
+        self.unseen = 0;
+        self.rang = false;
+        self.follow = true;
+        if let Some(search) = &mut self.search {
+            search.update(std::iter::empty());
+        }
+        if let Some(show) = &mut self.show {
+            show.update(std::iter::empty(), &self.theme);
+        }
+    }
 
-    let target = &args.target;
+    /// `:save [path]` writes the log, one timestamped line per entry, to `path`.
+    fn save_log(&mut self, path: &str) {
+        let result = File::create(path).and_then(|mut file| {
+            for entry in &self.log {
+                writeln!(file, "{} {}", utils::timestamp(entry.time), entry.display)?;
+            }
+            file.flush()
+        });
+        match result {
+            Ok(()) => self.log_msg(
+                format!("Saved {} log entries to {}", self.log.len(), path),
+                self.theme.info,
+                None,
+            ),
+            Err(err) => self.log_error(format!("Save to {} failed: {}", path, err)),
+        }
+    }
 
-    while app.running {
-        app.drain_net_events();
-        terminal.draw(|f| draw(f, &mut app, target))?;
+    /// `:export text|json|raw [path]` writes the entries from one Alt+M mark to the other: log
+    /// lines, one JSON object per entry (replayable with `udd replay`), or a directory with each
+    /// payload in its own file.
+    fn export(&mut self, arg: &str) {
+        let (format, path) = arg
+            .split_once(' ')
+            .map_or((arg, ""), |(format, path)| (format, path.trim()));
+        let default = match format {
+            "text" => "udd-export.txt",
+            "json" => "udd-export.jsonl",
+            "raw" => "udd-export",
+            _ => return self.log_error("usage: :export text|json|raw [path]"),
+        };
+        let &[a, b] = self.marks.as_slice() else {
+            self.log_error("mark the first and last entries to export with Alt+M");
+            return;
+        };
+        let path = if path.is_empty() { default } else { path };
+        let range = a.min(b)..=a.max(b);
+        let entries = self.log.range(range.clone());
 
-        if !event::poll(Duration::from_millis(100))? {
-            continue;
+        let result = match format {
+            "text" => File::create(path).and_then(|mut file| {
+                for entry in entries {
+                    writeln!(file, "{} {}", utils::timestamp(entry.time), entry.display)?;
+                }
+                file.flush()
+            }),
+            "json" => File::create(path).and_then(|mut file| {
+                for entry in entries {
+                    let event = match entry.side {
+                        Some(Side::Sent) => "sent",
+                        Some(Side::Received) => "received",
+                        None => "log",
+                    };
+                    let mut object = json::Object::new()
+                        .str("event", event)
+                        .str("ts", &utils::rfc3339(entry.time))
+                        .str("line", &entry.display);
+                    if let Some((mode, data)) = &entry.payload {
+                        let hex: String = data.iter().map(|b| format!("{:02x}", b)).collect();
+                        object = object
+                            .str("mode", mode.short_label())
+                            .num("bytes", data.len() as f64)
+                            .str("hex", &hex);
+                        if let Some(mqtt) = format::mqtt_json(data) {
+                            object = object.raw("mqtt", &mqtt);
+                        }
+                    }
+                    writeln!(file, "{}", object.finish())?;
+                }
+                file.flush()
+            }),
+            _ => std::fs::create_dir_all(path).and_then(|()| {
+                for (n, (entry, (_, data))) in entries
+                    .filter_map(|e| e.payload.as_ref().map(|p| (e, p)))
+                    .enumerate()
+                {
+                    let side = match entry.side {
+                        Some(Side::Received) => "recv",
+                        _ => "sent",
+                    };
+                    let stamp = utils::timestamp(entry.time).replace(':', "");
+                    let name = format!("{:04}-{}-{}.bin", n + 1, side, stamp);
+                    std::fs::write(std::path::Path::new(path).join(name), data)?;
+                }
+                Ok(())
+            }),
+        };
+        match result {
+            Ok(()) => self.log_msg(
+                format!(
+                    "Exported {} log entries to {}",
+                    range.end() - range.start() + 1,
+                    path
+                ),
+                self.theme.info,
+                None,
+            ),
+            Err(err) => self.log_error(format!("Export to {} failed: {}", path, err)),
         }
+    }
 
-        match event::read()? {
-            Event::Key(key) if key.kind == KeyEventKind::Press => match key.code {
-                KeyCode::Esc => app.running = false,
-                KeyCode::Tab => app.cycle_mode(),
-                KeyCode::Enter => app.send(),
-                KeyCode::Backspace => {
-                    app.input.pop();
+    /// `:macro record|stop|run|delete <name>` and `:macro list`.
+    fn macro_command(&mut self, arg: &str) {
+        let (action, name) = arg
+            .split_once(' ')
+            .map_or((arg, ""), |(action, name)| (action, name.trim()));
+        match (action, name) {
+            ("record", "") | ("run", "") | ("delete", "") => {
+                self.log_error(format!("usage: :macro {} <name>", action))
+            }
+            ("record", name) => {
+                self.macros.start_recording(name);
+                self.log_msg(
+                    format!("● Recording macro {} (:macro stop to finish)", name),
+                    self.theme.info,
+                    None,
+                );
+            }
+            ("stop", _) if self.macros.stop_playing() => {
+                self.log_msg("Macro stopped".into(), self.theme.info, None)
+            }
+            ("stop", _) => match self.macros.stop_recording() {
+                None => self.log_error("not recording a macro"),
+                Some((name, steps)) if steps.is_empty() => {
+                    self.log_error(format!("nothing sent, macro {} not saved", name))
+                }
+                Some((name, steps)) => {
+                    let lines: Vec<String> = steps.iter().map(|s| s.to_string()).collect();
+                    match config::save_macro(&name, &lines) {
+                        Ok(()) => self.log_msg(
+                            format!("Saved macro {} ({} sends, F5 to replay)", name, steps.len()),
+                            self.theme.info,
+                            None,
+                        ),
+                        Err(err) => self.log_error(format!(
+                            "Macro {} kept for this session only: {}",
+                            name, err
+                        )),
+                    }
                 }
-                KeyCode::Char(c) => app.input.push(c),
-                _ => {}
-            },
-            Event::Mouse(mouse) => match mouse.kind {
-                MouseEventKind::ScrollUp => app.scroll(-3),
-                MouseEventKind::ScrollDown => app.scroll(3),
-                _ => {}
             },
-            _ => {}
+            ("run", name) => self.run_macro(name),
+            ("delete", name) if self.macros.remove(name) => {
+                if let Err(err) = config::save_macro(name, &[]) {
+                    self.log_error(format!(
+                        "Macro {} not removed from the config file: {}",
+                        name, err
+                    ));
+                }
+            }
+            ("delete", name) => self.log_error(format!("no macro named {}", name)),
+            ("list", _) => {
+                let lines: Vec<String> = self
+                    .macros
+                    .names()
+                    .map(|(name, steps)| format!("  {} ({} sends)", name, steps))
+                    .collect();
+                if lines.is_empty() {
+                    self.log_msg(
+                        "No macros; :macro record <name> starts one".into(),
+                        self.theme.info,
+                        None,
+                    );
+                }
+                for line in lines {
+                    self.log_msg(line, self.theme.info, None);
+                }
+            }
+            _ => self.log_error("usage: :macro record|stop|run|delete <name>, or :macro list"),
         }
     }
 
-    let _ = app.tx.send(NetCommand::Shutdown);
-    let _ = network_thread.join();
-
-    disable_raw_mode()?;
-    execute!(stdout(), LeaveAlternateScreen, DisableMouseCapture)?;
-    Ok(())
-}
+    fn run_macro(&mut self, name: &str) {
+        if !self.macros.play(name, Instant::now()) {
+            self.log_error(format!("no macro named {}", name));
+            return;
+        }
+        self.log_msg(format!("▶ Running macro {}", name), self.theme.info, None);
+    }
 
-fn draw(f: &mut Frame, app: &mut App, target: &str) {
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([Constraint::Min(5), Constraint::Length(3)])
-        .split(f.area());
+    /// Send whatever is due: the next step of a playing macro and any `:every` timers.
+    fn tick(&mut self, now: Instant) {
+        while let Some(step) = self.macros.due(now) {
+            self.send_line(step.mode, step.input);
+        }
+        for (mode, input) in self.timers.due(now) {
+            self.send_line(mode, input);
+        }
+        let now = SystemTime::now();
+        for unacked in &self.unacked {
+            if now.duration_since(unacked.sent).unwrap_or_default() >= self.ack_timeout {
+                self.log[unacked.index].style = self.theme.error;
+            }
+        }
+    }
 
-    // Store log area for click detection
-    app.log_area = chunks[0];
+    /// A PUBACK puts its publish's entry back to its own style.
+    fn on_puback(&mut self, data: &[u8]) {
+        let Ok(UdpFrame {
+            msg_id,
+            packet: Packet::PubAck(_),
+        }) = UdpFrame::decode(data)
+        else {
+            return;
+        };
+        if let Some(at) = self.unacked.iter().position(|u| u.msg_id == msg_id) {
+            let unacked = self.unacked.remove(at);
+            self.log[unacked.index].style = unacked.style;
+        }
+    }
 
-    // Log with scrolling
-    let visible_height = chunks[0].height.saturating_sub(2) as usize;
-    let items: Vec<ListItem> = app
-        .log
-        .iter()
-        .skip(app.scroll_offset)
-        .take(visible_height)
-        .map(|e| {
-            let style = if e.payload.is_some() {
-                e.style.underlined() // Indicate clickable
-            } else {
-                e.style
-            };
-            ListItem::new(e.display.as_str()).style(style)
-        })
-        .collect();
+    /// When `tick` next has something to send.
+    fn next_due(&self) -> Option<Instant> {
+        match (self.macros.next_due(), self.timers.next_due()) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
 
-    let log = List::new(items).block(
-        Block::default()
-            .borders(Borders::ALL)
-            .title("Log (click to replay, scroll to navigate)"),
-    );
-    f.render_widget(log, chunks[0]);
+    /// `:every <interval> <input>` sends `input` in the current mode on a repeating timer.
+    fn every(&mut self, arg: &str) {
+        let Some((interval, input)) = arg.split_once(' ') else {
+            self.log_error("usage: :every <interval> <input>");
+            return;
+        };
+        let every = match utils::parse_duration(interval) {
+            Ok(every) if !every.is_zero() => every,
+            Ok(_) => return self.log_error("the interval must be more than zero"),
+            Err(err) => return self.log_error(err),
+        };
+        let id = self.timers.add(
+            every,
+            self.input_mode,
+            input.trim().to_string(),
+            Instant::now(),
+        );
+        self.log_msg(
+            format!(
+                "⏱ Timer #{} every {:?}; :timer pause|resume|cancel {}",
+                id, every, id
+            ),
+            self.theme.info,
+            None,
+        );
+    }
+
+    /// `:bookmark next|prev|only|clear`.
+    fn bookmark_command(&mut self, arg: &str) {
+        match arg {
+            "next" | "prev" if self.bookmarks.is_empty() => {
+                self.log_error("no bookmarks; Alt+B stars the selected entry")
+            }
+            "next" => {
+                let from = self
+                    .selected
+                    .map_or(0, |i| self.bookmarks.partition_point(|&b| b <= i));
+                let index = *self.bookmarks.get(from).unwrap_or(&self.bookmarks[0]);
+                self.selected = Some(index);
+                self.scroll_to(index);
+            }
+            "prev" => {
+                let before = self.selected.map_or(self.bookmarks.len(), |i| {
+                    self.bookmarks.partition_point(|&b| b < i)
+                });
+                let index = match before {
+                    0 => self.bookmarks[self.bookmarks.len() - 1],
+                    n => self.bookmarks[n - 1],
+                };
+                self.selected = Some(index);
+                self.scroll_to(index);
+            }
+            "only" => {
+                self.bookmarks_only = !self.bookmarks_only;
+                self.follow = true;
+            }
+            "clear" => {
+                self.bookmarks.clear();
+                self.bookmarks_only = false;
+            }
+            _ => self.log_error("usage: :bookmark next|prev|only|clear"),
+        }
+    }
+
+    /// Alt+B: star the inspected entry, or unstar it.
+    fn toggle_bookmark(&mut self) {
+        let Some(index) = self.inspected() else {
+            self.log_error("no entry selected");
+            return;
+        };
+        match self.bookmarks.binary_search(&index) {
+            Ok(at) => {
+                self.bookmarks.remove(at);
+                self.scroll_offset = self.scroll_offset.min(self.bottom_offset());
+            }
+            Err(at) => self.bookmarks.insert(at, index),
+        }
+    }
+
+    /// `:timer pause|resume|cancel <id>`, or `:timer cancel all`.
+    fn timer_command(&mut self, arg: &str) {
+        let usage = "usage: :timer pause|resume|cancel <id>";
+        let (action, id) = arg
+            .split_once(' ')
+            .map_or((arg, ""), |(action, id)| (action, id.trim()));
+        if (action, id) == ("cancel", "all") {
+            let n = self.timers.cancel_all();
+            self.log_msg(format!("Cancelled {} timer(s)", n), self.theme.info, None);
+            return;
+        }
+        let Ok(id) = id.trim_start_matches('#').parse() else {
+            return self.log_error(usage);
+        };
+        let found = match action {
+            "pause" => self.timers.set_paused(id, true, Instant::now()),
+            "resume" => self.timers.set_paused(id, false, Instant::now()),
+            "cancel" => self.timers.cancel(id),
+            _ => return self.log_error(usage),
+        };
+        if !found {
+            self.log_error(format!("no timer #{}", id));
+        }
+    }
+
+    /// Startup settings, with the values that can change at runtime replaced by their live state.
+    fn effective_config(&self) -> Vec<(&'static str, String)> {
+        let mut settings = self.settings.clone();
+        for (key, value) in settings.iter_mut() {
+            match *key {
+                "bind" => *value = self.net.bind.clone(),
+                "target" => *value = self.net.target.clone(),
+                "mode" => *value = self.input_mode.short_label().into(),
+                "broadcast" => *value = self.net.broadcast.clone(),
+                "detail width" => *value = self.side_width().to_string(),
+                "input height" => *value = self.input_height.to_string(),
+                _ => {}
+            }
+        }
+
+        let local = self
+            .local_addr
+            .map_or_else(|| "-".into(), |addr| addr.to_string());
+        let strategy = match self.send_strategy {
+            SendStrategy::Connected => "connected",
+            SendStrategy::Broadcast => "broadcast",
+        };
+        settings.insert(1, ("local", local));
+        settings.insert(3, ("send", strategy.into()));
+        settings
+    }
+
+    /// Width of the column beside the log, 0 when no side panel is open.
+    fn side_width(&self) -> u16 {
+        match (self.inspector, self.session_panel) {
+            (false, false) => 0,
+            (true, _) => self.detail_width.unwrap_or(82),
+            (false, true) => self.detail_width.unwrap_or(50),
+        }
+    }
+
+    /// Ctrl+Left/Right: widen or narrow the side column by `delta` columns.
+    fn resize_detail(&mut self, delta: i16) {
+        if self.side_width() == 0 {
+            return;
+        }
+        let width = self
+            .side_width()
+            .saturating_add_signed(delta)
+            .clamp(20, 200);
+        self.detail_width = Some(width);
+        self.save_layout("detail_width", width);
+    }
+
+    /// Ctrl+Up/Down: grow or shrink the input box by `delta` rows.
+    fn resize_input(&mut self, delta: i16) {
+        let height = self
+            .input_height
+            .saturating_add_signed(delta)
+            .clamp(3, MAX_INPUT_HEIGHT);
+        if height != self.input_height {
+            self.input_height = height;
+            self.save_layout("input_height", height);
+        }
+    }
+
+    fn save_layout(&mut self, key: &str, size: u16) {
+        if let Err(err) = config::save_setting(key, &size.to_string()) {
+            self.log_error(format!("Layout kept for this session only: {}", err));
+        }
+    }
+
+    fn on_network_died(&mut self) {
+        if !self.net_alive {
+            return;
+        }
+        self.net_alive = false;
+        self.log_msg(
+            "Network thread died, press Ctrl+R to restart".into(),
+            self.theme.error.bold(),
+            None,
+        );
+    }
+
+    /// Stop the current network thread (if any) and spawn a fresh one with new channels.
+    fn restart_network(&mut self) {
+        self.shutdown_network();
+
+        let (tx, rx, handle) = spawn_network_thread(&self.net);
+        self.tx = tx;
+        self.rx = rx;
+        self.network_thread = Some(handle);
+        self.net_alive = true;
+        self.local_addr = None;
+        self.peer_addr = None;
+        if self.send_strategy != SendStrategy::Connected {
+            let _ = self.tx.send(NetCommand::SetStrategy(self.send_strategy));
+        }
+        self.log_msg(
+            format!(
+                "Network thread restarted ({} → {})",
+                self.net.bind, self.net.target
+            ),
+            self.theme.hint,
+            None,
+        );
+    }
+
+    /// Point the session at a new target, keeping the log. A broadcast address derived from the
+    /// old target's port follows it.
+    fn retarget(&mut self, target: &str) {
+        if let Err(err) = utils::resolve(target) {
+            self.log_error(format!("target {}: {}", target, err));
+            return;
+        }
+        if self.net.broadcast == default_broadcast(&self.net.target) {
+            self.net.broadcast = default_broadcast(target);
+        }
+        self.net.target = target.to_string();
+        self.restart_network();
+    }
+
+    /// Rebind the socket to a new local address, keeping the log.
+    fn rebind(&mut self, bind: &str) {
+        if let Err(err) = utils::resolve(bind) {
+            self.log_error(format!("bind {}: {}", bind, err));
+            return;
+        }
+        self.net.bind = bind.to_string();
+        self.restart_network();
+    }
+
+    fn shutdown_network(&mut self) {
+        let _ = self.tx.send(NetCommand::Shutdown);
+        if let Some(handle) = self.network_thread.take() {
+            let _ = handle.join();
+        }
+    }
+
+    /// A refused SUBACK's style, a PUBLISH's topic color, or else `default`.
+    fn payload_style(&self, data: &[u8], default: Style) -> Style {
+        if format::is_suback_refused(data) {
+            return self.theme.refused;
+        }
+        let Some(topic) = format::publish_topic(data).filter(|_| !self.theme.topics.is_empty())
+        else {
+            return default;
+        };
+        if let Some((_, color)) = self
+            .topic_colors
+            .iter()
+            .find(|(filter, _)| topic_matches(filter, &topic))
+        {
+            return Style::default().fg(*color);
+        }
+        // FNV-1a, so a topic keeps its color from one run to the next.
+        let hash = topic.bytes().fold(0xcbf29ce484222325u64, |hash, b| {
+            (hash ^ b as u64).wrapping_mul(0x100000001b3)
+        });
+        self.theme.topics[(hash % self.theme.topics.len() as u64) as usize]
+    }
+
+    fn on_sent(
+        &mut self,
+        mode: InputMode,
+        data: Vec<u8>,
+        n: usize,
+        to: Option<SocketAddr>,
+        at: Instant,
+    ) {
+        if let Ok(frame) = UdpFrame::decode(&data) {
+            // Frames that never get an answer would otherwise pile up.
+            if self.in_flight.len() >= MAX_IN_FLIGHT {
+                self.in_flight
+                    .retain(|_, sent| at.duration_since(*sent) < Duration::from_secs(60));
+            }
+            self.in_flight.insert(frame.msg_id, at);
+        }
+        let display = format::format_for_mode(mode, self.text_encoding, &data);
+        let style = self.payload_style(&data, self.theme.sent);
+
+        let warning = format::empty_publish_warning(&data);
+        let line = match &self.line_format {
+            Some(template) => format::render_template(template, "→", mode, n, &data, &display),
+            None => match to {
+                Some(to) => format!(
+                    "→ [{}] {} bytes to {}: {}",
+                    mode.short_label(),
+                    n,
+                    to,
+                    display
+                ),
+                None => format!("→ [{}] {} bytes: {}", mode.short_label(), n, display),
+            },
+        };
+
+        let qos = match UdpFrame::decode(&data) {
+            Ok(UdpFrame {
+                msg_id,
+                packet: Packet::Publish(p),
+            }) => Some((msg_id, p.qos)),
+            _ => None,
+        };
+        let sent = SystemTime::now();
+        self.log_msg_at(sent, Some(Side::Sent), line, style, Some((mode, data)));
+        if let Some((msg_id, qos)) = qos
+            && !matches!(qos, QoS::AtMostOnce)
+        {
+            let index = self.log.len() - 1;
+            self.log[index].style = self.theme.warning;
+            self.unacked.push(Unacked {
+                msg_id,
+                index,
+                sent,
+                style,
+            });
+        }
+
+        if let Some(warning) = warning {
+            self.log_msg(format!("⚠ {}", warning), self.theme.warning, None);
+        }
+    }
+
+    /// The round trip to a datagram arriving `at`, if it carries the msg_id of a frame sent.
+    fn round_trip(&mut self, data: &[u8], at: Instant) -> Option<Duration> {
+        let frame = UdpFrame::decode(data).ok()?;
+        let sent = self.in_flight.remove(&frame.msg_id)?;
+        Some(at.duration_since(sent))
+    }
+
+    fn on_received(
+        &mut self,
+        raw: Vec<u8>,
+        from: Option<SocketAddr>,
+        time: SystemTime,
+        rtt: Option<Duration>,
+    ) {
+        let mode = self.input_mode;
+
+        if self.dump_on_error
+            && let Err(err) = UdpFrame::decode(&raw)
+        {
+            let lines = utils::hexdump(&raw);
+            self.log_msg_at(
+                time,
+                Some(Side::Received),
+                format!("← {} bytes: decode failed: {:?}", raw.len(), err),
+                self.theme.error,
+                Some((mode, raw)),
+            );
+            for line in lines {
+                self.log_msg_at(
+                    time,
+                    Some(Side::Received),
+                    format!("    {}", line),
+                    self.theme.error_detail,
+                    None,
+                );
+            }
+            return;
+        }
+
+        let display = format::format_for_mode(mode, self.text_encoding, &raw);
+        let style = self.payload_style(&raw, self.theme.received);
+        let mut line = match &self.line_format {
+            Some(template) => {
+                format::render_template(template, "←", mode, raw.len(), &raw, &display)
+            }
+            None => match from {
+                Some(from) => format!("← {} bytes from {}: {}", raw.len(), from, display),
+                None => format!("← {} bytes: {}", raw.len(), display),
+            },
+        };
+        if let Some(rtt) = rtt {
+            line.push_str(&format!(" (+{})", format_gap(rtt)));
+        }
+        self.log_msg_at(time, Some(Side::Received), line, style, Some((mode, raw)));
+    }
+
+    fn drain_net_events(&mut self) {
+        loop {
+            match self.rx.try_recv() {
+                Ok(NetEvent::Sent {
+                    mode,
+                    data,
+                    sent,
+                    to,
+                    source,
+                    at,
+                }) => {
+                    self.counters.sent(sent);
+                    self.mqtt.sent(&data, SystemTime::now());
+                    self.traffic.sent(at, sent);
+                    self.on_sent(mode, data, sent, to, at);
+                    // The warning `on_sent` may add comes after the datagram's own entry.
+                    if let Some(entry) = self
+                        .log
+                        .iter_mut()
+                        .rev()
+                        .find(|e| e.side == Some(Side::Sent))
+                    {
+                        entry.source = source;
+                    }
+                }
+                Ok(NetEvent::Bound { local, peer }) => {
+                    self.local_addr = Some(local);
+                    self.peer_addr = Some(peer);
+                }
+                Ok(NetEvent::Received { data, from, at }) => {
+                    self.traffic.received(at, data.len());
+                    self.counters.received(data.len());
+                    let time = SystemTime::now();
+                    self.mqtt.received(&data, time);
+                    self.on_puback(&data);
+                    // A reply carries the msg_id of the frame it answers.
+                    let rtt = self.round_trip(&data, at);
+                    match &mut self.paused {
+                        Some(held) => held.push((data, from, time, rtt)),
+                        None => self.on_received(data, from, time, rtt),
+                    }
+                }
+                Ok(NetEvent::Error(err)) => {
+                    self.counters.errors += 1;
+                    self.log_msg(format!("✗ {}", err), self.theme.error, None);
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.on_network_died();
+                    break;
+                }
+            }
+        }
+    }
+
+    fn toggle_send_strategy(&mut self) {
+        self.send_strategy = match self.send_strategy {
+            SendStrategy::Connected => SendStrategy::Broadcast,
+            SendStrategy::Broadcast => SendStrategy::Connected,
+        };
+
+        if self
+            .tx
+            .send(NetCommand::SetStrategy(self.send_strategy))
+            .is_err()
+        {
+            self.on_network_died();
+            return;
+        }
+
+        let display = match self.send_strategy {
+            SendStrategy::Connected => format!("Sending to {} (connected)", self.net.target),
+            SendStrategy::Broadcast => format!("Sending to {} (broadcast)", self.net.broadcast),
+        };
+        self.log_msg(display, self.theme.hint, None);
+    }
+
+    fn cycle_mode(&mut self) {
+        self.history_cursor = None;
+        self.input_mode = match self.input_mode {
+            InputMode::Auto => InputMode::Text,
+            InputMode::Text => InputMode::Hex,
+            InputMode::Hex => InputMode::Mqtt,
+            InputMode::Mqtt => InputMode::Base64,
+            InputMode::Base64 => InputMode::Auto,
+        };
+    }
+
+    /// Complete the word before the cursor. A unique match is filled in with a trailing space
+    /// (none after `key=`); several are narrowed to their common prefix and listed in the log.
+    fn complete(&mut self) {
+        let (start, candidates) = complete::complete(self.input.before_cursor());
+        match candidates.as_slice() {
+            [] => {}
+            [only] => {
+                let space = if only.ends_with('=') { "" } else { " " };
+                self.input
+                    .replace_before_cursor(start, &format!("{}{}", only, space));
+            }
+            _ => {
+                let prefix = complete::common_prefix(&candidates);
+                if prefix.len() > self.input.before_cursor().len() - start {
+                    self.input.replace_before_cursor(start, prefix);
+                }
+                self.log_msg(
+                    format!("  {}", candidates.join("  ")),
+                    self.theme.info,
+                    None,
+                );
+            }
+        }
+    }
+
+    /// Index of the log entry drawn at screen position `(column, row)`.
+    fn entry_at(&self, column: u16, row: u16) -> Option<usize> {
+        let area = self.log_area;
+        let inside = column > area.x
+            && column < area.right().saturating_sub(1)
+            && row > area.y
+            && row < area.bottom().saturating_sub(1);
+        let mut line = row.checked_sub(area.y + 1)? as usize;
+        let mut row = self.scroll_offset;
+        loop {
+            let height = self.entry_height(self.row_entry(row)?);
+            if line < height {
+                break;
+            }
+            line -= height;
+            row += 1;
+        }
+        self.row_entry(row).filter(|_| inside)
+    }
+
+    /// The log indices shown when only bookmarks are shown, `:show` narrows the view, or the
+    /// search filter hides non-matching entries, in that order of precedence.
+    fn filtered(&self) -> Option<&[usize]> {
+        if self.bookmarks_only {
+            return Some(&self.bookmarks);
+        }
+        if let Some(show) = &self.show {
+            return Some(show.matches());
+        }
+        self.search
+            .as_ref()
+            .filter(|s| s.filter && s.is_active())
+            .map(|s| s.matches())
+    }
+
+    /// Number of rows the log pane can scroll through.
+    fn row_count(&self) -> usize {
+        self.filtered().map_or(self.log.len(), <[usize]>::len)
+    }
+
+    /// The log index shown on scroll row `row`.
+    fn row_entry(&self, row: usize) -> Option<usize> {
+        match self.filtered() {
+            Some(rows) => rows.get(row).copied(),
+            None => (row < self.log.len()).then_some(row),
+        }
+    }
+
+    /// The timestamp column in front of each entry, led by a ★ when it is bookmarked.
+    fn stamp(&self, index: usize) -> Option<String> {
+        let time = self.log[index].time;
+        let stamp = match self.timestamps {
+            Timestamps::Off => None,
+            Timestamps::Absolute => Some(utils::timestamp(time)),
+            Timestamps::Relative => {
+                let previous = index.checked_sub(1).map_or(time, |p| self.log[p].time);
+                let gap = time.duration_since(previous).unwrap_or_default();
+                Some(format!("{:>12}", format!("+{}", format_gap(gap))))
+            }
+        };
+        if self.bookmarks.binary_search(&index).is_err() {
+            return stamp;
+        }
+        Some(stamp.map_or_else(|| "★".into(), |stamp| format!("★ {}", stamp)))
+    }
+
+    /// The lines entry `index` takes up when wrapping, after its timestamp.
+    fn wrapped(&self, index: usize) -> Vec<&str> {
+        let stamp = self.stamp(index).map_or(0, |s| s.width() + 1);
+        let width = (self.log_area.width.saturating_sub(2) as usize).saturating_sub(stamp);
+        format::wrap(&self.log[index].display, width)
+    }
+
+    /// Rows of the log pane that entry `index` takes up.
+    fn entry_height(&self, index: usize) -> usize {
+        match self.wrap && !self.split {
+            true => self.wrapped(index).len(),
+            false => 1,
+        }
+    }
+
+    /// The first scroll row of a page whose last entry is on `row`.
+    fn page_ending_at(&self, row: usize) -> usize {
+        let visible = self.log_area.height.saturating_sub(2) as usize;
+        let mut used = 0;
+        let mut first = row + 1;
+        while let Some(previous) = first.checked_sub(1)
+            && let Some(index) = self.row_entry(previous)
+        {
+            used += self.entry_height(index);
+            if used > visible && first <= row {
+                break;
+            }
+            first = previous;
+        }
+        first
+    }
+
+    /// The scroll offset that shows the newest entries.
+    fn bottom_offset(&self) -> usize {
+        match self.row_count().checked_sub(1) {
+            Some(last) => self.page_ending_at(last),
+            None => 0,
+        }
+    }
+
+    /// Scroll so log entry `index` is visible, if it is shown at all.
+    fn scroll_to(&mut self, index: usize) {
+        let row = match self.filtered() {
+            Some(rows) => match rows.binary_search(&index) {
+                Ok(row) => row,
+                Err(_) => return,
+            },
+            None => index,
+        };
+        if row < self.scroll_offset {
+            self.scroll_offset = row;
+        } else {
+            self.scroll_offset = self.scroll_offset.max(self.page_ending_at(row));
+        }
+        self.update_follow();
+    }
+
+    /// Follow again once the view reaches the bottom, and stop following when it leaves it.
+    fn update_follow(&mut self) {
+        self.follow = self.scroll_offset >= self.bottom_offset();
+        if self.follow {
+            self.unseen = 0;
+            self.rang = false;
+        }
+    }
+
+    fn toggle_follow(&mut self) {
+        self.follow = !self.follow;
+        self.unseen = 0;
+        self.rang = false;
+    }
+
+    /// Resend the payload of the log entry under a click at `(column, row)`, if it has one.
+    fn replay_at(&mut self, column: u16, row: u16) {
+        if let Some(index) = self.entry_at(column, row) {
+            self.replay(index);
+        }
+    }
+
+    /// Resend the payload of log entry `index`, if it has one.
+    fn replay(&mut self, index: usize) {
+        let Some((mode, data)) = self.log[index].payload.clone() else {
+            return;
+        };
+        self.log_msg(
+            format!("↻ Replaying {} bytes [{}]", data.len(), mode.short_label()),
+            self.theme.info,
+            None,
+        );
+        self.submit(NetCommand::SendRaw { mode, data });
+    }
+
+    /// Select the log entry under a click at `(column, row)` for the inspector.
+    fn select_at(&mut self, column: u16, row: u16) {
+        if let Some(index) = self.entry_at(column, row) {
+            self.selected = Some(index);
+            self.inspector = true;
+        }
+    }
+
+    /// Move the inspector selection by `delta` entries, scrolling it into view.
+    fn move_selection(&mut self, delta: isize) {
+        let Some(last) = self.row_count().checked_sub(1) else {
+            return;
+        };
+        let current = match (self.inspected(), self.filtered()) {
+            (Some(index), Some(rows)) => rows.partition_point(|&r| r < index),
+            (Some(index), None) => index,
+            (None, _) => last,
+        };
+        let row = current.saturating_add_signed(delta).min(last);
+        if let Some(index) = self.row_entry(row) {
+            self.selected = Some(index);
+            self.scroll_to(index);
+        }
+    }
+
+    /// Freeze or resume the log's incoming traffic. Datagrams that arrive while paused are
+    /// logged on resume, with their original receive times.
+    fn toggle_pause(&mut self) {
+        match self.paused.take() {
+            Some(held) => {
+                self.log_msg(
+                    format!("▶ Resumed, {} datagram(s) held", held.len()),
+                    self.theme.info,
+                    None,
+                );
+                for (data, from, time, rtt) in held {
+                    self.on_received(data, from, time, rtt);
+                }
+            }
+            None => {
+                self.log_msg(
+                    "⏸ Paused incoming traffic (Ctrl+P to resume)".into(),
+                    self.theme.info,
+                    None,
+                );
+                self.paused = Some(vec![]);
+            }
+        }
+    }
+
+    /// Copy the inspected entry to the system clipboard with OSC 52: its payload as hex, or
+    /// with `as_text` its log line.
+    fn copy_selected(&mut self, as_text: bool) {
+        let Some(entry) = self.inspected().map(|i| &self.log[i]) else {
+            self.log_error("nothing to copy");
+            return;
+        };
+        let (what, text) = match (&entry.payload, as_text) {
+            (_, true) => ("log line".to_string(), entry.display.clone()),
+            (Some((_, data)), false) => (
+                format!("{} bytes as hex", data.len()),
+                data.iter().map(|b| format!("{:02x}", b)).collect(),
+            ),
+            (None, false) => {
+                self.log_error("entry has no payload to copy");
+                return;
+            }
+        };
+        self.copy_to_clipboard(&what, &text);
+    }
+
+    /// Left button released: a click resends the entry under it, a drag copies the text.
+    fn release(&mut self) {
+        let Some(drag) = self.drag.take() else {
+            return;
+        };
+        if drag.is_click() {
+            let at = drag.anchor();
+            self.replay_at(at.x, at.y);
+        } else if !drag.text.is_empty() {
+            let what = format!("{} selected characters", drag.text.chars().count());
+            self.copy_to_clipboard(&what, &drag.text);
+        }
+    }
+
+    /// Alt+M: mark the inspected entry for a diff, or unmark it. A third mark replaces the oldest.
+    fn toggle_mark(&mut self) {
+        let Some(index) = self.inspected() else {
+            self.log_error("no entry selected");
+            return;
+        };
+        if self.log[index].payload.is_none() {
+            self.log_error("entry has no payload to compare");
+        } else if let Some(at) = self.marks.iter().position(|&m| m == index) {
+            self.marks.remove(at);
+        } else {
+            if self.marks.len() == 2 {
+                self.marks.remove(0);
+            }
+            self.marks.push(index);
+        }
+    }
+
+    /// Alt+D: compare the payloads of the two marked entries.
+    fn open_diff(&mut self) {
+        let &[a, b] = self.marks.as_slice() else {
+            self.log_error("mark two entries with Alt+M to compare them");
+            return;
+        };
+        let (Some((_, first)), Some((_, second))) = (&self.log[a].payload, &self.log[b].payload)
+        else {
+            return;
+        };
+        let title = format!("Diff: A {} bytes, B {} bytes", first.len(), second.len());
+        self.diff = Some(diff::Diff::new(title, first, second));
+    }
+
+    /// Alt+E: put the inspected entry's line back in the input to edit and resend. Entries that
+    /// weren't typed, such as received datagrams, load as hex.
+    fn edit_entry(&mut self) {
+        let Some(index) = self.inspected() else {
+            self.log_error("no entry selected");
+            return;
+        };
+        let entry = &self.log[index];
+        let (mode, text) = match (&entry.source, &entry.payload) {
+            (Some((mode, line)), _) => (*mode, line.clone()),
+            (None, Some((_, data))) => {
+                let hex: Vec<String> = data.iter().map(|b| format!("{:02x}", b)).collect();
+                (InputMode::Hex, hex.join(" "))
+            }
+            (None, None) => {
+                self.log_error("entry has no payload to edit");
+                return;
+            }
+        };
+        self.input_mode = mode;
+        self.input.set(text);
+        self.log_focus = false;
+    }
+
+    /// Hand `text` to the terminal's clipboard with OSC 52, logging `what` was copied.
+    fn copy_to_clipboard(&mut self, what: &str, text: &str) {
+        let mut out = stdout();
+        let result = write!(out, "\x1b]52;c;{}\x07", utils::base64(text.as_bytes()))
+            .and_then(|()| out.flush());
+        match result {
+            Ok(()) => self.log_msg(
+                format!("⧉ Copied {} to the clipboard", what),
+                self.theme.info,
+                None,
+            ),
+            Err(err) => self.log_error(format!("Copy failed: {}", err)),
+        }
+    }
+
+    /// Re-render the preview of the inspected entry in the next view: text, hex, then MQTT.
+    fn cycle_view(&mut self) {
+        let Some(index) = self.inspected() else {
+            self.log_error("no entry selected");
+            return;
+        };
+        let entry = &mut self.log[index];
+        let Some((mode, data)) = &entry.payload else {
+            self.log_error("entry has no payload to view");
+            return;
+        };
+        let current = entry.view.unwrap_or(*mode);
+        let next = match current {
+            InputMode::Text => InputMode::Hex,
+            InputMode::Hex => InputMode::Mqtt,
+            _ => InputMode::Text,
+        };
+        let old = format::format_for_mode(current, self.text_encoding, data);
+        let new = format::format_for_mode(next, self.text_encoding, data);
+        let Some(at) = entry.display.rfind(old.as_ref()) else {
+            self.log_error("this log line has no preview to re-render");
+            return;
+        };
+        entry.display.replace_range(at..at + old.len(), &new);
+        entry.view = Some(next);
+
+        if let Some(search) = &mut self.search {
+            search.update(self.log.iter().map(|e| e.display.as_str()));
+        }
+    }
+
+    fn open_search(&mut self) {
+        self.search = Some(search::Search::new());
+    }
+
+    /// Open the hex editor on the last buffer composed, or on the input line if it is hex.
+    fn open_compose(&mut self) {
+        let data = match utils::parse_hex(self.input.as_str()) {
+            Ok(data) if !data.is_empty() => {
+                self.input.clear();
+                data
+            }
+            _ => std::mem::take(&mut self.compose_draft),
+        };
+        self.compose = Some(hexedit::HexEditor::new(data));
+    }
+
+    fn compose_key(&mut self, key: KeyEvent) {
+        let Some(editor) = &mut self.compose else {
+            return;
+        };
+        match editor.key(key) {
+            hexedit::Outcome::Send if editor.data.is_empty() => {}
+            hexedit::Outcome::Send => {
+                let data = editor.data.clone();
+                self.submit(NetCommand::SendRaw {
+                    mode: InputMode::Hex,
+                    data,
+                });
+            }
+            hexedit::Outcome::Close => {
+                if let Some(editor) = self.compose.take() {
+                    self.compose_draft = editor.data;
+                }
+            }
+            hexedit::Outcome::Edited | hexedit::Outcome::Ignored => {}
+        }
+    }
+
+    /// Pasted text goes to the search prompt or hex editor while one has focus.
+    fn paste(&mut self, text: &str) {
+        if let Some(editor) = &mut self.compose {
+            match utils::parse_hex(text) {
+                Ok(bytes) => editor.insert(&bytes),
+                Err(err) => self.log_error(format!("Paste into compose: {}", err)),
+            }
+            return;
+        }
+        match &mut self.search {
+            Some(search) if search.editing => {
+                search.query.insert_str(text);
+                search.update(self.log.iter().map(|e| e.display.as_str()));
+            }
+            _ => self.input.insert_str(text),
+        }
+    }
+
+    /// Keys while the log has focus. Returns false for keys handled as usual, such as Ctrl keys.
+    fn log_key(&mut self, key: KeyEvent) -> bool {
+        if self.vim && self.vim_key(key) {
+            return true;
+        }
+        match key.code {
+            KeyCode::Esc | KeyCode::BackTab => self.log_focus = false,
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::Left | KeyCode::Char('h') => self.hscroll = self.hscroll.saturating_sub(8),
+            KeyCode::Right | KeyCode::Char('l') => self.hscroll += 8,
+            KeyCode::Char('[') => self.fields.move_cursor(-1),
+            KeyCode::Char(']') => self.fields.move_cursor(1),
+            KeyCode::Char(' ') => {
+                let decoded = self
+                    .inspected()
+                    .and_then(|i| self.log[i].payload.as_ref())
+                    .and_then(|(_, data)| fields::decode(data));
+                if let Some(decoded) = decoded {
+                    self.fields.toggle(&decoded);
+                }
+            }
+            KeyCode::Char('m') => self.toggle_mark(),
+            KeyCode::Char('d') => self.open_diff(),
+            KeyCode::Char('e') => self.edit_entry(),
+            KeyCode::Char('b') => self.toggle_bookmark(),
+            KeyCode::Home => self.move_selection(isize::MIN),
+            KeyCode::End => self.move_selection(isize::MAX),
+            KeyCode::Enter => match self.inspected() {
+                Some(index) => self.replay(index),
+                None => self.log_error("no entry selected"),
+            },
+            // Typing would edit an input line that isn't focused.
+            KeyCode::Char(_)
+                if !key
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => {}
+            _ => return false,
+        }
+        true
+    }
+
+    /// Normal mode keys added by `keys = vim`. Returns false for the ones `log_key` shares.
+    fn vim_key(&mut self, key: KeyEvent) -> bool {
+        let pending = self.pending.take();
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => {}
+            KeyCode::Char('i') | KeyCode::Char('a') => self.log_focus = false,
+            KeyCode::Char(':') => {
+                self.log_focus = false;
+                self.input.set(":".into());
+            }
+            KeyCode::Char('/') => self.open_search(),
+            KeyCode::Char('d') if ctrl => self.page(true),
+            KeyCode::Char('u') if ctrl => self.page(false),
+            KeyCode::Char('G') => self.move_selection(isize::MAX),
+            KeyCode::Char('g') if pending == Some('g') => self.move_selection(isize::MIN),
+            KeyCode::Char('y') if pending == Some('y') => self.copy_selected(false),
+            KeyCode::Char(c @ ('g' | 'y')) => self.pending = Some(c),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Keys for an open search. Returns false for keys that fall through to the input line.
+    fn search_key(&mut self, key: KeyEvent) -> bool {
+        let Some(search) = &mut self.search else {
+            return false;
+        };
+
+        if search.editing {
+            match key.code {
+                KeyCode::Esc => self.search = None,
+                KeyCode::Enter => search.editing = false,
+                KeyCode::Tab => search.filter = !search.filter,
+                KeyCode::Backspace if search.query.is_empty() => self.search = None,
+                _ => {
+                    if search.query.edit(key) {
+                        search.update(self.log.iter().map(|e| e.display.as_str()));
+                        if let Some(last) = search.matches().last().copied() {
+                            self.scroll_to(last);
+                        }
+                    }
+                }
+            }
+            // Keep the filtered view in range as matches come and go.
+            self.scroll(0);
+            return true;
+        }
+
+        // Once the prompt closes, the search keys only apply while the input line is empty.
+        if !self.input.is_empty() {
+            return false;
+        }
+        let target = match key.code {
+            KeyCode::Esc => {
+                self.search = None;
+                self.scroll(0);
+                return true;
+            }
+            KeyCode::Char('/') => {
+                search.editing = true;
+                return true;
+            }
+            KeyCode::Char('f') => {
+                search.filter = !search.filter;
+                self.scroll(0);
+                return true;
+            }
+            KeyCode::Char('n') => search.next(self.selected),
+            KeyCode::Char('N') => search.previous(self.selected),
+            _ => return false,
+        };
+        if let Some(index) = target {
+            self.selected = Some(index);
+            self.scroll_to(index);
+        }
+        true
+    }
+
+    /// The entry the inspector shows: the selection, or else the newest entry with a payload.
+    fn inspected(&self) -> Option<usize> {
+        self.selected
+            .or_else(|| self.log.iter().rposition(|e| e.payload.is_some()))
+    }
+
+    fn scroll(&mut self, delta: i16) {
+        let max_scroll = self.bottom_offset();
+
+        if delta < 0 {
+            self.scroll_offset = self
+                .scroll_offset
+                .saturating_sub(delta.unsigned_abs() as usize);
+        } else {
+            self.scroll_offset = (self.scroll_offset + delta as usize).min(max_scroll);
+        }
+        self.update_follow();
+    }
+
+    /// PageUp/PageDown: scroll by a screenful, keeping one entry of overlap.
+    fn page(&mut self, down: bool) {
+        if down {
+            let visible = self.log_area.height.saturating_sub(2) as usize;
+            let mut used = 0;
+            let mut row = self.scroll_offset;
+            while let Some(index) = self.row_entry(row) {
+                used += self.entry_height(index);
+                if used >= visible {
+                    break;
+                }
+                row += 1;
+            }
+            self.scroll_offset = row.max(self.scroll_offset + 1).min(self.bottom_offset());
+        } else if let Some(row) = self.scroll_offset.checked_sub(1) {
+            self.scroll_offset = self.page_ending_at(row + 1).min(row);
+        }
+        self.update_follow();
+    }
+
+    /// Ctrl+Home/Ctrl+End: jump to the oldest or the newest entry.
+    fn scroll_edge(&mut self, end: bool) {
+        self.scroll_offset = if end { self.bottom_offset() } else { 0 };
+        self.update_follow();
+    }
+}
+
+pub fn run(args: &TuiArgs, config: Config) -> io::Result<()> {
+    let mut app = App::new(args, config);
+
+    enable_raw_mode()?;
+    execute!(
+        stdout(),
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableBracketedPaste
+    )?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
+
+    while app.running {
+        app.tick(Instant::now());
+        app.drain_net_events();
+        terminal.draw(|f| draw(f, &mut app))?;
+
+        // Wake early for a macro step or timer that falls due before the next redraw.
+        let timeout = app.next_due().map_or(Duration::from_millis(100), |due| {
+            due.saturating_duration_since(Instant::now())
+                .min(Duration::from_millis(100))
+        });
+        if !event::poll(timeout)? {
+            continue;
+        }
+
+        match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press && app.help => app.help = false,
+            Event::Key(key) if key.kind == KeyEventKind::Press && app.compose.is_some() => {
+                app.compose_key(key)
+            }
+            Event::Key(key) if key.kind == KeyEventKind::Press && app.diff.is_some() => {
+                if let Some(diff) = &mut app.diff
+                    && !diff.key(key)
+                {
+                    app.diff = None;
+                }
+            }
+            Event::Key(key) if key.kind == KeyEventKind::Press && app.search_key(key) => {}
+            Event::Key(key)
+                if key.kind == KeyEventKind::Press && app.log_focus && app.log_key(key) => {}
+            Event::Key(key) if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Esc if app.vim => app.log_focus = true,
+                KeyCode::Esc => app.running = false,
+                KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    app.restart_network()
+                }
+                KeyCode::Char('b') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    app.toggle_send_strategy()
+                }
+                KeyCode::Char('g') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    app.rate_bytes = !app.rate_bytes
+                }
+                KeyCode::Char('f') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    app.toggle_follow()
+                }
+                KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    app.toggle_pause()
+                }
+                KeyCode::Char('y') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    app.copy_selected(false)
+                }
+                KeyCode::Char('y') if key.modifiers.contains(KeyModifiers::ALT) => {
+                    app.copy_selected(true)
+                }
+                KeyCode::Char('v') if key.modifiers.contains(KeyModifiers::ALT) => app.cycle_view(),
+                KeyCode::Char('m') if key.modifiers.contains(KeyModifiers::ALT) => {
+                    app.toggle_mark()
+                }
+                KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::ALT) => app.open_diff(),
+                KeyCode::Char('e') if key.modifiers.contains(KeyModifiers::ALT) => app.edit_entry(),
+                KeyCode::Char('b') if key.modifiers.contains(KeyModifiers::ALT) => {
+                    app.toggle_bookmark()
+                }
+                KeyCode::Char('n') if key.modifiers.contains(KeyModifiers::ALT) => {
+                    app.bookmark_command("next")
+                }
+                KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::ALT) => {
+                    app.bookmark_command("prev")
+                }
+                KeyCode::Char('t') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    app.timestamps = app.timestamps.next()
+                }
+                KeyCode::Char('e') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    app.open_compose()
+                }
+                KeyCode::F(2) => app.inspector = !app.inspector,
+                KeyCode::F(3) => app.split = !app.split,
+                KeyCode::F(6) => app.wrap = !app.wrap,
+                KeyCode::F(4) => app.session_panel = !app.session_panel,
+                KeyCode::F(5) => match app.macros.last.clone() {
+                    Some(name) => app.run_macro(&name),
+                    None => app.log_error("no macro to replay; :macro record <name> starts one"),
+                },
+                KeyCode::Char('/') if app.input.is_empty() => app.open_search(),
+                KeyCode::Char('?') if app.input.is_empty() => app.help = true,
+                KeyCode::Up if key.modifiers.contains(KeyModifiers::CONTROL) => app.resize_input(1),
+                KeyCode::Down if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    app.resize_input(-1)
+                }
+                KeyCode::Left if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    app.resize_detail(4)
+                }
+                KeyCode::Right if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    app.resize_detail(-4)
+                }
+                KeyCode::Left if app.input.is_empty() => {
+                    app.hscroll = app.hscroll.saturating_sub(8)
+                }
+                KeyCode::Right if app.input.is_empty() => app.hscroll += 8,
+                KeyCode::PageUp => app.page(false),
+                KeyCode::PageDown => app.page(true),
+                KeyCode::End if app.input.is_empty() && app.unseen > 0 => app.scroll_edge(true),
+                KeyCode::Home if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    app.scroll_edge(false)
+                }
+                KeyCode::End if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    app.scroll_edge(true)
+                }
+                KeyCode::Up if key.modifiers.contains(KeyModifiers::ALT) => app.move_selection(-1),
+                KeyCode::Down if key.modifiers.contains(KeyModifiers::ALT) => app.move_selection(1),
+                KeyCode::Tab
+                    if app.input.is_empty()
+                        || !(app.input.as_str().starts_with(':')
+                            || matches!(app.input_mode, InputMode::Auto | InputMode::Mqtt)) =>
+                {
+                    app.cycle_mode()
+                }
+                KeyCode::Tab => app.complete(),
+                KeyCode::BackTab => app.log_focus = true,
+                KeyCode::Enter if key.modifiers.contains(KeyModifiers::ALT) => {
+                    app.multiline = !app.multiline
+                }
+                KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => app.send(),
+                KeyCode::Enter if app.multiline => app.input.insert_str("\n"),
+                KeyCode::Enter => app.send(),
+                KeyCode::Up if !app.input.line_up() => app.history_up(),
+                KeyCode::Down if !app.input.line_down() => app.history_down(),
+                KeyCode::Up | KeyCode::Down => {}
+                _ => {
+                    app.input.edit(key);
+                }
+            },
+            Event::Paste(text) => app.paste(&text.replace("\r\n", "\n")),
+            Event::Mouse(mouse) => match mouse.kind {
+                MouseEventKind::ScrollLeft => app.hscroll = app.hscroll.saturating_sub(8),
+                MouseEventKind::ScrollRight => app.hscroll += 8,
+                // Terminals without horizontal wheel events send Shift+wheel as vertical.
+                MouseEventKind::ScrollUp if mouse.modifiers.contains(KeyModifiers::SHIFT) => {
+                    app.hscroll = app.hscroll.saturating_sub(8)
+                }
+                MouseEventKind::ScrollDown if mouse.modifiers.contains(KeyModifiers::SHIFT) => {
+                    app.hscroll += 8
+                }
+                MouseEventKind::ScrollUp => app.scroll(-3),
+                MouseEventKind::ScrollDown => app.scroll(3),
+                MouseEventKind::Down(MouseButton::Left) => {
+                    let at = Position::new(mouse.column, mouse.row);
+                    if app.log_area.inner(Margin::new(1, 1)).contains(at) {
+                        app.drag = Some(select::Selection::new(at));
+                    }
+                }
+                MouseEventKind::Drag(MouseButton::Left) => {
+                    if let Some(drag) = &mut app.drag {
+                        drag.extend(Position::new(mouse.column, mouse.row));
+                    }
+                }
+                MouseEventKind::Up(MouseButton::Left) => app.release(),
+                MouseEventKind::Down(MouseButton::Right) => app.select_at(mouse.column, mouse.row),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    app.shutdown_network();
+
+    disable_raw_mode()?;
+    execute!(
+        stdout(),
+        LeaveAlternateScreen,
+        DisableMouseCapture,
+        DisableBracketedPaste
+    )?;
+    Ok(())
+}
+
+fn draw(f: &mut Frame, app: &mut App) {
+    let banner_height = if app.net_alive { 0 } else { 1 };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(5),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Length(banner_height),
+            Constraint::Length(input_height(app, f.area().width)),
+        ])
+        .split(f.area());
+
+    // Side panels share a column right of the log, the inspector on top.
+    let panes = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Min(30), Constraint::Length(app.side_width())])
+        .split(chunks[0]);
+    // Running `:every` timers get a box under the log.
+    let timer_rows = match app.timers.list().len() {
+        0 => 0,
+        n => n.min(5) as u16 + 2,
+    };
+    let [log_area, timers_area] =
+        Layout::vertical([Constraint::Min(3), Constraint::Length(timer_rows)]).areas(panes[0]);
+    let side = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(match (app.inspector, app.session_panel) {
+            (true, true) => [Constraint::Percentage(60), Constraint::Percentage(40)],
+            (true, false) => [Constraint::Fill(1), Constraint::Length(0)],
+            (false, _) => [Constraint::Length(0), Constraint::Fill(1)],
+        })
+        .split(panes[1]);
+    let inspector_area = app.inspector.then_some(side[0]);
+    let session_area = app.session_panel.then_some(side[1]);
+
+    // Store log area for click detection
+    app.log_area = log_area;
+
+    // Log with scrolling
+    let visible_height = log_area.height.saturating_sub(2) as usize;
+    if app.follow {
+        app.scroll_offset = app.bottom_offset();
+    }
+    let inspected = (app.inspector || app.log_focus)
+        .then(|| app.inspected())
+        .flatten();
+    let search = app.search.as_ref().filter(|s| s.is_active());
+    let mut rows_left = visible_height;
+    let shown: Vec<usize> = (app.scroll_offset..)
+        .map_while(|row| app.row_entry(row))
+        .take_while(|&i| {
+            let fits = rows_left > 0;
+            rows_left = rows_left.saturating_sub(app.entry_height(i));
+            fits
+        })
+        .collect();
+    // Scrolling right stops once the widest entry in view ends at the pane's edge.
+    let widest = shown
+        .iter()
+        .map(|&i| app.log[i].display.width() + app.stamp(i).map_or(0, |s| s.width() + 1))
+        .max()
+        .unwrap_or(0);
+    app.hscroll = match app.wrap || app.split {
+        true => 0,
+        false => app
+            .hscroll
+            .min(widest.saturating_sub(log_area.width.saturating_sub(2) as usize)),
+    };
+    let items: Vec<ListItem> = shown
+        .into_iter()
+        .map(|i| {
+            let e = &app.log[i];
+            let mut style = if e.payload.is_some() {
+                e.style.underlined() // Indicate clickable
+            } else {
+                e.style
+            };
+            if search.is_some_and(|s| !s.filter && s.contains(i)) {
+                style = style.patch(app.theme.search_match);
+            }
+            if inspected == Some(i) {
+                style = style.reversed();
+            }
+            if app.marks.contains(&i) {
+                style = style.italic().bold();
+            }
+            let stamp = app.stamp(i);
+            if app.wrap && !app.split {
+                // Continuation rows line up under the first, past the timestamp.
+                let indent = stamp.as_ref().map_or(0, |s| s.width() + 1);
+                let lines: Vec<Line> = app
+                    .wrapped(i)
+                    .into_iter()
+                    .enumerate()
+                    .map(|(n, text)| match (n, &stamp) {
+                        (0, Some(stamp)) => Line::from(vec![
+                            Span::styled(stamp.clone(), app.theme.hint),
+                            Span::raw(" "),
+                            Span::raw(text),
+                        ]),
+                        (0, None) => Line::raw(text),
+                        _ => Line::raw(format!("{}{}", " ".repeat(indent), text)),
+                    })
+                    .collect();
+                return ListItem::new(lines).style(style);
+            }
+            let mut spans = match stamp {
+                Some(stamp) => vec![Span::styled(stamp, app.theme.hint), Span::raw(" ")],
+                None => vec![],
+            };
+            if !app.split {
+                spans.push(Span::raw(format::skip_columns(&e.display, app.hscroll)));
+                return ListItem::new(Line::from(spans)).style(style);
+            }
+
+            // Split view: the entry sits in its own column of the row, styled alone.
+            let stamp_width: usize = spans.iter().map(|s| s.width()).sum();
+            let column = log_area.width.saturating_sub(5) as usize / 2;
+            let column = column.saturating_sub(stamp_width / 2);
+            let separator = Span::styled(" │ ", app.theme.hint);
+            match e.side {
+                Some(Side::Sent) => {
+                    let text = format::truncate(&e.display, column);
+                    let pad = column.saturating_sub(text.width());
+                    spans.push(Span::styled(format!("{}{}", text, " ".repeat(pad)), style));
+                    spans.push(separator);
+                }
+                Some(Side::Received) => {
+                    spans.push(Span::raw(" ".repeat(column)));
+                    spans.push(separator);
+                    spans.push(Span::styled(e.display.as_str(), style));
+                }
+                None => spans.push(Span::styled(e.display.as_str(), style)),
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+
+    let mut title = match (&app.paused, search) {
+        (Some(held), _) => format!(
+            "Log PAUSED, {} received datagram(s) held (Ctrl+P to resume)",
+            held.len()
+        ),
+        (None, Some(s)) => format!(
+            "Log /{}: {} match(es){} (n/N to jump, f to filter, Esc to clear)",
+            s.query.as_str(),
+            s.matches().len(),
+            if s.filter { ", filtered" } else { "" }
+        ),
+        (None, None) => "Log (click to replay, right-click to inspect, scroll to navigate)".into(),
+    };
+    if app.split {
+        title.push_str(" [sent │ received]");
+    }
+    if app.hscroll > 0 {
+        title.push_str(&format!(" [→ {} columns]", app.hscroll));
+    }
+
+    if app.bookmarks_only {
+        title.push_str(&format!(" [{} bookmarked only]", app.bookmarks.len()));
+    } else if let Some(show) = &app.show {
+        title.push_str(&format!(
+            " [showing {}, :show all for everything]",
+            show.words()
+        ));
+    }
+    match app.marks.len() {
+        1 => title.push_str(" [1 marked, Alt+M another to compare]"),
+        2 => title.push_str(" [2 marked, Alt+D to diff]"),
+        _ => {}
+    }
+    let mut block = Block::default().borders(Borders::ALL);
+    if app.log_focus && app.vim {
+        title.push_str(" [NORMAL: j/k select, yy copies, i to type, : for commands]");
+        block = block.border_style(app.theme.heading);
+    } else if app.log_focus {
+        title.push_str(" [j/k select, Enter resends, Esc back to input]");
+        block = block.border_style(app.theme.heading);
+    }
+    if app.unseen > 0 {
+        let badge = format!(" ▼ {} new (End to jump) ", app.unseen);
+        block = block.title_bottom(Line::styled(badge, app.theme.warning).right_aligned());
+    }
+    let log = List::new(items).block(block.title(title));
+    f.render_widget(log, log_area);
+    if let Some(drag) = &mut app.drag {
+        drag.draw(f.buffer_mut(), log_area.inner(Margin::new(1, 1)));
+    }
 
     // Scrollbar
     let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight);
-    let mut scrollbar_state = ScrollbarState::new(app.log.len()).position(app.scroll_offset);
-    f.render_stateful_widget(scrollbar, chunks[0], &mut scrollbar_state);
+    let mut scrollbar_state = ScrollbarState::new(app.row_count()).position(app.scroll_offset);
+    f.render_stateful_widget(scrollbar, log_area, &mut scrollbar_state);
+
+    if let Some(area) = inspector_area {
+        let lines = inspected.map_or_else(Vec::new, |i| {
+            inspector_lines(&app.log[i], &app.theme, &mut app.fields)
+        });
+        let inspector = Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Inspector (Alt+Up/Down to select, Ctrl+Y/Alt+Y to copy, F2 to close)"),
+        );
+        f.render_widget(inspector, area);
+    }
+
+    if let Some(area) = session_area {
+        let session = Paragraph::new(app.mqtt.lines(&app.theme)).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("MQTT session (F4 to close)"),
+        );
+        f.render_widget(session, area);
+    }
 
     let (mode_str, mode_style) = match app.input_mode {
-        InputMode::Auto => ("[AUTO]", Style::default().fg(Color::Blue).bold()),
-        InputMode::Text => ("[TEXT]", Style::default().fg(Color::Green).bold()),
-        InputMode::Hex => ("[HEX] ", Style::default().fg(Color::Magenta).bold()),
-        InputMode::Mqtt => ("[MQTT]", Style::default().fg(Color::Yellow).bold()),
+        InputMode::Auto => ("[AUTO]", app.theme.mode_auto),
+        InputMode::Text => ("[TEXT]", app.theme.mode_text),
+        InputMode::Hex => ("[HEX] ", app.theme.mode_hex),
+        InputMode::Mqtt => ("[MQTT]", app.theme.mode_mqtt),
+        InputMode::Base64 => ("[B64] ", app.theme.mode_base64),
+    };
+
+    // Sent and received packets (or bytes) per second over the last minute
+    let (sent, received) = app.traffic.series(Instant::now(), app.rate_bytes);
+    let unit = if app.rate_bytes { "B/s" } else { "/s" };
+    let rate_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Length(10),
+            Constraint::Fill(1),
+            Constraint::Length(10),
+            Constraint::Fill(1),
+        ])
+        .split(chunks[1]);
+    let graphs = [
+        (format!(" tx{}:", unit), sent, app.theme.sent),
+        (format!(" rx{}:", unit), received, app.theme.rate),
+    ];
+    for (i, (label, data, style)) in graphs.into_iter().enumerate() {
+        let [label_area, graph_area] = [rate_chunks[2 * i], rate_chunks[2 * i + 1]];
+        f.render_widget(Paragraph::new(label).dim(), label_area);
+        // Keep the newest samples when the pane is narrower than the window.
+        let skip = data.len().saturating_sub(graph_area.width as usize);
+        let sparkline = Sparkline::default().data(&data[skip..]).style(style);
+        f.render_widget(sparkline, graph_area);
+    }
+
+    if timer_rows > 0 {
+        let now = Instant::now();
+        let lines: Vec<Line> = app
+            .timers
+            .list()
+            .iter()
+            .map(|t| Line::styled(t.describe(now), app.theme.info))
+            .collect();
+        let title = format!(" Timers ({}) ", lines.len());
+        let timers =
+            Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title));
+        f.render_widget(timers, timers_area);
+    }
+
+    let status = Paragraph::new(app.counters.render(app.local_addr)).style(app.theme.info);
+    f.render_widget(status, chunks[2]);
+
+    if !app.net_alive {
+        let banner = Paragraph::new(" ✗ Network thread died — press Ctrl+R to restart")
+            .style(app.theme.banner);
+        f.render_widget(banner, chunks[3]);
+    }
+
+    // Once bound, show the real endpoints: the ephemeral source port and the resolved target.
+    let (target, target_style) = match app.send_strategy {
+        SendStrategy::Connected => (app.net.target.clone(), app.theme.target),
+        SendStrategy::Broadcast => (app.net.broadcast.clone(), app.theme.broadcast),
+    };
+    let target = match (app.local_addr, app.peer_addr, app.send_strategy) {
+        (Some(local), Some(peer), SendStrategy::Connected) if peer.to_string() == target => {
+            format!("{} → {}", local, peer)
+        }
+        (Some(local), Some(peer), SendStrategy::Connected) => {
+            format!("{} → {} ({})", local, peer, target)
+        }
+        (Some(local), _, SendStrategy::Broadcast) => format!("{} → {}", local, target),
+        _ => target,
     };
 
-    let line = Line::from(vec![
+    let mut line = Line::from(vec![
         Span::raw(" Target: "),
-        Span::styled(target, Style::default().fg(Color::Cyan)),
+        Span::styled(target, target_style),
         Span::raw(" │ Mode: "),
         Span::styled(mode_str, mode_style),
-        Span::raw(" (tab to cycle)"),
+        Span::raw(" (tab to complete, or cycle when empty)"),
     ]);
+    if app.vim {
+        line.push_span(Span::styled(
+            if app.log_focus {
+                " -- NORMAL -- "
+            } else {
+                " -- INSERT -- "
+            },
+            app.theme.heading,
+        ));
+    }
+    if app.multiline {
+        line.push_span(Span::styled(
+            " ¶ multi-line: Enter adds a line, Ctrl+S sends ",
+            app.theme.info,
+        ));
+    }
+    if let Some(name) = app.macros.recording() {
+        line.push_span(Span::styled(format!(" ● REC {} ", name), app.theme.error));
+    } else if app.macros.is_playing() {
+        line.push_span(Span::styled(" ▶ macro ", app.theme.info));
+    }
+
+    // The search prompt takes over the input box while it has focus.
+    let (input, title) = match app.search.as_ref().filter(|s| s.editing) {
+        Some(search) => {
+            let title = match search.error() {
+                Some(err) => Line::styled(format!(" Search: {}", err), app.theme.error),
+                None => Line::raw(
+                    " Search (re: for a regex, Tab to filter, Enter to keep, Esc to cancel)",
+                ),
+            };
+            (&search.query, title)
+        }
+        None => (&app.input, line),
+    };
+
+    // Long input wraps onto the rows of a taller box, scrolled to keep the cursor in view.
+    let width = chunks[4].width.saturating_sub(2) as usize;
+    let rows = format::chop(input.as_str(), width);
+    let cursor = input.before_cursor().len();
+    let cursor_row = rows
+        .iter()
+        .rposition(|(start, _)| *start <= cursor)
+        .unwrap_or(0);
+    let cursor_column = input.before_cursor()[rows[cursor_row].0..]
+        .width()
+        .min(width);
+    let height = chunks[4].height.saturating_sub(2).max(1) as usize;
+    let first = (cursor_row + 1).saturating_sub(height);
+    let lines: Vec<Line> = rows
+        .into_iter()
+        .skip(first)
+        .map(|(_, row)| Line::raw(row))
+        .collect();
+    let widget = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title));
+    f.render_widget(widget, chunks[4]);
+
+    f.set_cursor_position((
+        chunks[4].x + cursor_column as u16 + 1,
+        chunks[4].y + (cursor_row - first) as u16 + 1,
+    ));
+
+    if let Some(editor) = &mut app.compose {
+        editor.draw(f, chunks[0], &app.theme);
+    }
+    if let Some(diff) = &mut app.diff {
+        diff.draw(f, chunks[0], &app.theme);
+    }
+    if app.help {
+        help::draw(f, f.area(), &app.theme);
+    }
+}
+
+/// Whether `topic` matches `filter`, where `+` stands for any one level and a trailing `#` for
+/// any number of them.
+fn topic_matches(filter: &str, topic: &str) -> bool {
+    let (mut filter, mut topic) = (filter.split('/'), topic.split('/'));
+    loop {
+        match (filter.next(), topic.next()) {
+            (None, None) | (Some("#"), _) => return true,
+            (Some(f), Some(t)) if f == "+" || f == t => {}
+            _ => return false,
+        }
+    }
+}
+
+/// The input box's height: the configured one, grown in multi-line mode to fit what is typed.
+fn input_height(app: &App, width: u16) -> u16 {
+    if !app.multiline {
+        return app.input_height;
+    }
+    let rows = format::chop(app.input.as_str(), width.saturating_sub(2) as usize).len();
+    (rows as u16 + 2).clamp(app.input_height, MAX_INPUT_HEIGHT)
+}
+
+/// Everything known about one log entry: its line, receive time, and the payload in full.
+/// Decoded MQTT frames get a field tree, the bytes of the field under its cursor highlighted
+/// in the dump.
+fn inspector_lines(
+    entry: &LogEntry,
+    theme: &theme::Palette,
+    tree: &mut fields::Tree,
+) -> Vec<Line<'static>> {
+    let mut lines = vec![
+        Line::styled(entry.display.clone(), entry.style),
+        Line::from(format!("time: {}", utils::timestamp(entry.time))),
+    ];
+
+    let Some((mode, data)) = &entry.payload else {
+        return lines;
+    };
+    lines.push(Line::from(format!(
+        "{} bytes [{}]",
+        data.len(),
+        entry.view.unwrap_or(*mode).short_label()
+    )));
+    lines.push(Line::default());
+
+    let Some(decoded) = fields::decode(data) else {
+        lines.extend(utils::hexdump(data).into_iter().map(Line::from));
+        return lines;
+    };
+    let (tree_lines, range) = tree.lines(&decoded, theme);
+    lines.extend(fields::hexdump(data, range, theme.search_match));
+    lines.push(Line::default());
+    lines.extend(tree_lines);
+    lines
+}
+
+/// A gap between log entries, e.g. `850µs`, `12.3ms` or `4.250s`.
+fn format_gap(gap: Duration) -> String {
+    match gap.as_micros() {
+        us @ 0..1_000 => format!("{}µs", us),
+        us @ 1_000..1_000_000 => format!("{:.1}ms", us as f64 / 1000.0),
+        _ => format!("{:.3}s", gap.as_secs_f64()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LengthPrefix;
+
+    const TIMEOUT: Duration = Duration::from_secs(2);
+
+    /// An app for `udd tui 127.0.0.1:9 <flags>`, without history.
+    fn app(flags: &[&str]) -> App {
+        let command = <TuiArgs as clap::Args>::augment_args(clap::Command::new("tui"));
+        let argv = ["tui", "127.0.0.1:9", "--no-history"].iter().chain(flags);
+        let matches = command.get_matches_from(argv);
+        let args = <TuiArgs as clap::FromArgMatches>::from_arg_matches(&matches).unwrap();
+        App::new(&args, Config::default())
+    }
+
+    /// Route the app's network commands to the returned receiver instead of its thread.
+    fn capture(app: &mut App) -> Receiver<NetCommand> {
+        let (tx, rx) = mpsc::channel();
+        app.tx = tx;
+        rx
+    }
+
+    /// The typed lines of every `Send` command captured so far.
+    fn sent_inputs(rx: &Receiver<NetCommand>) -> Vec<String> {
+        rx.try_iter()
+            .filter_map(|command| match command {
+                NetCommand::Send { input, .. } => Some(input),
+                _ => None,
+            })
+            .collect()
+    }
 
-    let input = Paragraph::new(app.input.as_str())
-        .block(Block::default().borders(Borders::ALL).title(line));
-    f.render_widget(input, chunks[1]);
+    /// The log lines after the "Ready" banner.
+    fn logged(app: &App) -> Vec<&str> {
+        app.log.iter().skip(1).map(|e| e.display.as_str()).collect()
+    }
+
+    fn net_config(bind: &str, target: SocketAddr) -> NetConfig {
+        NetConfig {
+            bind: bind.into(),
+            reuse_addr: false,
+            reuse_port: false,
+            target: target.to_string(),
+            broadcast: default_broadcast(&target.to_string()),
+            framing: Framing {
+                length_prefix: LengthPrefix::None,
+                strip_length_prefix: false,
+                pad_to: None,
+                pad_byte: 0,
+                pad_strict: false,
+            },
+        }
+    }
+
+    #[test]
+    fn respawned_network_thread_has_working_channels() {
+        let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+        peer.set_read_timeout(Some(TIMEOUT)).unwrap();
+        let target = peer.local_addr().unwrap();
+
+        // A bind that can't succeed ends the first thread, disconnecting its channels.
+        let (_, rx, handle) = spawn_network_thread(&net_config("256.0.0.1:0", target));
+        handle.join().unwrap();
+        assert!(matches!(rx.recv(), Ok(NetEvent::Error(_))));
+        assert!(matches!(rx.try_recv(), Err(TryRecvError::Disconnected)));
+
+        let (tx, rx, handle) = spawn_network_thread(&net_config("127.0.0.1:0", target));
+        let Ok(NetEvent::Bound { local, peer: bound }) = rx.recv_timeout(TIMEOUT) else {
+            panic!("expected a Bound event");
+        };
+        assert_eq!(bound, target);
+
+        tx.send(NetCommand::SendRaw {
+            mode: InputMode::Text,
+            data: b"ping".to_vec(),
+        })
+        .unwrap();
+        let mut buffer = [0u8; 16];
+        let (n, from) = peer.recv_from(&mut buffer).unwrap();
+        assert_eq!(&buffer[..n], b"ping");
+        assert_eq!(from, local);
+        assert!(matches!(
+            rx.recv_timeout(TIMEOUT),
+            Ok(NetEvent::Sent { sent: 4, .. })
+        ));
+
+        peer.send_to(b"pong", local).unwrap();
+        let Ok(NetEvent::Received { data, from, .. }) = rx.recv_timeout(TIMEOUT) else {
+            panic!("expected a Received event");
+        };
+        assert_eq!(data, b"pong");
+        assert_eq!(from, None);
+
+        tx.send(NetCommand::Shutdown).unwrap();
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn toggling_the_strategy_redirects_the_next_datagram() {
+        let mut app = app(&[]);
+        let rx = capture(&mut app);
+        app.toggle_send_strategy();
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(NetCommand::SetStrategy(SendStrategy::Broadcast))
+        ));
+        app.toggle_send_strategy();
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(NetCommand::SetStrategy(SendStrategy::Connected))
+        ));
 
-    f.set_cursor_position((chunks[1].x + app.input.len() as u16 + 1, chunks[1].y + 1));
+        // Loopback stands in for the broadcast address.
+        let target = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let broadcast = UdpSocket::bind("127.0.0.1:0").unwrap();
+        for peer in [&target, &broadcast] {
+            peer.set_read_timeout(Some(TIMEOUT)).unwrap();
+        }
+        let mut config = net_config("127.0.0.1:0", target.local_addr().unwrap());
+        config.broadcast = broadcast.local_addr().unwrap().to_string();
+        let (tx, _rx, handle) = spawn_network_thread(&config);
+
+        let mut buffer = [0u8; 16];
+        for (strategy, peer, data) in [
+            (SendStrategy::Connected, &target, b"one"),
+            (SendStrategy::Broadcast, &broadcast, b"two"),
+            (SendStrategy::Connected, &target, b"six"),
+        ] {
+            tx.send(NetCommand::SetStrategy(strategy)).unwrap();
+            tx.send(NetCommand::SendRaw {
+                mode: InputMode::Text,
+                data: data.to_vec(),
+            })
+            .unwrap();
+            let n = peer.recv(&mut buffer).unwrap();
+            assert_eq!(&buffer[..n], data);
+        }
+
+        tx.send(NetCommand::Shutdown).unwrap();
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn dump_on_error_logs_the_decode_error_and_a_hexdump() {
+        let mut app = app(&["--dump-on-error"]);
+        // A msg_id and a PUBLISH header with no remaining length.
+        let raw = vec![0x00, 0x01, 0x30];
+        let Err(err) = UdpFrame::decode(&raw) else {
+            panic!("expected a malformed frame");
+        };
+
+        app.on_received(raw, None, SystemTime::now(), None);
+        let lines = logged(&app);
+        assert_eq!(lines[0], format!("← 3 bytes: decode failed: {:?}", err));
+        assert!(lines[1].contains("00 01 30"), "{}", lines[1]);
+    }
+
+    #[test]
+    fn pasted_lines_are_sent_separately() {
+        let mut split = app(&[]);
+        let rx = capture(&mut split);
+        split.input.set("pub a/b 1\n\n  pub a/c 2\n".into());
+        split.send();
+        assert_eq!(sent_inputs(&rx), ["pub a/b 1", "  pub a/c 2"]);
+
+        let mut literal = app(&["--literal-newlines"]);
+        let rx = capture(&mut literal);
+        literal.input.set("pub a/b 1\npub a/c 2".into());
+        literal.send();
+        assert_eq!(sent_inputs(&rx), ["pub a/b 1\npub a/c 2"]);
+    }
+
+    #[test]
+    fn log_file_gets_one_line_per_entry() {
+        let path = std::env::temp_dir().join(format!("udd-log-file-{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut app = app(&["--log-file", path.to_str().unwrap()]);
+
+        app.log_msg("first".into(), Style::default(), None);
+        app.log_msg("second".into(), Style::default(), None);
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let lines: Vec<_> = contents.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with(" first"), "{}", lines[0]);
+        assert!(lines[1].ends_with(" second"), "{}", lines[1]);
+    }
 }
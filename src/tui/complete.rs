@@ -0,0 +1,113 @@
+/// The commands the input line understands in Auto and MQTT mode.
+const COMMANDS: &[&str] = &[
+    "b64",
+    "connack",
+    "connect",
+    "disconnect",
+    "fuzz",
+    "ping",
+    "pingresp",
+    "pub",
+    "puback",
+    "suback",
+    "sub",
+];
+
+/// `:` commands.
+const META: &[&str] = &[
+    ":bind",
+    ":bookmark",
+    ":clear",
+    ":config",
+    ":every",
+    ":export",
+    ":filter",
+    ":help",
+    ":history",
+    ":macro",
+    ":mode",
+    ":quit",
+    ":save",
+    ":show",
+    ":target",
+    ":timer",
+    ":timers",
+];
+
+/// The option keys and flags a command takes after its first word.
+fn options(command: &str) -> &'static [&'static str] {
+    match command {
+        "connect" => &["keepalive=", "user=", "pass=", "clean="],
+        "pub" | "publish" => &["qos=", "retain"],
+        "sub" | "subscribe" => &["qos="],
+        "connack" => &["accepted", "rejected", "unavailable", "session="],
+        "suback" => &["0", "1", "2", "fail"],
+        ":mode" => &["auto", "text", "hex", "mqtt", "base64"],
+        ":macro" => &["record", "stop", "run", "list", "delete"],
+        ":timer" => &["pause", "resume", "cancel"],
+        ":bookmark" => &["next", "prev", "only", "clear"],
+        ":export" => &["text", "json", "raw"],
+        ":show" => &[
+            "all",
+            "sent",
+            "received",
+            "errors",
+            "connect",
+            "connack",
+            "publish",
+            "puback",
+            "subscribe",
+            "suback",
+            "pingreq",
+            "pingresp",
+            "disconnect",
+        ],
+        _ => &[],
+    }
+}
+
+/// Completions for the last word of `input`, with the byte offset where that word starts. A `:`
+/// command also matches fuzzily, its letters in order but not next to each other (`:tms` for
+/// `:timers`), when nothing starts with what was typed.
+pub(super) fn complete(input: &str) -> (usize, Vec<&'static str>) {
+    let start = input.rfind(' ').map_or(0, |i| i + 1);
+    let (head, word) = input.split_at(start);
+    let candidates = match head.split_whitespace().next() {
+        None if word.starts_with(':') => META,
+        None => COMMANDS,
+        Some(command) => options(&command.to_lowercase()),
+    };
+    let matches: Vec<_> = candidates
+        .iter()
+        .copied()
+        .filter(|candidate| candidate.starts_with(word))
+        .collect();
+    if !matches.is_empty() || candidates != META {
+        return (start, matches);
+    }
+    let fuzzy = META
+        .iter()
+        .copied()
+        .filter(|candidate| {
+            let mut letters = candidate.chars();
+            word.chars().all(|c| letters.any(|l| l == c))
+        })
+        .collect();
+    (start, fuzzy)
+}
+
+/// The longest prefix every candidate shares. Fuzzy matches may share less than was typed.
+pub(super) fn common_prefix<'a>(candidates: &[&'a str]) -> &'a str {
+    let Some((first, rest)) = candidates.split_first() else {
+        return "";
+    };
+    let len = rest.iter().fold(first.len(), |len, candidate| {
+        first
+            .bytes()
+            .zip(candidate.bytes())
+            .take(len)
+            .take_while(|(a, b)| a == b)
+            .count()
+    });
+    &first[..len]
+}
@@ -0,0 +1,190 @@
+use super::theme::Palette;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::Frame;
+use ratatui::layout::{Constraint, Flex, Layout, Rect};
+use ratatui::prelude::{Line, Span};
+use ratatui::widgets::{Block, Borders, Clear, Paragraph};
+
+const ROW: usize = 16;
+
+/// Payloads bigger than this (in compared byte pairs) are lined up by offset instead of by
+/// their longest common subsequence.
+const MAX_ALIGN: usize = 4_000_000;
+
+/// One column of the aligned payloads.
+#[derive(Clone, Copy)]
+enum Cell {
+    Same(u8),
+    Changed(u8, u8),
+    /// Only in the first payload.
+    Left(u8),
+    /// Only in the second payload.
+    Right(u8),
+}
+
+/// The diff popup for two marked log entries: their payloads aligned byte by byte, with the
+/// bytes that differ highlighted.
+pub(super) struct Diff {
+    title: String,
+    cells: Vec<Cell>,
+    top: usize,
+}
+
+impl Diff {
+    pub fn new(title: String, a: &[u8], b: &[u8]) -> Self {
+        Self {
+            title,
+            cells: align(a, b),
+            top: 0,
+        }
+    }
+
+    /// Scroll keys; any other key closes the popup and returns false.
+    pub fn key(&mut self, key: KeyEvent) -> bool {
+        let rows = self.cells.len().div_ceil(ROW);
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => self.top = self.top.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => self.top = (self.top + 1).min(rows),
+            KeyCode::PageUp => self.top = self.top.saturating_sub(8),
+            KeyCode::PageDown => self.top = (self.top + 8).min(rows),
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn draw(&mut self, f: &mut Frame, area: Rect, theme: &Palette) {
+        let [popup] = Layout::horizontal([Constraint::Length(72)])
+            .flex(Flex::Center)
+            .areas(area);
+        let [popup] = Layout::vertical([Constraint::Percentage(70)])
+            .flex(Flex::Center)
+            .areas(popup);
+
+        let changed = self
+            .cells
+            .iter()
+            .filter(|c| !matches!(c, Cell::Same(_)))
+            .count();
+        let mut lines = vec![
+            Line::styled(
+                format!("{} of {} aligned bytes differ", changed, self.cells.len()),
+                theme.heading,
+            ),
+            Line::default(),
+        ];
+        let height = (popup.height.saturating_sub(4) / 3).max(1) as usize;
+        let rows = self.cells.len().div_ceil(ROW);
+        self.top = self.top.min(rows.saturating_sub(height));
+        for row in self.top..rows.min(self.top + height) {
+            let cells = &self.cells[row * ROW..((row + 1) * ROW).min(self.cells.len())];
+            lines.push(side(row, cells, true, theme));
+            lines.push(side(row, cells, false, theme));
+            lines.push(Line::default());
+        }
+
+        let title = format!(
+            " {} (Up/Down to scroll, any other key to close) ",
+            self.title
+        );
+        f.render_widget(Clear, popup);
+        f.render_widget(
+            Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title)),
+            popup,
+        );
+    }
+}
+
+/// One payload's half of a row: `A 0010  00 01 -- 03 ...`, gaps as `--`.
+fn side(row: usize, cells: &[Cell], first: bool, theme: &Palette) -> Line<'static> {
+    let label = match first {
+        true => format!("A {:04x}  ", row * ROW),
+        false => "B       ".to_string(),
+    };
+    let mut spans = vec![Span::styled(label, theme.hint)];
+    for (i, cell) in cells.iter().enumerate() {
+        if i == 8 {
+            spans.push(Span::raw(" "));
+        }
+        let (byte, same) = match (*cell, first) {
+            (Cell::Same(b), _) => (Some(b), true),
+            (Cell::Changed(a, _), true) | (Cell::Left(a), true) => (Some(a), false),
+            (Cell::Changed(_, b), false) | (Cell::Right(b), false) => (Some(b), false),
+            (Cell::Left(_), false) | (Cell::Right(_), true) => (None, false),
+        };
+        let text = byte.map_or_else(|| "--".to_string(), |b| format!("{:02x}", b));
+        match same {
+            true => spans.push(Span::raw(text)),
+            false => spans.push(Span::styled(text, theme.error)),
+        }
+        spans.push(Span::raw(" "));
+    }
+    Line::from(spans)
+}
+
+/// Line `a` and `b` up on their longest common subsequence, so an inserted or dropped byte
+/// shows as a gap rather than shifting everything after it. Runs of bytes only in one payload
+/// facing a run only in the other are paired up as changed bytes.
+fn align(a: &[u8], b: &[u8]) -> Vec<Cell> {
+    if a.len().saturating_mul(b.len()) > MAX_ALIGN {
+        let mut cells: Vec<Cell> = a
+            .iter()
+            .zip(b)
+            .map(|(&x, &y)| {
+                if x == y {
+                    Cell::Same(x)
+                } else {
+                    Cell::Changed(x, y)
+                }
+            })
+            .collect();
+        cells.extend(a.iter().skip(b.len()).map(|&x| Cell::Left(x)));
+        cells.extend(b.iter().skip(a.len()).map(|&y| Cell::Right(y)));
+        return cells;
+    }
+
+    // lcs[i][j]: the common subsequence length of a[i..] and b[j..].
+    let width = b.len() + 1;
+    let mut lcs = vec![0u32; (a.len() + 1) * width];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i * width + j] = match a[i] == b[j] {
+                true => lcs[(i + 1) * width + j + 1] + 1,
+                false => lcs[(i + 1) * width + j].max(lcs[i * width + j + 1]),
+            };
+        }
+    }
+
+    let mut cells = vec![];
+    let (mut left, mut right) = (vec![], vec![]);
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            flush(&mut cells, &mut left, &mut right);
+            cells.push(Cell::Same(a[i]));
+            i += 1;
+            j += 1;
+        } else if j == b.len()
+            || (i < a.len() && lcs[(i + 1) * width + j] >= lcs[i * width + j + 1])
+        {
+            left.push(a[i]);
+            i += 1;
+        } else {
+            right.push(b[j]);
+            j += 1;
+        }
+    }
+    flush(&mut cells, &mut left, &mut right);
+    cells
+}
+
+/// Emit the bytes seen since the last common one: paired as changes, then the leftovers.
+fn flush(cells: &mut Vec<Cell>, left: &mut Vec<u8>, right: &mut Vec<u8>) {
+    let paired = left.len().min(right.len());
+    cells.extend(
+        left.iter()
+            .zip(right.iter())
+            .map(|(&x, &y)| Cell::Changed(x, y)),
+    );
+    cells.extend(left.drain(..).skip(paired).map(Cell::Left));
+    cells.extend(right.drain(..).skip(paired).map(Cell::Right));
+}
@@ -0,0 +1,311 @@
+use super::format::suback_label;
+use super::theme::Palette;
+use mqtt::{Packet, UdpFrame};
+use ratatui::prelude::{Line, Span, Style, Stylize};
+use std::collections::HashSet;
+use std::ops::Range;
+
+/// A decoded MQTT field for the inspector tree, with the bytes of the frame it was read from.
+///
+/// A frame is the two-byte msg_id, the fixed header (the type and flags byte, then the remaining
+/// length) and the packet's own bytes, so those ranges follow from the layout. The codec doesn't
+/// report offsets within a packet, so strings are found by their MQTT length prefix from the
+/// start of the packet on, and the payload at the tail. A value that can't be found has no range.
+pub(super) struct Field {
+    label: String,
+    range: Option<Range<usize>>,
+    children: Vec<Field>,
+}
+
+impl Field {
+    fn leaf(label: String, range: Option<Range<usize>>) -> Self {
+        Self {
+            label,
+            range,
+            children: vec![],
+        }
+    }
+
+    /// What collapsing is remembered by, so a collapsed `payload` stays collapsed from one
+    /// entry to the next.
+    fn key(&self) -> &str {
+        self.label.split(':').next().unwrap_or(&self.label)
+    }
+}
+
+/// The field tree of an MQTT frame, or `None` if `data` doesn't decode.
+pub(super) fn decode(data: &[u8]) -> Option<Vec<Field>> {
+    let frame = UdpFrame::decode(data).ok()?;
+    let &byte = data.get(2)?;
+    let (length, header_end) = remaining_length(data, 3)?;
+
+    let name = match &frame.packet {
+        Packet::Connect(_) => "CONNECT",
+        Packet::ConnAck(_) => "CONNACK",
+        Packet::Publish(_) => "PUBLISH",
+        Packet::PubAck(_) => "PUBACK",
+        Packet::Subscribe(_) => "SUBSCRIBE",
+        Packet::SubAck(_) => "SUBACK",
+        Packet::Ping(_) => "PINGREQ",
+        Packet::Pong(_) => "PINGRESP",
+        Packet::Disconnect(_) => "DISCONNECT",
+    };
+    let bit = |label: String| Field::leaf(label, Some(2..3));
+    let mut header_fields = vec![bit(format!("type: {} ({})", byte >> 4, name))];
+    if let Packet::Publish(p) = &frame.packet {
+        header_fields.push(bit(format!("dup: {}", byte >> 3 & 1)));
+        header_fields.push(bit(format!("qos: {} ({:?})", byte >> 1 & 0b11, p.qos)));
+        header_fields.push(bit(format!("retain: {}", byte & 1)));
+    } else {
+        header_fields.push(bit(format!("flags: {:04b}", byte & 0x0f)));
+    }
+    header_fields.push(Field::leaf(
+        format!("remaining length: {}", length),
+        Some(3..header_end),
+    ));
+
+    let body = match &frame.packet {
+        Packet::Connect(c) => {
+            let client_id = string(data, &c.client_id, header_end);
+            vec![
+                Field::leaf(format!("client_id: {}", c.client_id), client_id),
+                Field::leaf(format!("keep_alive: {}s", c.keep_alive), None),
+                Field::leaf(format!("clean_session: {}", c.clean_session), None),
+            ]
+        }
+        Packet::ConnAck(c) => vec![
+            Field::leaf(format!("return_code: {:?}", c.return_code), None),
+            Field::leaf(format!("session_present: {}", c.session_present), None),
+        ],
+        Packet::Publish(p) => {
+            let topic = string(data, &p.topic, header_end);
+            let payload = data
+                .ends_with(&p.payload)
+                .then(|| data.len() - p.payload.len()..data.len());
+            vec![
+                Field::leaf(format!("topic: {}", p.topic), topic),
+                Field {
+                    label: format!("payload: {} bytes", p.payload.len()),
+                    range: payload,
+                    children: vec![Field::leaf(
+                        format!("\"{}\"", String::from_utf8_lossy(&p.payload)),
+                        None,
+                    )],
+                },
+            ]
+        }
+        Packet::Subscribe(s) => {
+            let mut from = header_end;
+            s.filters
+                .iter()
+                .map(|filter| {
+                    // Each topic filter is followed by its requested QoS byte.
+                    let range = string(data, &filter.topic, from).map(|r| {
+                        from = r.end;
+                        r.start..(r.end + 1).min(data.len())
+                    });
+                    Field::leaf(
+                        format!("filter: {} qos={:?}", filter.topic, filter.qos),
+                        range,
+                    )
+                })
+                .collect()
+        }
+        Packet::SubAck(s) => {
+            let first = data.len().saturating_sub(s.return_codes.len());
+            s.return_codes
+                .iter()
+                .enumerate()
+                .map(|(i, code)| {
+                    Field::leaf(
+                        format!("return_code[{}]: {}", i, suback_label(code)),
+                        Some(first + i..first + i + 1),
+                    )
+                })
+                .collect()
+        }
+        Packet::PubAck(_) | Packet::Ping(_) | Packet::Pong(_) | Packet::Disconnect(_) => vec![],
+    };
+
+    Some(vec![
+        Field::leaf(format!("msg_id: {}", frame.msg_id), Some(0..2)),
+        Field {
+            label: format!("fixed header: 0x{:02x}", byte),
+            range: Some(2..header_end),
+            children: header_fields,
+        },
+        Field {
+            label: name.to_string(),
+            range: None,
+            children: body,
+        },
+    ])
+}
+
+/// The fixed header's remaining length starting at `at`, and where it ends. A byte with the
+/// high bit clear ends it, so the single-byte form reads the same way.
+fn remaining_length(data: &[u8], at: usize) -> Option<(usize, usize)> {
+    let mut length = 0;
+    for (i, &b) in data.get(at..)?.iter().take(4).enumerate() {
+        length |= usize::from(b & 0x7f) << (7 * i);
+        if b & 0x80 == 0 {
+            return Some((length, at + i + 1));
+        }
+    }
+    None
+}
+
+/// Where `needle` first occurs in `data` at or after `from`.
+fn find(data: &[u8], needle: &[u8], from: usize) -> Option<Range<usize>> {
+    if needle.is_empty() || from > data.len() {
+        return None;
+    }
+    data[from..]
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|at| from + at..from + at + needle.len())
+}
+
+/// Where string `s` is encoded, preferably with its two-byte length prefix.
+fn string(data: &[u8], s: &str, from: usize) -> Option<Range<usize>> {
+    let len = u16::try_from(s.len()).ok()?.to_be_bytes();
+    let prefixed = [&len[..], s.as_bytes()].concat();
+    find(data, &prefixed, from).or_else(|| find(data, s.as_bytes(), from))
+}
+
+/// Which inspector fields are collapsed and which one has the cursor, kept across entries.
+#[derive(Default)]
+pub(super) struct Tree {
+    collapsed: HashSet<String>,
+    cursor: usize,
+}
+
+impl Tree {
+    /// Move the cursor by `delta` rows; it is clamped when the tree is drawn.
+    pub fn move_cursor(&mut self, delta: isize) {
+        self.cursor = self.cursor.saturating_add_signed(delta);
+    }
+
+    /// Expand or collapse the field under the cursor.
+    pub fn toggle(&mut self, fields: &[Field]) {
+        let rows = self.rows(fields);
+        let Some((_, field)) = rows.get(self.cursor.min(rows.len().saturating_sub(1))) else {
+            return;
+        };
+        if field.children.is_empty() {
+            return;
+        }
+        let key = field.key().to_string();
+        if !self.collapsed.remove(&key) {
+            self.collapsed.insert(key);
+        }
+    }
+
+    /// The fields shown, with their depth: children of collapsed fields are left out.
+    fn rows<'a>(&self, fields: &'a [Field]) -> Vec<(usize, &'a Field)> {
+        fn walk<'a>(
+            tree: &Tree,
+            fields: &'a [Field],
+            depth: usize,
+            out: &mut Vec<(usize, &'a Field)>,
+        ) {
+            for field in fields {
+                out.push((depth, field));
+                if !tree.collapsed.contains(field.key()) {
+                    walk(tree, &field.children, depth + 1, out);
+                }
+            }
+        }
+        let mut out = vec![];
+        walk(self, fields, 0, &mut out);
+        out
+    }
+
+    /// The tree as lines, the cursor's row reversed, and the byte range of the field under it.
+    pub fn lines(
+        &mut self,
+        fields: &[Field],
+        theme: &Palette,
+    ) -> (Vec<Line<'static>>, Option<Range<usize>>) {
+        let rows = self.rows(fields);
+        self.cursor = self.cursor.min(rows.len().saturating_sub(1));
+        let lines = rows
+            .iter()
+            .enumerate()
+            .map(|(i, (depth, field))| {
+                let marker = match (
+                    field.children.is_empty(),
+                    self.collapsed.contains(field.key()),
+                ) {
+                    (true, _) => "  ",
+                    (false, true) => "▸ ",
+                    (false, false) => "▾ ",
+                };
+                let range = field
+                    .range
+                    .as_ref()
+                    .map_or_else(String::new, |r| format!("  [{}..{}]", r.start, r.end));
+                let style = match i == self.cursor {
+                    true => theme.mqtt.reversed(),
+                    false => theme.mqtt,
+                };
+                Line::from(vec![
+                    Span::styled(
+                        format!("{}{}{}", "  ".repeat(*depth), marker, field.label),
+                        style,
+                    ),
+                    Span::styled(range, theme.hint),
+                ])
+            })
+            .collect();
+        let range = rows.get(self.cursor).and_then(|(_, f)| f.range.clone());
+        (lines, range)
+    }
+}
+
+/// Columns before the ASCII column of a full hex dump row: offset, gap, 16 cells and a space.
+const DUMP_WIDTH: usize = 10 + 49 + 1;
+
+/// A hex dump like `utils::hexdump`, with the bytes in `highlight` picked out in `style`.
+pub(super) fn hexdump(
+    data: &[u8],
+    highlight: Option<Range<usize>>,
+    style: Style,
+) -> Vec<Line<'static>> {
+    let marked = |i: usize| highlight.as_ref().is_some_and(|r| r.contains(&i));
+    data.chunks(16)
+        .enumerate()
+        .map(|(row, chunk)| {
+            let mut spans = vec![Span::raw(format!("{:08x}  ", row * 16))];
+            let mut ascii = vec![Span::raw("|")];
+            for (j, &b) in chunk.iter().enumerate() {
+                if j == 8 {
+                    spans.push(Span::raw(" "));
+                }
+                let c = if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                };
+                let (hex, c) = (format!("{:02x}", b), c.to_string());
+                match marked(row * 16 + j) {
+                    true => {
+                        spans.push(Span::styled(hex, style));
+                        ascii.push(Span::styled(c, style));
+                    }
+                    false => {
+                        spans.push(Span::raw(hex));
+                        ascii.push(Span::raw(c));
+                    }
+                }
+                spans.push(Span::raw(" "));
+            }
+            // Pad a short last row so the ASCII column lines up.
+            let width: usize = spans.iter().map(|s| s.content.len()).sum();
+            spans.push(Span::raw(" ".repeat(DUMP_WIDTH.saturating_sub(width))));
+            ascii.push(Span::raw("|"));
+            spans.extend(ascii);
+            Line::from(spans)
+        })
+        .collect()
+}
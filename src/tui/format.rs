@@ -1,19 +1,17 @@
-use crate::InputMode;
-use mqtt::{Packet, UdpFrame};
+use crate::{InputMode, TextEncoding, json, utils};
+use mqtt::{Packet, SubAckReturnCode, UdpFrame};
 use std::borrow::Cow;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 /// Format payload for display
-pub fn format(data: &[u8]) -> Cow<'_, str> {
+pub fn format(data: &[u8], encoding: TextEncoding) -> Cow<'_, str> {
     if let Some(pretty) = format_mqtt_frame(data) {
         return Cow::Owned(pretty);
     }
 
-    if let Some(s) = str::from_utf8(data).ok() {
-        return if s.len() > 50 {
-            Cow::Owned(format!("{}...", &s[..47]))
-        } else {
-            Cow::Borrowed(s)
-        };
+    if let Some(text) = format_text(data, encoding) {
+        return text;
     }
 
     let hex: String = data
@@ -30,13 +28,44 @@ pub fn format(data: &[u8]) -> Cow<'_, str> {
 }
 
 /// Format payload for display, honoring the selected input mode.
-pub fn format_for_mode(mode: InputMode, data: &[u8]) -> Cow<'_, str> {
+pub fn format_for_mode(mode: InputMode, encoding: TextEncoding, data: &[u8]) -> Cow<'_, str> {
     match mode {
         InputMode::Hex => Cow::Owned(format_hex(data)),
         InputMode::Mqtt => Cow::Owned(format_mqtt_frame(data).unwrap_or_else(|| format_hex(data))),
-        InputMode::Text => format_text(data).unwrap_or_else(|| Cow::Owned(format_hex(data))),
-        InputMode::Auto => format(data),
+        InputMode::Text => {
+            format_text(data, encoding).unwrap_or_else(|| Cow::Owned(format_hex(data)))
+        }
+        InputMode::Base64 => Cow::Owned(format_base64(data)),
+        InputMode::Auto => format(data, encoding),
+    }
+}
+
+/// Render a log line from a user template.
+///
+/// Placeholders: `{dir}`, `{mode}`, `{bytes}`, `{hex}` (full payload), `{mqtt}` (decoded frame
+/// or `-`) and `{preview}` (the mode-specific summary shown by default).
+pub fn render_template(
+    template: &str,
+    dir: &str,
+    mode: InputMode,
+    bytes: usize,
+    data: &[u8],
+    preview: &str,
+) -> String {
+    let mut line = template
+        .replace("{dir}", dir)
+        .replace("{mode}", mode.short_label())
+        .replace("{bytes}", &bytes.to_string())
+        .replace("{preview}", preview);
+    if line.contains("{hex}") {
+        let hex: Vec<_> = data.iter().map(|b| format!("{:02x}", b)).collect();
+        line = line.replace("{hex}", &hex.join(" "));
     }
+    if line.contains("{mqtt}") {
+        let mqtt = format_mqtt_frame(data).unwrap_or_else(|| "-".into());
+        line = line.replace("{mqtt}", &mqtt);
+    }
+    line
 }
 
 fn format_hex(data: &[u8]) -> String {
@@ -52,20 +81,132 @@ fn format_hex(data: &[u8]) -> String {
     }
 }
 
-fn format_text(data: &[u8]) -> Option<Cow<'_, str>> {
-    str::from_utf8(data).ok().map(|s| {
-        if s.len() > 50 {
-            Cow::Owned(format!("{}...", &s[..47]))
-        } else {
-            Cow::Borrowed(s)
+fn format_base64(data: &[u8]) -> String {
+    // 36 bytes encode to 48 characters, matching the width of the hex preview.
+    let encoded = utils::base64(&data[..data.len().min(36)]);
+    if data.len() > 36 {
+        format!("{}...", encoded)
+    } else {
+        encoded
+    }
+}
+
+/// Decode text in the given encoding. Only UTF-8 can fail; latin1 maps each byte to the same
+/// code point and ascii shows high bytes as `.`.
+fn format_text(data: &[u8], encoding: TextEncoding) -> Option<Cow<'_, str>> {
+    let text = match encoding {
+        TextEncoding::Utf8 => Cow::Borrowed(str::from_utf8(data).ok()?),
+        TextEncoding::Latin1 => Cow::Owned(data.iter().map(|&b| b as char).collect()),
+        TextEncoding::Ascii => Cow::Owned(
+            data.iter()
+                .map(|&b| if b.is_ascii() { b as char } else { '.' })
+                .collect(),
+        ),
+    };
+
+    if text.width() > 50 {
+        Some(Cow::Owned(truncate(&text, 50).into_owned()))
+    } else {
+        Some(text)
+    }
+}
+
+/// Cut `text` to at most `width` terminal columns, ending it with `...` if anything was cut.
+/// Cuts fall between graphemes and count wide characters as two columns.
+pub fn truncate(text: &str, width: usize) -> Cow<'_, str> {
+    if text.width() <= width {
+        return Cow::Borrowed(text);
+    }
+    let mut head = String::new();
+    let mut used = 0;
+    for g in text.graphemes(true) {
+        used += g.width();
+        if used > width.saturating_sub(3) {
+            break;
         }
-    })
+        head.push_str(g);
+    }
+    head.push_str("...");
+    Cow::Owned(head)
+}
+
+/// `text` without its first `columns` terminal columns. A wide character cut in half is dropped.
+pub fn skip_columns(text: &str, columns: usize) -> &str {
+    let mut used = 0;
+    for (at, g) in text.grapheme_indices(true) {
+        if used >= columns {
+            return &text[at..];
+        }
+        used += g.width();
+    }
+    ""
+}
+
+/// Break `text` into lines of at most `width` columns, at a space where there is one.
+pub fn wrap(text: &str, width: usize) -> Vec<&str> {
+    let width = width.max(1);
+    let mut lines = vec![];
+    let mut rest = text;
+    while rest.width() > width {
+        let mut end = 0;
+        let mut space = None;
+        let mut used = 0;
+        for (at, g) in rest.grapheme_indices(true) {
+            used += g.width();
+            if used > width {
+                break;
+            }
+            if g == " " {
+                space = Some(at);
+            }
+            end = at + g.len();
+        }
+        // A grapheme wider than the line still has to go somewhere.
+        if end == 0 {
+            end = rest.graphemes(true).next().map_or(rest.len(), str::len);
+        }
+        let (line, next) = match space {
+            Some(at) if at > 0 => (&rest[..at], &rest[at + 1..]),
+            _ => rest.split_at(end),
+        };
+        lines.push(line);
+        rest = next;
+    }
+    lines.push(rest);
+    lines
+}
+
+/// Cut `text` into rows of at most `width` columns, between graphemes, and at each newline.
+/// Rows come with the byte offset they start at. Always at least one row.
+pub fn chop(text: &str, width: usize) -> Vec<(usize, &str)> {
+    let width = width.max(1);
+    let mut rows = vec![];
+    let (mut start, mut used) = (0, 0);
+    for (at, g) in text.grapheme_indices(true) {
+        if g == "\n" || g == "\r\n" {
+            rows.push((start, &text[start..at]));
+            (start, used) = (at + g.len(), 0);
+            continue;
+        }
+        if used + g.width() > width && at > start {
+            rows.push((start, &text[start..at]));
+            (start, used) = (at, 0);
+        }
+        used += g.width();
+    }
+    rows.push((start, &text[start..]));
+    rows
 }
 
 /// Decode and format MQTT frame for display
 fn format_mqtt_frame(data: &[u8]) -> Option<String> {
-    let frame = UdpFrame::decode(data).ok()?;
+    UdpFrame::decode(data)
+        .ok()
+        .map(|frame| frame_summary(&frame))
+}
 
+/// One-line summary of a decoded frame, e.g. `#7 SUBACK [QoS1, FAILURE]`.
+fn frame_summary(frame: &UdpFrame) -> String {
     let pkt_str = match &frame.packet {
         Packet::Connect(c) => {
             format!("CONNECT client={} ka={}", c.client_id, c.keep_alive)
@@ -73,25 +214,186 @@ fn format_mqtt_frame(data: &[u8]) -> Option<String> {
         Packet::ConnAck(c) => {
             format!("CONNACK {:?} session={}", c.return_code, c.session_present)
         }
+        // An empty topic is illegal in MQTT, so call it out if the codec lets one through.
+        Packet::Publish(p) if p.topic.is_empty() => {
+            format!("PUBLISH <malformed: empty topic> qos={:?}", p.qos)
+        }
         Packet::Publish(p) => {
             let payload_preview = String::from_utf8_lossy(&p.payload);
-            let preview = if payload_preview.len() > 30 {
-                format!("{}...", &payload_preview[..27])
+            let preview = truncate(&payload_preview, 30);
+            if p.payload.is_empty() {
+                format!("PUBLISH {} qos={:?} (empty payload)", p.topic, p.qos)
             } else {
-                payload_preview.into_owned()
-            };
-            format!("PUBLISH {} qos={:?} \"{}\"", p.topic, p.qos, preview)
+                format!("PUBLISH {} qos={:?} \"{}\"", p.topic, p.qos, preview)
+            }
         }
         Packet::PubAck(_) => "PUBACK".into(),
         Packet::Subscribe(s) => {
             let topics: Vec<_> = s.filters.iter().map(|f| f.topic.as_str()).collect();
             format!("SUBSCRIBE [{}]", topics.join(", "))
         }
-        Packet::SubAck(s) => format!("SUBACK {:?}", s.return_codes),
+        Packet::SubAck(s) => {
+            let codes: Vec<_> = s.return_codes.iter().map(suback_label).collect();
+            format!("SUBACK [{}]", codes.join(", "))
+        }
         Packet::Ping(_) => "PING".into(),
         Packet::Pong(_) => "PONG".into(),
         Packet::Disconnect(_) => "DISCONNECT".into(),
     };
 
-    Some(format!("#{} {}", frame.msg_id, pkt_str))
+    format!("#{} {}", frame.msg_id, pkt_str)
+}
+
+/// Decoded MQTT frame as a JSON object, for machine-readable output.
+pub fn mqtt_json(data: &[u8]) -> Option<String> {
+    let frame = UdpFrame::decode(data).ok()?;
+    let object = json::Object::new().num("msg_id", frame.msg_id);
+
+    let object = match &frame.packet {
+        Packet::Connect(c) => object
+            .str("type", "CONNECT")
+            .str("client_id", &c.client_id)
+            .num("keep_alive", c.keep_alive),
+        Packet::ConnAck(c) => object
+            .str("type", "CONNACK")
+            .str("return_code", &format!("{:?}", c.return_code))
+            .bool("session_present", c.session_present),
+        Packet::Publish(p) => object
+            .str("type", "PUBLISH")
+            .str("topic", &p.topic)
+            .str("qos", &format!("{:?}", p.qos))
+            .str("payload", &String::from_utf8_lossy(&p.payload)),
+        Packet::PubAck(_) => object.str("type", "PUBACK"),
+        Packet::Subscribe(s) => object
+            .str("type", "SUBSCRIBE")
+            .strs("topics", s.filters.iter().map(|f| f.topic.as_str())),
+        Packet::SubAck(s) => object
+            .str("type", "SUBACK")
+            .strs("return_codes", s.return_codes.iter().map(suback_label)),
+        Packet::Ping(_) => object.str("type", "PING"),
+        Packet::Pong(_) => object.str("type", "PONG"),
+        Packet::Disconnect(_) => object.str("type", "DISCONNECT"),
+    };
+
+    Some(object.finish())
+}
+
+pub fn suback_label(code: &SubAckReturnCode) -> &'static str {
+    match code {
+        SubAckReturnCode::SuccessQoS0 => "QoS0",
+        SubAckReturnCode::SuccessQoS1 => "QoS1",
+        SubAckReturnCode::SuccessQoS2 => "QoS2",
+        SubAckReturnCode::Failure => "FAILURE",
+    }
+}
+
+/// Warning for a Publish with a zero-length payload, which many devices treat as a delete.
+pub fn empty_publish_warning(data: &[u8]) -> Option<&'static str> {
+    match UdpFrame::decode(data) {
+        Ok(UdpFrame {
+            packet: Packet::Publish(p),
+            ..
+        }) if p.payload.is_empty() => {
+            Some("publish has an empty payload (clears the retained message on most brokers)")
+        }
+        _ => None,
+    }
+}
+
+/// The packet type of an MQTT frame, e.g. `PUBLISH`.
+pub fn packet_name(data: &[u8]) -> Option<&'static str> {
+    Some(match UdpFrame::decode(data).ok()?.packet {
+        Packet::Connect(_) => "CONNECT",
+        Packet::ConnAck(_) => "CONNACK",
+        Packet::Publish(_) => "PUBLISH",
+        Packet::PubAck(_) => "PUBACK",
+        Packet::Subscribe(_) => "SUBSCRIBE",
+        Packet::SubAck(_) => "SUBACK",
+        Packet::Ping(_) => "PINGREQ",
+        Packet::Pong(_) => "PINGRESP",
+        Packet::Disconnect(_) => "DISCONNECT",
+    })
+}
+
+/// The topic of a PUBLISH frame.
+pub fn publish_topic(data: &[u8]) -> Option<String> {
+    match UdpFrame::decode(data).ok()?.packet {
+        Packet::Publish(p) => Some(p.topic),
+        _ => None,
+    }
+}
+
+/// Whether the payload is a SubAck in which at least one filter was refused.
+pub fn is_suback_refused(data: &[u8]) -> bool {
+    match UdpFrame::decode(data) {
+        Ok(UdpFrame {
+            packet: Packet::SubAck(s),
+            ..
+        }) => s
+            .return_codes
+            .iter()
+            .any(|code| matches!(code, SubAckReturnCode::Failure)),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mqtt::{Publish, SubAck};
+
+    #[test]
+    fn suback_lists_friendly_return_codes() {
+        let frame = UdpFrame::new(
+            7,
+            SubAck::new(vec![
+                SubAckReturnCode::SuccessQoS1,
+                SubAckReturnCode::Failure,
+            ]),
+        );
+        assert_eq!(frame_summary(&frame), "#7 SUBACK [QoS1, FAILURE]");
+    }
+
+    #[test]
+    fn text_encoding_decides_how_high_bytes_render() {
+        let data = [b'c', b'a', b'f', 0xe9];
+        let text = |encoding| format_for_mode(InputMode::Text, encoding, &data);
+        assert_eq!(text(TextEncoding::Utf8), "63 61 66 e9");
+        assert_eq!(text(TextEncoding::Latin1), "café");
+        assert_eq!(text(TextEncoding::Ascii), "caf.");
+        assert_eq!(format(&[0xe9], TextEncoding::Latin1), "é");
+    }
+
+    #[test]
+    fn template_fills_placeholders() {
+        let line = render_template(
+            "{dir} {mode} {bytes}B [{hex}] {mqtt}",
+            "→",
+            InputMode::Hex,
+            3,
+            &[0xde, 0xad, 0x01],
+            "de ad 01",
+        );
+        assert_eq!(line, "→ HEX 3B [de ad 01] -");
+    }
+
+    #[test]
+    fn empty_publish_is_flagged() {
+        let empty = frame_summary(&UdpFrame::new(3, Publish::new("a/b", "")));
+        assert!(empty.starts_with("#3 PUBLISH a/b "), "{}", empty);
+        assert!(empty.ends_with(" (empty payload)"), "{}", empty);
+
+        let full = frame_summary(&UdpFrame::new(3, Publish::new("a/b", "21.5")));
+        assert!(full.ends_with(" \"21.5\""), "{}", full);
+    }
+
+    #[test]
+    fn empty_topic_is_called_malformed() {
+        let summary = frame_summary(&UdpFrame::new(4, Publish::new("", "x")));
+        assert!(
+            summary.starts_with("#4 PUBLISH <malformed: empty topic>"),
+            "{}",
+            summary
+        );
+    }
 }
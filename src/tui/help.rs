@@ -0,0 +1,201 @@
+use super::theme::Palette;
+use ratatui::Frame;
+use ratatui::layout::{Constraint, Flex, Layout, Rect};
+use ratatui::prelude::{Line, Span, Style, Stylize};
+use ratatui::widgets::{Block, Borders, Clear, Paragraph};
+
+/// `(keys, what they do)`, grouped under headings with an empty key column.
+const HELP: &[(&str, &str)] = &[
+    ("", "Keys"),
+    ("Enter", "send the input line"),
+    (
+        "Alt+Enter",
+        "multi-line input: Enter adds a line, Ctrl+S sends",
+    ),
+    (
+        "Tab",
+        "complete a command or option; cycle the mode on an empty line",
+    ),
+    ("Up/Down", "walk the input history of the current mode"),
+    ("Left/Right Home/End", "move the cursor"),
+    ("PageUp/PageDown", "scroll the log a screenful"),
+    ("Ctrl+Home/End", "jump to the oldest / newest log entry"),
+    (
+        "Ctrl+W / Ctrl+U",
+        "delete the word / everything before the cursor",
+    ),
+    (
+        "Ctrl+E",
+        "compose a payload in a hex editor; Enter sends it",
+    ),
+    (
+        "/",
+        "search the log (on an empty line); n/N jump, f filters",
+    ),
+    ("F2", "toggle the inspector pane"),
+    ("F3", "split the log into sent and received columns"),
+    ("F4", "toggle the MQTT session panel"),
+    ("F6", "wrap long entries instead of cutting them off"),
+    (
+        "Left/Right",
+        "scroll the log sideways, on an empty line (or Shift+wheel)",
+    ),
+    (
+        "Ctrl+arrows",
+        "resize the side panels (Left/Right) and the input (Up/Down)",
+    ),
+    ("Alt+Up/Down", "select a log entry for the inspector"),
+    (
+        "Esc (keys = vim)",
+        "normal mode: gg/G, Ctrl+D/U, yy copies, i or : to type",
+    ),
+    (
+        "Shift+Tab",
+        "focus the log: j/k select, Enter resends, Esc returns",
+    ),
+    ("Alt+V", "view the selected entry as text, hex, then MQTT"),
+    ("Alt+M / Alt+D", "mark two entries / diff their payloads"),
+    ("Alt+E", "load the selected entry into the input to edit"),
+    (
+        "Alt+B / Alt+N/P",
+        "bookmark the selected entry / jump to the next/previous",
+    ),
+    (
+        "[ ] / Space",
+        "in the focused log: pick an MQTT field / fold it",
+    ),
+    (
+        "Ctrl+Y / Alt+Y",
+        "copy the selected payload as hex / its log line",
+    ),
+    (
+        "Ctrl+T",
+        "cycle timestamps: off, wall clock, since previous",
+    ),
+    ("Ctrl+P", "pause / resume received traffic"),
+    ("Ctrl+G", "graph bytes or packets per second"),
+    (
+        "Ctrl+F",
+        "follow the newest entry (scrolling up stops following)",
+    ),
+    (
+        "End",
+        "jump to new entries when scrolled up (on an empty line)",
+    ),
+    ("Ctrl+B", "toggle sending to the broadcast address"),
+    ("Ctrl+R", "restart the network thread"),
+    (
+        "click",
+        "resend an entry's payload; right-click inspects, drag copies",
+    ),
+    ("? / Esc", "this help / quit"),
+    ("", "Modes"),
+    (
+        "AUTO",
+        "MQTT command, then hex, then text; fuzz <len>, b64 <data>",
+    ),
+    (
+        "TEXT",
+        "text with escapes (\\n, \\x01) and {seq} {ts} {ts_ms} {rand:N}",
+    ),
+    ("HEX", "hex bytes, spaces allowed"),
+    ("MQTT", "MQTT commands only"),
+    ("B64", "base64"),
+    (
+        "@host:port",
+        "prefix a line to send just that datagram elsewhere",
+    ),
+    ("", "MQTT commands"),
+    (
+        "connect",
+        "<client_id> [keepalive=N] [user=X] [pass=X] [clean=1]",
+    ),
+    ("pub", "<topic> [payload] [qos=0|1|2] [retain]"),
+    ("sub", "<topic>[,<topic>...] [qos=0|1|2]"),
+    ("ping / disconnect", "PINGREQ / DISCONNECT"),
+    ("puback / pong", "bare acknowledgements"),
+    ("connack", "[accepted|rejected|unavailable] [session=1]"),
+    ("suback", "<0|1|2|fail>..."),
+    ("", "Commands"),
+    (":clear", "empty the log"),
+    (":config", "show the effective settings"),
+    (
+        ":filter <query>",
+        "show only matching entries; :filter alone shows all",
+    ),
+    (
+        ":bookmark <action>",
+        "next, prev, only (show just bookmarks) or clear",
+    ),
+    (
+        ":show <what>",
+        "list only sent, received, errors or an MQTT type; all",
+    ),
+    (":mode <mode>", "switch to auto, text, hex, mqtt or base64"),
+    (":save [path]", "write the log to a file (udd-log.txt)"),
+    (
+        ":export <fmt> [path]",
+        "write the entries between two Alt+M marks: text, json, raw",
+    ),
+    (":quit", "leave; Tab completes commands, fuzzily"),
+    (":help", "this help"),
+    ("::<text>", "send a line starting with a literal :"),
+    (":history", "list recent input"),
+    (
+        ":every <time> <line>",
+        "resend a line on a timer, e.g. :every 2s pub hb ok",
+    ),
+    (
+        ":timer <action> <id>",
+        "pause, resume or cancel a timer; :timers lists them",
+    ),
+    (
+        ":macro record <name>",
+        "record the following sends; :macro stop saves",
+    ),
+    (
+        ":macro run <name>",
+        "replay a macro with its pacing (F5: the last one)",
+    ),
+    (":macro list / delete", "list macros / delete one"),
+    (
+        ":target <host:port>",
+        "send to a new target, keeping the log",
+    ),
+    (
+        ":bind <addr:port>",
+        "rebind the local socket, keeping the log",
+    ),
+];
+
+/// Draw the help popup centered over `area`.
+pub fn draw(f: &mut Frame, area: Rect, theme: &Palette) {
+    let width = HELP.iter().map(|(k, _)| k.len()).max().unwrap_or(0);
+    let lines: Vec<Line> = HELP
+        .iter()
+        .map(|&(keys, text)| match keys {
+            "" => Line::styled(text, theme.heading),
+            _ => Line::from(vec![
+                Span::styled(format!("  {:<width$}  ", keys), Style::default().bold()),
+                Span::raw(text),
+            ]),
+        })
+        .collect();
+
+    let [popup] = Layout::horizontal([Constraint::Length(90)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [popup] = Layout::vertical([Constraint::Length(lines.len() as u16 + 2)])
+        .flex(Flex::Center)
+        .areas(popup);
+
+    f.render_widget(Clear, popup);
+    f.render_widget(
+        Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" Help (any key to close) "),
+        ),
+        popup,
+    );
+}
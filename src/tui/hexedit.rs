@@ -0,0 +1,175 @@
+use super::theme::Palette;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::Frame;
+use ratatui::layout::{Constraint, Flex, Layout, Rect};
+use ratatui::prelude::{Line, Span, Stylize};
+use ratatui::widgets::{Block, Borders, Clear, Paragraph};
+
+const ROW: usize = 16;
+
+/// The compose popup's byte buffer, edited a nibble at a time. `cursor` may sit one past the
+/// last byte, where typing appends.
+#[derive(Default)]
+pub(super) struct HexEditor {
+    pub data: Vec<u8>,
+    cursor: usize,
+    /// The cursor is on the low nibble of its byte.
+    low: bool,
+    /// First row drawn, kept so the cursor stays in view.
+    top: usize,
+}
+
+/// What a key did to the editor.
+pub(super) enum Outcome {
+    Edited,
+    Send,
+    Close,
+    Ignored,
+}
+
+impl HexEditor {
+    pub fn new(data: Vec<u8>) -> Self {
+        Self {
+            data,
+            ..Self::default()
+        }
+    }
+
+    pub fn key(&mut self, key: KeyEvent) -> Outcome {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return Outcome::Close,
+            KeyCode::Char('e') if ctrl => return Outcome::Close,
+            KeyCode::Enter => return Outcome::Send,
+            KeyCode::Char('u') if ctrl => *self = Self::default(),
+            KeyCode::Char(c) if c.is_ascii_hexdigit() => self.type_nibble(c),
+            KeyCode::Insert => {
+                self.data.insert(self.cursor, 0);
+                self.low = false;
+            }
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.data.remove(self.cursor);
+                self.low = false;
+            }
+            KeyCode::Delete if self.cursor < self.data.len() => {
+                self.data.remove(self.cursor);
+                self.low = false;
+            }
+            KeyCode::Left if self.low => self.low = false,
+            KeyCode::Left if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.low = true;
+            }
+            KeyCode::Right if !self.low && self.cursor < self.data.len() => self.low = true,
+            KeyCode::Right if self.cursor < self.data.len() => {
+                self.cursor += 1;
+                self.low = false;
+            }
+            KeyCode::Up => self.cursor = self.cursor.saturating_sub(ROW),
+            KeyCode::Down => self.cursor = (self.cursor + ROW).min(self.data.len()),
+            KeyCode::Home => self.cursor -= self.cursor % ROW,
+            KeyCode::End => {
+                self.cursor = (self.cursor - self.cursor % ROW + ROW - 1).min(self.data.len())
+            }
+            _ => return Outcome::Ignored,
+        }
+        Outcome::Edited
+    }
+
+    /// Insert pasted bytes at the cursor.
+    pub fn insert(&mut self, bytes: &[u8]) {
+        self.data
+            .splice(self.cursor..self.cursor, bytes.iter().copied());
+        self.cursor += bytes.len();
+        self.low = false;
+    }
+
+    fn type_nibble(&mut self, c: char) {
+        let nibble = c.to_digit(16).unwrap_or(0) as u8;
+        if self.cursor == self.data.len() {
+            self.data.push(0);
+        }
+        let byte = &mut self.data[self.cursor];
+        if self.low {
+            *byte = (*byte & 0xf0) | nibble;
+            self.cursor += 1;
+            self.low = false;
+        } else {
+            *byte = (nibble << 4) | (*byte & 0x0f);
+            self.low = true;
+        }
+    }
+
+    /// Draw the editor as a popup centered over `area`.
+    pub fn draw(&mut self, f: &mut Frame, area: Rect, theme: &Palette) {
+        let [popup] = Layout::horizontal([Constraint::Length(80)])
+            .flex(Flex::Center)
+            .areas(area);
+        let [popup] = Layout::vertical([Constraint::Percentage(60)])
+            .flex(Flex::Center)
+            .areas(popup);
+
+        let height = popup.height.saturating_sub(2).max(1) as usize;
+        let row = self.cursor / ROW;
+        if row < self.top {
+            self.top = row;
+        } else if row >= self.top + height {
+            self.top = row + 1 - height;
+        }
+
+        let rows = self.data.len() / ROW + 1;
+        let lines: Vec<Line> = (self.top..rows.min(self.top + height))
+            .map(|row| self.line(row, theme))
+            .collect();
+
+        let title = format!(
+            " Compose: {} bytes (Enter to send, Insert adds a byte, Ctrl+U clears, Esc to close) ",
+            self.data.len()
+        );
+        f.render_widget(Clear, popup);
+        f.render_widget(
+            Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title)),
+            popup,
+        );
+    }
+
+    /// One row: offset, 16 hex cells split in two groups of 8, and the ASCII column.
+    fn line(&self, row: usize, theme: &Palette) -> Line<'static> {
+        let start = row * ROW;
+        let mut spans = vec![Span::styled(format!("{:08x}  ", start), theme.hint)];
+        let mut ascii = vec![Span::raw(" |")];
+
+        for i in start..start + ROW {
+            if i == start + 8 {
+                spans.push(Span::raw(" "));
+            }
+            let at_cursor = i == self.cursor;
+            match self.data.get(i) {
+                Some(&byte) => {
+                    let hex = format!("{:02x}", byte);
+                    let (high, low) = hex.split_at(1);
+                    let mark = |on: bool, s: &str| match on {
+                        true => Span::raw(s.to_string()).reversed(),
+                        false => Span::raw(s.to_string()),
+                    };
+                    spans.push(mark(at_cursor && !self.low, high));
+                    spans.push(mark(at_cursor && self.low, low));
+                    let c = match byte.is_ascii_graphic() || byte == b' ' {
+                        true => byte as char,
+                        false => '.',
+                    };
+                    let c = Span::styled(c.to_string(), theme.mqtt);
+                    ascii.push(if at_cursor { c.underlined() } else { c });
+                }
+                None if at_cursor => spans.push(Span::raw("__").reversed()),
+                None => spans.push(Span::raw("  ")),
+            }
+            spans.push(Span::raw(" "));
+        }
+
+        ascii.push(Span::raw("|"));
+        spans.extend(ascii);
+        Line::from(spans)
+    }
+}
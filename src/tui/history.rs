@@ -0,0 +1,190 @@
+use crate::InputMode;
+use clap::ValueEnum;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+/// How many entries are loaded from the file at startup, and kept when it is rewritten.
+const MAX_ENTRIES: usize = 1000;
+
+/// Submitted input lines, kept across runs in `~/.local/share/udd/history` (or under
+/// `$XDG_DATA_HOME`). Each line of the file is `<mode>\t<input>`. The file is readable by its
+/// owner only, and lines carrying a `pass=` are kept for this run but never written to it.
+pub(super) struct History {
+    entries: Vec<(InputMode, String)>,
+    file: Option<(PathBuf, File)>,
+    /// Lines in the file, which is rewritten with the newest entries once this passes twice
+    /// `MAX_ENTRIES`.
+    lines: usize,
+}
+
+impl History {
+    /// A history that lives only for this run.
+    pub fn in_memory() -> Self {
+        Self {
+            entries: vec![],
+            file: None,
+            lines: 0,
+        }
+    }
+
+    /// Load the history file and keep it open for appending.
+    pub fn load() -> io::Result<Self> {
+        let path = path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no $HOME"))?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let mut entries = vec![];
+        if let Ok(file) = File::open(&path) {
+            for line in BufReader::new(file).lines() {
+                let line = line?;
+                let entry = match line.split_once('\t') {
+                    Some((mode, input)) => match InputMode::from_str(mode, true) {
+                        Ok(mode) => (mode, input.to_string()),
+                        Err(_) => (InputMode::Auto, line),
+                    },
+                    None => (InputMode::Auto, line),
+                };
+                entries.push(entry);
+            }
+        }
+        let lines = entries.len();
+        let excess = entries.len().saturating_sub(MAX_ENTRIES);
+        entries.drain(..excess);
+
+        let file = open(&path, false)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+        }
+        let mut history = Self {
+            entries,
+            file: Some((path, file)),
+            lines,
+        };
+        if excess > 0 {
+            history.rewrite()?;
+        }
+        Ok(history)
+    }
+
+    pub fn entries(&self) -> &[(InputMode, String)] {
+        &self.entries
+    }
+
+    /// The newest entry for `mode` before index `before` (or overall when `None`).
+    pub fn previous(&self, mode: InputMode, before: Option<usize>) -> Option<usize> {
+        let end = before.unwrap_or(self.entries.len());
+        self.entries[..end]
+            .iter()
+            .rposition(|(entry_mode, _)| *entry_mode == mode)
+    }
+
+    /// The oldest entry for `mode` after index `after`.
+    pub fn next(&self, mode: InputMode, after: usize) -> Option<usize> {
+        self.entries
+            .iter()
+            .enumerate()
+            .skip(after + 1)
+            .find(|(_, (entry_mode, _))| *entry_mode == mode)
+            .map(|(i, _)| i)
+    }
+
+    /// Record a submitted line. A repeat of the previous entry is not recorded again. If the
+    /// file can't be written it is dropped and the error returned; the history stays in memory.
+    pub fn push(&mut self, mode: InputMode, input: &str) -> io::Result<()> {
+        if input.contains('\n')
+            || self
+                .entries
+                .last()
+                .is_some_and(|(last_mode, last)| *last_mode == mode && last == input)
+        {
+            return Ok(());
+        }
+        self.entries.push((mode, input.to_string()));
+
+        let Some((_, file)) = &mut self.file else {
+            return Ok(());
+        };
+        if is_secret(input) {
+            return Ok(());
+        }
+        let mut result = write_entry(file, mode, input);
+        self.lines += 1;
+        if result.is_ok() && self.lines > 2 * MAX_ENTRIES {
+            result = self.rewrite();
+        }
+        if result.is_err() {
+            self.file = None;
+        }
+        result
+    }
+
+    /// Replace the file with its newest `MAX_ENTRIES` lines.
+    fn rewrite(&mut self) -> io::Result<()> {
+        let Some((path, _)) = &self.file else {
+            return Ok(());
+        };
+        let kept: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|(_, input)| !is_secret(input))
+            .collect();
+        let kept = &kept[kept.len().saturating_sub(MAX_ENTRIES)..];
+
+        let temp = path.with_extension("tmp");
+        let mut file = open(&temp, true)?;
+        for (mode, input) in kept {
+            write_entry(&mut file, *mode, input)?;
+        }
+        std::fs::rename(&temp, path)?;
+
+        let path = path.clone();
+        let file = open(&path, false)?;
+        self.file = Some((path, file));
+        self.lines = kept.len();
+        Ok(())
+    }
+}
+
+/// Open the history file for appending, or empty it with `truncate`, creating it owner-only.
+fn open(path: &Path, truncate: bool) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.create(true);
+    match truncate {
+        true => options.write(true).truncate(true),
+        false => options.append(true),
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)
+}
+
+fn write_entry(file: &mut File, mode: InputMode, input: &str) -> io::Result<()> {
+    let name = mode
+        .to_possible_value()
+        .map_or_else(|| "auto".into(), |v| v.get_name().to_string());
+    writeln!(file, "{}\t{}", name, input)
+}
+
+/// Whether `input` carries a password (`connect ... pass=X`), which is never written to disk.
+fn is_secret(input: &str) -> bool {
+    input
+        .split_whitespace()
+        .any(|word| word.starts_with("pass="))
+}
+
+fn path() -> Option<PathBuf> {
+    let data = std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share"))
+        })?;
+    Some(data.join("udd").join("history"))
+}
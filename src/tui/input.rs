@@ -0,0 +1,173 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The TUI's input line and the edit point within it. It holds several lines in multi-line
+/// mode or after a paste. `cursor` is a byte offset that
+/// always sits on a grapheme boundary, so an accented letter or emoji moves and deletes as one.
+#[derive(Default)]
+pub(super) struct InputLine {
+    text: String,
+    cursor: usize,
+}
+
+impl InputLine {
+    /// Apply an editing key. Returns false for keys that aren't editing keys.
+    pub fn edit(&mut self, key: KeyEvent) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('w') if ctrl => self.delete_word(),
+            KeyCode::Char('u') if ctrl => self.delete_to_start(),
+            KeyCode::Backspace => self.backspace(),
+            KeyCode::Delete => self.delete(),
+            KeyCode::Left => self.left(),
+            KeyCode::Right => self.right(),
+            KeyCode::Home => self.home(),
+            KeyCode::End => self.end(),
+            KeyCode::Char(c) => self.insert(c),
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// The text left of the cursor.
+    pub fn before_cursor(&self) -> &str {
+        &self.text[..self.cursor]
+    }
+
+    /// Replace the whole line, with the cursor at the end.
+    pub fn set(&mut self, text: String) {
+        self.cursor = text.len();
+        self.text = text;
+    }
+
+    pub fn take(&mut self) -> String {
+        self.cursor = 0;
+        std::mem::take(&mut self.text)
+    }
+
+    pub fn clear(&mut self) {
+        self.take();
+    }
+
+    fn insert(&mut self, c: char) {
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+
+    pub fn insert_str(&mut self, s: &str) {
+        self.text.insert_str(self.cursor, s);
+        self.cursor += s.len();
+    }
+
+    /// Replace the text from byte `start` up to the cursor, leaving the cursor after `with`.
+    pub fn replace_before_cursor(&mut self, start: usize, with: &str) {
+        self.text.replace_range(start..self.cursor, with);
+        self.cursor = start + with.len();
+    }
+
+    /// The grapheme before the cursor, if any.
+    fn previous_grapheme(&self) -> Option<&str> {
+        self.before_cursor().graphemes(true).next_back()
+    }
+
+    /// The grapheme after the cursor, if any.
+    fn next_grapheme(&self) -> Option<&str> {
+        self.text[self.cursor..].graphemes(true).next()
+    }
+
+    fn backspace(&mut self) {
+        if let Some(g) = self.previous_grapheme() {
+            let start = self.cursor - g.len();
+            self.text.replace_range(start..self.cursor, "");
+            self.cursor = start;
+        }
+    }
+
+    fn delete(&mut self) {
+        if let Some(g) = self.next_grapheme() {
+            let end = self.cursor + g.len();
+            self.text.replace_range(self.cursor..end, "");
+        }
+    }
+
+    fn left(&mut self) {
+        if let Some(g) = self.previous_grapheme() {
+            self.cursor -= g.len();
+        }
+    }
+
+    fn right(&mut self) {
+        if let Some(g) = self.next_grapheme() {
+            self.cursor += g.len();
+        }
+    }
+
+    /// Home and End stay on the cursor's line of multi-line input.
+    fn home(&mut self) {
+        self.cursor = self.before_cursor().rfind('\n').map_or(0, |i| i + 1);
+    }
+
+    fn end(&mut self) {
+        self.cursor += self.text[self.cursor..]
+            .find('\n')
+            .unwrap_or(self.text.len() - self.cursor);
+    }
+
+    /// Move to the same column of the line above. Returns false on the first line.
+    pub fn line_up(&mut self) -> bool {
+        let before = self.before_cursor();
+        let Some(newline) = before.rfind('\n') else {
+            return false;
+        };
+        let column = before[newline + 1..].graphemes(true).count();
+        let start = self.text[..newline].rfind('\n').map_or(0, |i| i + 1);
+        self.cursor = start + column_offset(&self.text[start..newline], column);
+        true
+    }
+
+    /// Move to the same column of the line below. Returns false on the last line.
+    pub fn line_down(&mut self) -> bool {
+        let Some(newline) = self.text[self.cursor..].find('\n') else {
+            return false;
+        };
+        let before = self.before_cursor();
+        let column = before[before.rfind('\n').map_or(0, |i| i + 1)..]
+            .graphemes(true)
+            .count();
+        let start = self.cursor + newline + 1;
+        let end = self.text[start..]
+            .find('\n')
+            .map_or(self.text.len(), |i| start + i);
+        self.cursor = start + column_offset(&self.text[start..end], column);
+        true
+    }
+
+    /// Ctrl+W: delete the word before the cursor, and the whitespace after it.
+    fn delete_word(&mut self) {
+        let before = self.before_cursor().trim_end();
+        let start = before.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        self.text.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+
+    /// Ctrl+U: delete everything before the cursor.
+    fn delete_to_start(&mut self) {
+        self.text.replace_range(..self.cursor, "");
+        self.cursor = 0;
+    }
+}
+
+/// The byte offset of grapheme `column` in `line`, or its end if it is shorter.
+fn column_offset(line: &str, column: usize) -> usize {
+    line.grapheme_indices(true)
+        .nth(column)
+        .map_or(line.len(), |(at, _)| at)
+}
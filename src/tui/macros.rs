@@ -0,0 +1,178 @@
+use crate::{InputMode, utils};
+use clap::ValueEnum;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// One recorded send: the pause before it, its input mode and the line as typed.
+#[derive(Clone)]
+pub(super) struct Step {
+    pub delay: Duration,
+    pub mode: InputMode,
+    pub input: String,
+}
+
+/// `+250ms mqtt connect dev1`, as stored in the config file.
+impl FromStr for Step {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let usage = || format!("expected `+<delay> <mode> <input>`, got `{}`", s);
+        let (delay, rest) = s.trim().split_once(' ').ok_or_else(usage)?;
+        let (mode, input) = rest.trim_start().split_once(' ').ok_or_else(usage)?;
+        Ok(Step {
+            delay: utils::parse_duration(delay.strip_prefix('+').ok_or_else(usage)?)?,
+            mode: InputMode::from_str(mode, true)?,
+            input: input.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mode = self
+            .mode
+            .to_possible_value()
+            .map_or_else(|| "auto".into(), |v| v.get_name().to_string());
+        write!(f, "+{}ms {} {}", self.delay.as_millis(), mode, self.input)
+    }
+}
+
+struct Recording {
+    name: String,
+    steps: Vec<Step>,
+    last: Option<Instant>,
+}
+
+struct Playback {
+    steps: Vec<Step>,
+    next: usize,
+    due: Instant,
+}
+
+/// Named send sequences: recorded from what is typed, replayed with their original pacing.
+pub(super) struct Macros {
+    macros: Vec<(String, Vec<Step>)>,
+    recording: Option<Recording>,
+    playing: Option<Playback>,
+    /// The macro F5 replays: the last one recorded or run.
+    pub last: Option<String>,
+}
+
+impl Macros {
+    /// Parse the config file's macros, returning the errors for those that don't parse.
+    pub fn load(config: &[(String, Vec<String>)]) -> (Self, Vec<String>) {
+        let mut macros = vec![];
+        let mut errors = vec![];
+        for (name, lines) in config {
+            match lines.iter().map(|l| l.parse()).collect() {
+                Ok(steps) => macros.push((name.clone(), steps)),
+                Err(err) => errors.push(format!("macro {}: {}", name, err)),
+            }
+        }
+        let macros = Self {
+            macros,
+            recording: None,
+            playing: None,
+            last: None,
+        };
+        (macros, errors)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.macros
+            .iter()
+            .map(|(name, steps)| (name.as_str(), steps.len()))
+    }
+
+    pub fn recording(&self) -> Option<&str> {
+        self.recording.as_ref().map(|r| r.name.as_str())
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing.is_some()
+    }
+
+    pub fn start_recording(&mut self, name: &str) {
+        self.recording = Some(Recording {
+            name: name.to_string(),
+            steps: vec![],
+            last: None,
+        });
+    }
+
+    /// Note a line sent while recording. Multi-line input isn't recorded: each step is one line
+    /// of the config file.
+    pub fn record(&mut self, mode: InputMode, input: &str, now: Instant) {
+        if input.contains('\n') {
+            return;
+        }
+        if let Some(recording) = &mut self.recording {
+            let delay = recording.last.map_or(Duration::ZERO, |last| now - last);
+            recording.last = Some(now);
+            recording.steps.push(Step {
+                delay,
+                mode,
+                input: input.to_string(),
+            });
+        }
+    }
+
+    /// Finish recording, returning the new macro's name and steps.
+    pub fn stop_recording(&mut self) -> Option<(String, Vec<Step>)> {
+        let recording = self.recording.take()?;
+        if recording.steps.is_empty() {
+            return Some((recording.name, vec![]));
+        }
+        self.macros.retain(|(name, _)| *name != recording.name);
+        self.macros
+            .push((recording.name.clone(), recording.steps.clone()));
+        self.last = Some(recording.name.clone());
+        Some((recording.name, recording.steps))
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.macros.len();
+        self.macros.retain(|(n, _)| n != name);
+        self.macros.len() != before
+    }
+
+    /// Start replaying `name`; its first step is due at once.
+    pub fn play(&mut self, name: &str, now: Instant) -> bool {
+        let Some((_, steps)) = self
+            .macros
+            .iter()
+            .find(|(n, steps)| n == name && !steps.is_empty())
+        else {
+            return false;
+        };
+        self.playing = Some(Playback {
+            steps: steps.clone(),
+            next: 0,
+            due: now,
+        });
+        self.last = Some(name.to_string());
+        true
+    }
+
+    pub fn stop_playing(&mut self) -> bool {
+        self.playing.take().is_some()
+    }
+
+    /// When the next step should be sent, if a macro is playing.
+    pub fn next_due(&self) -> Option<Instant> {
+        self.playing.as_ref().map(|p| p.due)
+    }
+
+    /// The step due at `now`, if any. Playback ends after the last step.
+    pub fn due(&mut self, now: Instant) -> Option<Step> {
+        let playback = self.playing.as_mut().filter(|p| p.due <= now)?;
+        let step = playback.steps[playback.next].clone();
+        playback.next += 1;
+        match playback.steps.get(playback.next) {
+            Some(next) => playback.due = now + next.delay,
+            None => self.playing = None,
+        }
+        Some(step)
+    }
+}
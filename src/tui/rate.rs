@@ -0,0 +1,116 @@
+use std::collections::VecDeque;
+use std::time::Instant;
+
+/// Per-second event counts over a sliding window, oldest first.
+pub struct RateHistory {
+    start: Instant,
+    buckets: VecDeque<u64>,
+    /// Second (relative to `start`) that the last bucket represents.
+    current: u64,
+    window: usize,
+}
+
+impl RateHistory {
+    pub fn new(window: usize) -> Self {
+        Self {
+            start: Instant::now(),
+            buckets: VecDeque::from(vec![0; window]),
+            current: 0,
+            window,
+        }
+    }
+
+    pub fn record(&mut self, now: Instant, amount: u64) {
+        self.roll(now);
+        if let Some(last) = self.buckets.back_mut() {
+            *last += amount;
+        }
+    }
+
+    /// Advance the window to `now`, pushing an empty bucket for every second that passed.
+    pub fn roll(&mut self, now: Instant) {
+        let second = now.saturating_duration_since(self.start).as_secs();
+        let elapsed = second.saturating_sub(self.current);
+        for _ in 0..elapsed.min(self.window as u64) {
+            self.buckets.pop_front();
+            self.buckets.push_back(0);
+        }
+        self.current = self.current.max(second);
+    }
+
+    pub fn data(&self) -> Vec<u64> {
+        self.buckets.iter().copied().collect()
+    }
+}
+
+/// Packet and byte rates in both directions, for the sparklines.
+pub struct Traffic {
+    sent: RateHistory,
+    sent_bytes: RateHistory,
+    received: RateHistory,
+    received_bytes: RateHistory,
+}
+
+impl Traffic {
+    pub fn new(window: usize) -> Self {
+        Self {
+            sent: RateHistory::new(window),
+            sent_bytes: RateHistory::new(window),
+            received: RateHistory::new(window),
+            received_bytes: RateHistory::new(window),
+        }
+    }
+
+    pub fn sent(&mut self, now: Instant, bytes: usize) {
+        self.sent.record(now, 1);
+        self.sent_bytes.record(now, bytes as u64);
+    }
+
+    pub fn received(&mut self, now: Instant, bytes: usize) {
+        self.received.record(now, 1);
+        self.received_bytes.record(now, bytes as u64);
+    }
+
+    /// The `(sent, received)` series in packets or bytes per second, rolled forward to `now`.
+    pub fn series(&mut self, now: Instant, bytes: bool) -> (Vec<u64>, Vec<u64>) {
+        let (sent, received) = match bytes {
+            true => (&mut self.sent_bytes, &mut self.received_bytes),
+            false => (&mut self.sent, &mut self.received),
+        };
+        sent.roll(now);
+        received.roll(now);
+        (sent.data(), received.data())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn buckets_roll_over_at_second_boundaries() {
+        let mut rate = RateHistory::new(3);
+        let start = rate.start;
+        let at = |ms| start + Duration::from_millis(ms);
+
+        rate.record(at(100), 1);
+        rate.record(at(999), 1);
+        assert_eq!(rate.data(), [0, 0, 2]);
+
+        rate.record(at(1_000), 5);
+        assert_eq!(rate.data(), [0, 2, 5]);
+
+        // Idle seconds leave empty buckets behind.
+        rate.record(at(3_200), 1);
+        assert_eq!(rate.data(), [5, 0, 1]);
+
+        // A gap longer than the window clears it.
+        rate.roll(at(60_000));
+        assert_eq!(rate.data(), [0, 0, 0]);
+
+        // A late timestamp counts towards the current second.
+        rate.record(at(59_500), 1);
+        assert_eq!(rate.data(), [0, 0, 1]);
+    }
+}
@@ -0,0 +1,104 @@
+use super::input::InputLine;
+
+/// An incremental log search opened with `/`. Matches are tracked as log indices, in order, so
+/// `n`/`N` and the filtered view don't rescan the whole log.
+pub(super) struct Search {
+    pub query: InputLine,
+    pattern: Result<Pattern, String>,
+    /// The prompt has focus; cleared by Enter so `n`/`N` can step through the matches.
+    pub editing: bool,
+    /// Show only matching entries instead of highlighting them.
+    pub filter: bool,
+    matches: Vec<usize>,
+}
+
+enum Pattern {
+    Substring(String),
+    Regex(regex_lite::Regex),
+}
+
+impl Search {
+    pub fn new() -> Self {
+        Self {
+            query: InputLine::default(),
+            pattern: Ok(Pattern::Substring(String::new())),
+            editing: true,
+            filter: false,
+            matches: vec![],
+        }
+    }
+
+    /// Recompile the query and rescan `log`. A query starting with `re:` is a regex, anything
+    /// else a plain substring.
+    pub fn update<'a>(&mut self, log: impl Iterator<Item = &'a str>) {
+        let query = self.query.as_str();
+        self.pattern = match query.strip_prefix("re:") {
+            Some(re) => regex_lite::Regex::new(re)
+                .map(Pattern::Regex)
+                .map_err(|err| err.to_string()),
+            None => Ok(Pattern::Substring(query.to_string())),
+        };
+        self.matches = log
+            .enumerate()
+            .filter(|(_, line)| self.is_match(line))
+            .map(|(i, _)| i)
+            .collect();
+    }
+
+    /// Check a newly logged entry.
+    pub fn push(&mut self, index: usize, line: &str) {
+        if self.is_match(line) {
+            self.matches.push(index);
+        }
+    }
+
+    /// Forget the `count` oldest entries, dropped from the front of the log.
+    pub fn evict(&mut self, count: usize) {
+        let gone = self.matches.partition_point(|&m| m < count);
+        self.matches.drain(..gone);
+        self.matches.iter_mut().for_each(|m| *m -= count);
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.query.is_empty()
+    }
+
+    fn is_match(&self, line: &str) -> bool {
+        match &self.pattern {
+            _ if self.query.is_empty() => false,
+            Ok(Pattern::Substring(s)) => line.contains(s.as_str()),
+            Ok(Pattern::Regex(re)) => re.is_match(line),
+            Err(_) => false,
+        }
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.pattern.as_ref().err().map(String::as_str)
+    }
+
+    pub fn matches(&self) -> &[usize] {
+        &self.matches
+    }
+
+    /// Whether log entry `index` matches.
+    pub fn contains(&self, index: usize) -> bool {
+        self.matches.binary_search(&index).is_ok()
+    }
+
+    /// The first match after `index`, wrapping around to the first.
+    pub fn next(&self, index: Option<usize>) -> Option<usize> {
+        let from = index.map_or(0, |i| self.matches.partition_point(|&m| m <= i));
+        self.matches.get(from).or(self.matches.first()).copied()
+    }
+
+    /// The last match before `index`, wrapping around to the last.
+    pub fn previous(&self, index: Option<usize>) -> Option<usize> {
+        let before = index.map_or(self.matches.len(), |i| {
+            self.matches.partition_point(|&m| m < i)
+        });
+        match before {
+            0 => self.matches.last().copied(),
+            n => Some(self.matches[n - 1]),
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Position, Rect};
+use ratatui::style::Modifier;
+use unicode_width::UnicodeWidthStr;
+
+/// A click-drag over the log, in screen cells. The text under it is read back from what was
+/// drawn, so it copies exactly what is on screen: timestamps, wrapped rows and split columns.
+pub(super) struct Selection {
+    anchor: Position,
+    head: Position,
+    /// The selected text as of the last draw.
+    pub text: String,
+}
+
+impl Selection {
+    pub fn new(at: Position) -> Self {
+        Self {
+            anchor: at,
+            head: at,
+            text: String::new(),
+        }
+    }
+
+    pub fn extend(&mut self, to: Position) {
+        self.head = to;
+    }
+
+    /// Nothing was dragged: a plain click.
+    pub fn is_click(&self) -> bool {
+        self.anchor == self.head
+    }
+
+    pub fn anchor(&self) -> Position {
+        self.anchor
+    }
+
+    /// The selected range in reading order, clamped to `area`.
+    fn bounds(&self, area: Rect) -> (Position, Position) {
+        let clamp = |p: Position| Position {
+            x: p.x.clamp(area.left(), area.right().saturating_sub(1)),
+            y: p.y.clamp(area.top(), area.bottom().saturating_sub(1)),
+        };
+        let (a, b) = (clamp(self.anchor), clamp(self.head));
+        match (a.y, a.x) <= (b.y, b.x) {
+            true => (a, b),
+            false => (b, a),
+        }
+    }
+
+    /// Reverse the selected cells of `area` in `buf` and remember the text under them.
+    pub fn draw(&mut self, buf: &mut Buffer, area: Rect) {
+        if area.is_empty() {
+            return;
+        }
+        let (start, end) = self.bounds(area);
+        let mut lines = vec![];
+        for y in start.y..=end.y {
+            let from = if y == start.y { start.x } else { area.left() };
+            let to = if y == end.y { end.x } else { area.right() - 1 };
+            let mut line = String::new();
+            let mut skip = 0;
+            for x in from..=to {
+                let cell = &mut buf[(x, y)];
+                cell.modifier.insert(Modifier::REVERSED);
+                // A wide character's trailing cells are blanks that aren't part of the text.
+                if skip > 0 {
+                    skip -= 1;
+                    continue;
+                }
+                line.push_str(cell.symbol());
+                skip = cell.symbol().width().saturating_sub(1);
+            }
+            lines.push(line.trim_end().to_string());
+        }
+        self.text = lines.join("\n");
+    }
+}
@@ -0,0 +1,182 @@
+use super::format::suback_label;
+use super::theme::Palette;
+use crate::utils;
+use mqtt::{ConnectReturnCode, Packet, QoS, UdpFrame};
+use ratatui::prelude::{Line, Span};
+use std::time::SystemTime;
+
+/// Logical MQTT state pieced together from the frames the TUI sends and receives.
+pub(super) struct MqttSession {
+    connection: Connection,
+    subscriptions: Vec<Subscription>,
+    /// Subscribes awaiting a SubAck: msg_id and the topics, in filter order.
+    pending: Vec<(u16, Vec<String>)>,
+    /// QoS 1/2 publishes awaiting a PubAck: msg_id, topic and when they were sent.
+    unacked: Vec<(u16, String, SystemTime)>,
+    keep_alive: Option<u16>,
+    last_ping: Option<SystemTime>,
+    last_pong: Option<SystemTime>,
+}
+
+enum Connection {
+    Disconnected,
+    /// Connect sent, no ConnAck yet.
+    Connecting(String),
+    Connected {
+        client_id: String,
+        session_present: bool,
+    },
+    Refused(ConnectReturnCode),
+}
+
+pub(super) struct Subscription {
+    pub topic: String,
+    pub qos: QoS,
+    /// The SubAck's verdict, once it arrives.
+    ack: Option<&'static str>,
+}
+
+impl MqttSession {
+    pub fn new() -> Self {
+        Self {
+            connection: Connection::Disconnected,
+            subscriptions: vec![],
+            pending: vec![],
+            unacked: vec![],
+            keep_alive: None,
+            last_ping: None,
+            last_pong: None,
+        }
+    }
+
+    pub fn sent(&mut self, data: &[u8], now: SystemTime) {
+        let Ok(frame) = UdpFrame::decode(data) else {
+            return;
+        };
+        match frame.packet {
+            Packet::Connect(c) => {
+                *self = Self::new();
+                self.keep_alive = Some(c.keep_alive);
+                self.connection = Connection::Connecting(c.client_id);
+            }
+            Packet::Subscribe(s) => {
+                let mut topics = vec![];
+                for filter in s.filters {
+                    self.subscriptions.retain(|sub| sub.topic != filter.topic);
+                    topics.push(filter.topic.clone());
+                    self.subscriptions.push(Subscription {
+                        topic: filter.topic,
+                        qos: filter.qos,
+                        ack: None,
+                    });
+                }
+                self.pending.push((frame.msg_id, topics));
+            }
+            Packet::Publish(p) if !matches!(p.qos, QoS::AtMostOnce) => {
+                self.unacked.push((frame.msg_id, p.topic, now));
+            }
+            Packet::Ping(_) => self.last_ping = Some(now),
+            Packet::Disconnect(_) => self.connection = Connection::Disconnected,
+            _ => {}
+        }
+    }
+
+    pub fn received(&mut self, data: &[u8], now: SystemTime) {
+        let Ok(frame) = UdpFrame::decode(data) else {
+            return;
+        };
+        match frame.packet {
+            Packet::ConnAck(ack) => {
+                let client_id = match &self.connection {
+                    Connection::Connecting(id) => id.clone(),
+                    _ => "?".into(),
+                };
+                self.connection = match ack.return_code {
+                    ConnectReturnCode::Accepted => Connection::Connected {
+                        client_id,
+                        session_present: ack.session_present,
+                    },
+                    code => Connection::Refused(code),
+                };
+            }
+            Packet::SubAck(ack) => {
+                let Some(at) = self.pending.iter().position(|(id, _)| *id == frame.msg_id) else {
+                    return;
+                };
+                let (_, topics) = self.pending.remove(at);
+                for (topic, code) in topics.iter().zip(&ack.return_codes) {
+                    if let Some(sub) = self.subscriptions.iter_mut().find(|s| s.topic == *topic) {
+                        sub.ack = Some(suback_label(code));
+                    }
+                }
+            }
+            Packet::PubAck(_) => self.unacked.retain(|(id, ..)| *id != frame.msg_id),
+            Packet::Pong(_) => self.last_pong = Some(now),
+            _ => {}
+        }
+    }
+
+    /// The panel's contents.
+    pub fn lines(&self, theme: &Palette) -> Vec<Line<'static>> {
+        let (state, style) = match &self.connection {
+            Connection::Disconnected => ("disconnected".to_string(), theme.hint),
+            Connection::Connecting(id) => {
+                (format!("{}: CONNECT sent, no CONNACK", id), theme.warning)
+            }
+            Connection::Connected {
+                client_id,
+                session_present,
+            } => (
+                format!(
+                    "{}: connected (session present: {})",
+                    client_id, session_present
+                ),
+                theme.received,
+            ),
+            Connection::Refused(code) => (format!("refused: {:?}", code), theme.error),
+        };
+        let mut lines = vec![Line::styled(state, style)];
+
+        let stamp = |time: Option<SystemTime>| time.map_or_else(|| "-".into(), utils::timestamp);
+        if let Some(keep_alive) = self.keep_alive {
+            lines.push(Line::raw(format!("keep-alive {}s", keep_alive)));
+        }
+        lines.push(Line::raw(format!(
+            "ping {}  pong {}",
+            stamp(self.last_ping),
+            stamp(self.last_pong)
+        )));
+
+        lines.push(Line::default());
+        lines.push(Line::styled(
+            format!("Subscriptions ({})", self.subscriptions.len()),
+            theme.heading,
+        ));
+        for sub in &self.subscriptions {
+            let (ack, style) = match sub.ack {
+                None => ("pending", theme.warning),
+                Some("FAILURE") => ("FAILURE", theme.error),
+                Some(granted) => (granted, theme.received),
+            };
+            lines.push(Line::from(vec![
+                Span::raw(format!("  {} {:?} ", sub.topic, sub.qos)),
+                Span::styled(ack, style),
+            ]));
+        }
+
+        lines.push(Line::default());
+        lines.push(Line::styled(
+            format!("Unacked publishes ({})", self.unacked.len()),
+            theme.heading,
+        ));
+        for (id, topic, at) in &self.unacked {
+            lines.push(Line::raw(format!(
+                "  #{} {} at {}",
+                id,
+                topic,
+                utils::timestamp(*at)
+            )));
+        }
+        lines
+    }
+}
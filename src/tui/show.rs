@@ -0,0 +1,98 @@
+use super::theme::Palette;
+use super::{LogEntry, Side, format};
+
+/// What `:show` keeps in view.
+enum Criterion {
+    Sent,
+    Received,
+    /// Entries drawn as errors: failed sends and decodes, refused SUBACKs.
+    Errors,
+    /// MQTT frames of one packet type, by its name as `format::packet_name` gives it.
+    Packet(&'static str),
+}
+
+/// A `:show` view over the log: only the entries meeting every criterion are listed. Like a
+/// search, matches are tracked as log indices so new entries are checked as they arrive.
+pub(super) struct Show {
+    words: String,
+    criteria: Vec<Criterion>,
+    matches: Vec<usize>,
+}
+
+impl Show {
+    /// `sent`, `received`, `errors` or an MQTT packet type (`publish`, `suback`, ...), one or more.
+    pub fn parse(words: &str) -> Result<Self, String> {
+        let criteria = words
+            .split_whitespace()
+            .map(|word| match word.to_lowercase().as_str() {
+                "sent" => Ok(Criterion::Sent),
+                "received" | "recv" => Ok(Criterion::Received),
+                "errors" | "error" => Ok(Criterion::Errors),
+                "connect" => Ok(Criterion::Packet("CONNECT")),
+                "connack" => Ok(Criterion::Packet("CONNACK")),
+                "publish" | "pub" => Ok(Criterion::Packet("PUBLISH")),
+                "puback" => Ok(Criterion::Packet("PUBACK")),
+                "subscribe" | "sub" => Ok(Criterion::Packet("SUBSCRIBE")),
+                "suback" => Ok(Criterion::Packet("SUBACK")),
+                "ping" | "pingreq" => Ok(Criterion::Packet("PINGREQ")),
+                "pong" | "pingresp" => Ok(Criterion::Packet("PINGRESP")),
+                "disconnect" => Ok(Criterion::Packet("DISCONNECT")),
+                _ => Err(format!("can't show `{}`", word)),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            words: words.split_whitespace().collect::<Vec<_>>().join(" "),
+            criteria,
+            matches: vec![],
+        })
+    }
+
+    /// What is shown, as typed.
+    pub fn words(&self) -> &str {
+        &self.words
+    }
+
+    fn is_match(&self, entry: &LogEntry, theme: &Palette) -> bool {
+        self.criteria.iter().all(|criterion| match criterion {
+            Criterion::Sent => entry.side == Some(Side::Sent),
+            Criterion::Received => entry.side == Some(Side::Received),
+            Criterion::Errors => {
+                [theme.error, theme.error_detail, theme.refused].contains(&entry.style)
+            }
+            Criterion::Packet(name) => {
+                entry
+                    .payload
+                    .as_ref()
+                    .and_then(|(_, data)| format::packet_name(data))
+                    == Some(*name)
+            }
+        })
+    }
+
+    /// Rescan the whole log.
+    pub fn update<'a>(&mut self, log: impl Iterator<Item = &'a LogEntry>, theme: &Palette) {
+        self.matches = log
+            .enumerate()
+            .filter(|(_, entry)| self.is_match(entry, theme))
+            .map(|(i, _)| i)
+            .collect();
+    }
+
+    /// Check a newly logged entry.
+    pub fn push(&mut self, index: usize, entry: &LogEntry, theme: &Palette) {
+        if self.is_match(entry, theme) {
+            self.matches.push(index);
+        }
+    }
+
+    /// Forget the `count` oldest entries, dropped from the front of the log.
+    pub fn evict(&mut self, count: usize) {
+        let gone = self.matches.partition_point(|&m| m < count);
+        self.matches.drain(..gone);
+        self.matches.iter_mut().for_each(|m| *m -= count);
+    }
+
+    pub fn matches(&self) -> &[usize] {
+        &self.matches
+    }
+}
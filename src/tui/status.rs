@@ -0,0 +1,62 @@
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+/// Session totals for the status bar, counted as network events are drained.
+pub struct Counters {
+    start: Instant,
+    pub sent: u64,
+    pub sent_bytes: u64,
+    pub received: u64,
+    pub received_bytes: u64,
+    pub errors: u64,
+}
+
+impl Counters {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            sent: 0,
+            sent_bytes: 0,
+            received: 0,
+            received_bytes: 0,
+            errors: 0,
+        }
+    }
+
+    pub fn sent(&mut self, bytes: usize) {
+        self.sent += 1;
+        self.sent_bytes += bytes as u64;
+    }
+
+    pub fn received(&mut self, bytes: usize) {
+        self.received += 1;
+        self.received_bytes += bytes as u64;
+    }
+
+    /// The status bar text, e.g. `tx 12 (1.2 KiB) │ rx 40 (9.8 KiB) │ errors 0 │ ...`.
+    pub fn render(&self, local: Option<SocketAddr>) -> String {
+        format!(
+            " tx {} ({}) │ rx {} ({}) │ errors {} │ bound {} │ {}",
+            self.sent,
+            bytes(self.sent_bytes),
+            self.received,
+            bytes(self.received_bytes),
+            self.errors,
+            local.map_or_else(|| "-".into(), |addr| addr.to_string()),
+            elapsed(self.start.elapsed()),
+        )
+    }
+}
+
+fn bytes(n: u64) -> String {
+    match n {
+        0..1024 => format!("{} B", n),
+        1024..1_048_576 => format!("{:.1} KiB", n as f64 / 1024.0),
+        _ => format!("{:.1} MiB", n as f64 / 1_048_576.0),
+    }
+}
+
+fn elapsed(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
@@ -0,0 +1,165 @@
+use crate::Theme;
+use ratatui::prelude::{Color, Style, Stylize};
+
+/// Every style the TUI draws with, resolved from a [`Theme`].
+pub(super) struct Palette {
+    pub sent: Style,
+    pub received: Style,
+    /// A SUBACK that refused a filter, sent or received.
+    pub refused: Style,
+    pub error: Style,
+    /// Hexdump lines under a datagram that failed to decode.
+    pub error_detail: Style,
+    pub warning: Style,
+    /// Replies to `:` commands and other status lines.
+    pub info: Style,
+    pub hint: Style,
+    pub search_match: Style,
+    pub mode_auto: Style,
+    pub mode_text: Style,
+    pub mode_hex: Style,
+    pub mode_mqtt: Style,
+    pub mode_base64: Style,
+    pub target: Style,
+    pub broadcast: Style,
+    pub banner: Style,
+    pub rate: Style,
+    pub heading: Style,
+    /// Decoded MQTT fields in the inspector.
+    pub mqtt: Style,
+    /// PUBLISH entries, picked by a hash of their topic. Empty for no topic colors.
+    pub topics: Vec<Style>,
+}
+
+impl Palette {
+    pub fn new(theme: Theme) -> Self {
+        let fg = |color| Style::default().fg(color);
+        match theme {
+            Theme::Dark => Self {
+                sent: fg(Color::Cyan),
+                received: fg(Color::Green),
+                refused: fg(Color::LightRed),
+                error: fg(Color::Red),
+                error_detail: fg(Color::Red).dim(),
+                warning: fg(Color::Yellow),
+                info: fg(Color::Gray),
+                hint: Style::default().dim(),
+                search_match: Style::default().bg(Color::DarkGray),
+                mode_auto: fg(Color::Blue).bold(),
+                mode_text: fg(Color::Green).bold(),
+                mode_hex: fg(Color::Magenta).bold(),
+                mode_mqtt: fg(Color::Yellow).bold(),
+                mode_base64: fg(Color::Cyan).bold(),
+                target: fg(Color::Cyan),
+                broadcast: fg(Color::LightRed).bold(),
+                banner: fg(Color::White).bg(Color::Red).bold(),
+                rate: fg(Color::Green),
+                heading: fg(Color::Yellow).bold(),
+                mqtt: fg(Color::Yellow),
+                topics: [
+                    Color::LightBlue,
+                    Color::LightMagenta,
+                    Color::LightYellow,
+                    Color::LightCyan,
+                    Color::Rgb(0xff, 0xa0, 0x50),
+                    Color::Rgb(0xb0, 0xe0, 0x60),
+                ]
+                .map(fg)
+                .to_vec(),
+            },
+            // Named colors like yellow and gray wash out on white, so use darker RGB shades.
+            Theme::Light => {
+                let amber = Color::Rgb(0x9a, 0x60, 0x00);
+                Self {
+                    sent: fg(Color::Blue),
+                    received: fg(Color::Rgb(0x00, 0x7a, 0x1f)),
+                    refused: fg(Color::Red).bold(),
+                    error: fg(Color::Red),
+                    error_detail: fg(Color::Rgb(0x9a, 0x30, 0x30)),
+                    warning: fg(amber),
+                    info: fg(Color::Rgb(0x55, 0x55, 0x55)),
+                    hint: fg(Color::Rgb(0x70, 0x70, 0x70)),
+                    search_match: Style::default().bg(Color::Rgb(0xff, 0xf0, 0xa0)),
+                    mode_auto: fg(Color::Blue).bold(),
+                    mode_text: fg(Color::Rgb(0x00, 0x7a, 0x1f)).bold(),
+                    mode_hex: fg(Color::Magenta).bold(),
+                    mode_mqtt: fg(amber).bold(),
+                    mode_base64: fg(Color::Rgb(0x00, 0x70, 0x80)).bold(),
+                    target: fg(Color::Blue),
+                    broadcast: fg(Color::Red).bold(),
+                    banner: fg(Color::White).bg(Color::Red).bold(),
+                    rate: fg(Color::Rgb(0x00, 0x7a, 0x1f)),
+                    heading: fg(amber).bold(),
+                    mqtt: fg(amber),
+                    topics: [
+                        Color::Rgb(0x1f, 0x4e, 0xb4),
+                        Color::Rgb(0x8e, 0x24, 0xaa),
+                        Color::Rgb(0x00, 0x70, 0x80),
+                        Color::Rgb(0xb3, 0x4a, 0x00),
+                        Color::Rgb(0x5a, 0x6e, 0x00),
+                        Color::Rgb(0xa0, 0x1c, 0x5a),
+                    ]
+                    .map(fg)
+                    .to_vec(),
+                }
+            }
+            Theme::HighContrast => Self {
+                sent: fg(Color::LightCyan).bold(),
+                received: fg(Color::LightGreen).bold(),
+                refused: fg(Color::Black).bg(Color::LightRed).bold(),
+                error: fg(Color::LightRed).bold(),
+                error_detail: fg(Color::LightRed),
+                warning: fg(Color::LightYellow).bold(),
+                info: fg(Color::White),
+                hint: fg(Color::White),
+                search_match: fg(Color::Black).bg(Color::LightYellow),
+                mode_auto: fg(Color::Black).bg(Color::LightBlue).bold(),
+                mode_text: fg(Color::Black).bg(Color::LightGreen).bold(),
+                mode_hex: fg(Color::Black).bg(Color::LightMagenta).bold(),
+                mode_mqtt: fg(Color::Black).bg(Color::LightYellow).bold(),
+                mode_base64: fg(Color::Black).bg(Color::LightCyan).bold(),
+                target: fg(Color::LightCyan).bold(),
+                broadcast: fg(Color::Black).bg(Color::LightRed).bold(),
+                banner: fg(Color::White).bg(Color::Red).bold(),
+                rate: fg(Color::LightGreen),
+                heading: fg(Color::LightYellow).bold().underlined(),
+                mqtt: fg(Color::LightYellow),
+                topics: [
+                    Color::LightBlue,
+                    Color::LightMagenta,
+                    Color::LightYellow,
+                    Color::White,
+                ]
+                .map(|color| fg(color).bold())
+                .to_vec(),
+            },
+            // No colors at all; what matters is carried by modifiers.
+            Theme::Monochrome => {
+                let plain = Style::default();
+                Self {
+                    sent: plain,
+                    received: plain,
+                    refused: plain.bold(),
+                    error: plain.bold(),
+                    error_detail: plain.dim(),
+                    warning: plain.bold(),
+                    info: plain,
+                    hint: plain.dim(),
+                    search_match: plain.bold().italic(),
+                    mode_auto: plain.bold(),
+                    mode_text: plain.bold(),
+                    mode_hex: plain.bold(),
+                    mode_mqtt: plain.bold(),
+                    mode_base64: plain.bold(),
+                    target: plain,
+                    broadcast: plain.bold(),
+                    banner: plain.reversed().bold(),
+                    rate: plain,
+                    heading: plain.bold().underlined(),
+                    mqtt: plain,
+                    topics: vec![],
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use crate::InputMode;
+use std::time::{Duration, Instant};
+
+/// A line re-sent on an interval, registered with `:every`.
+pub(super) struct Timer {
+    pub id: u32,
+    every: Duration,
+    pub mode: InputMode,
+    pub input: String,
+    next: Instant,
+    paused: bool,
+    sent: u64,
+}
+
+impl Timer {
+    /// `#1 every 2s [MQTT] pub heartbeat ok (12 sent, next in 1.2s)`.
+    pub fn describe(&self, now: Instant) -> String {
+        let state = match self.paused {
+            true => "paused".to_string(),
+            false => format!(
+                "next in {:.1}s",
+                self.next.saturating_duration_since(now).as_secs_f64()
+            ),
+        };
+        format!(
+            "#{} every {:?} [{}] {} ({} sent, {})",
+            self.id,
+            self.every,
+            self.mode.short_label(),
+            self.input,
+            self.sent,
+            state
+        )
+    }
+}
+
+#[derive(Default)]
+pub(super) struct Timers {
+    timers: Vec<Timer>,
+    next_id: u32,
+}
+
+impl Timers {
+    pub fn list(&self) -> &[Timer] {
+        &self.timers
+    }
+
+    /// Register a timer that first fires one interval from now. Returns its id.
+    pub fn add(&mut self, every: Duration, mode: InputMode, input: String, now: Instant) -> u32 {
+        self.next_id += 1;
+        self.timers.push(Timer {
+            id: self.next_id,
+            every,
+            mode,
+            input,
+            next: now + every,
+            paused: false,
+            sent: 0,
+        });
+        self.next_id
+    }
+
+    /// Pause or resume timer `id`; a resumed timer fires one interval later.
+    pub fn set_paused(&mut self, id: u32, paused: bool, now: Instant) -> bool {
+        let Some(timer) = self.timers.iter_mut().find(|t| t.id == id) else {
+            return false;
+        };
+        timer.paused = paused;
+        timer.next = now + timer.every;
+        true
+    }
+
+    pub fn cancel(&mut self, id: u32) -> bool {
+        let before = self.timers.len();
+        self.timers.retain(|t| t.id != id);
+        self.timers.len() != before
+    }
+
+    pub fn cancel_all(&mut self) -> usize {
+        std::mem::take(&mut self.timers).len()
+    }
+
+    pub fn next_due(&self) -> Option<Instant> {
+        self.timers
+            .iter()
+            .filter(|t| !t.paused)
+            .map(|t| t.next)
+            .min()
+    }
+
+    /// The sends due at `now`. A timer that fell behind fires once, not once per missed interval.
+    pub fn due(&mut self, now: Instant) -> Vec<(InputMode, String)> {
+        let mut due = vec![];
+        for timer in self
+            .timers
+            .iter_mut()
+            .filter(|t| !t.paused && t.next <= now)
+        {
+            timer.sent += 1;
+            timer.next += timer.every;
+            if timer.next <= now {
+                timer.next = now + timer.every;
+            }
+            due.push((timer.mode, timer.input.clone()));
+        }
+        due
+    }
+}
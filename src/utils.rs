@@ -9,6 +9,65 @@ pub fn parse_hex(s: &str) -> Result<Vec<u8>, String> {
         .collect()
 }
 
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Standard base64 with padding.
+pub fn base64(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64_ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Decode standard or URL-safe base64. Whitespace is ignored and padding is optional.
+pub fn parse_base64(s: &str) -> Result<Vec<u8>, String> {
+    let digits: Vec<u8> = s.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    let digits = match digits.iter().position(|&b| b == b'=') {
+        Some(pad) if digits[pad..].iter().all(|&b| b == b'=') => &digits[..pad],
+        Some(_) => return Err("base64 padding in the middle of the data".into()),
+        None => &digits[..],
+    };
+    if digits.len() % 4 == 1 {
+        return Err("truncated base64 data".into());
+    }
+
+    let mut out = Vec::with_capacity(digits.len() * 3 / 4);
+    let mut acc = 0u32;
+    for (i, &b) in digits.iter().enumerate() {
+        let value = match b {
+            b'A'..=b'Z' => b - b'A',
+            b'a'..=b'z' => b - b'a' + 26,
+            b'0'..=b'9' => b - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return Err(format!("invalid base64 character '{}'", b as char)),
+        };
+        acc = acc << 6 | value as u32;
+        if i % 4 == 3 {
+            out.extend_from_slice(&acc.to_be_bytes()[1..]);
+            acc = 0;
+        }
+    }
+    match digits.len() % 4 {
+        2 => out.push((acc >> 4) as u8),
+        3 => out.extend_from_slice(&((acc >> 2) as u16).to_be_bytes()),
+        _ => {}
+    }
+    Ok(out)
+}
+
 pub fn parse_text_with_escapes(s: &str) -> Vec<u8> {
     let mut result = Vec::new();
     let mut chars = s.chars().peekable();
@@ -51,8 +110,377 @@ pub fn parse_text_with_escapes(s: &str) -> Vec<u8> {
                 _ => result.push(b'\\'),
             }
         } else {
-            result.push(c as u8);
+            result.extend(c.encode_utf8(&mut [0; 4]).as_bytes());
         }
     }
     result
 }
+
+/// Canonical hexdump: offset, 16 hex bytes (split in two groups of 8), and an ASCII column.
+pub fn hexdump(data: &[u8]) -> Vec<String> {
+    data.chunks(16)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let mut hex = String::with_capacity(49);
+            for (j, b) in chunk.iter().enumerate() {
+                if j == 8 {
+                    hex.push(' ');
+                }
+                hex.push_str(&format!("{:02x} ", b));
+            }
+            let ascii: String = chunk
+                .iter()
+                .map(|&b| {
+                    if b.is_ascii_graphic() || b == b' ' {
+                        b as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            format!("{:08x}  {:<49} |{}|", i * 16, hex, ascii)
+        })
+        .collect()
+}
+
+/// Deterministic pseudo-random bytes (splitmix64), so a given seed always yields the same payload.
+pub fn random_bytes(len: usize, seed: u64) -> Vec<u8> {
+    let mut state = seed;
+    let mut out = Vec::with_capacity(len + 8);
+    while out.len() < len {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        out.extend_from_slice(&z.to_le_bytes());
+    }
+    out.truncate(len);
+    out
+}
+
+/// Wall-clock time of day (UTC) as `HH:MM:SS.mmm`.
+pub fn timestamp(time: std::time::SystemTime) -> String {
+    let since_epoch = time
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    let secs = since_epoch.as_secs() % 86_400;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// Parse a duration such as `250ms`, `2s`, `1.5s` or `1m`. A bare number is milliseconds.
+pub fn parse_duration(s: &str) -> Result<std::time::Duration, String> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (value, unit) = s.split_at(split);
+    let value: f64 = value
+        .parse()
+        .map_err(|_| format!("invalid duration: {}", s))?;
+    let secs = match unit.trim() {
+        "" | "ms" => value / 1000.0,
+        "us" => value / 1_000_000.0,
+        "s" => value,
+        "m" => value * 60.0,
+        "h" => value * 3600.0,
+        other => return Err(format!("unknown duration unit: {}", other)),
+    };
+    if !secs.is_finite() {
+        return Err(format!("invalid duration: {}", s));
+    }
+    std::time::Duration::try_from_secs_f64(secs).map_err(|_| format!("duration too large: {}", s))
+}
+
+/// Expand a glob whose wildcards (`*`, `?`) are in the file name only, sorted by path.
+pub fn expand_glob(pattern: &str) -> std::io::Result<Vec<std::path::PathBuf>> {
+    let path = std::path::Path::new(pattern);
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => std::path::Path::new("."),
+    };
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+
+    if dir.to_string_lossy().contains(['*', '?']) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "wildcards are only supported in the file name",
+        ));
+    }
+
+    let mut paths = vec![];
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if let Some(file_name) = entry.file_name().to_str()
+            && wildcard_match(name, file_name)
+        {
+            paths.push(entry.path());
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((bp, bn)) => {
+                    p = bp + 1;
+                    n = bn + 1;
+                    backtrack = Some((bp, bn + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Parse a port list like `53`, `5000-5100` or `53,67-69`.
+pub fn parse_ports(s: &str) -> Result<Vec<u16>, String> {
+    let port = |p: &str| {
+        p.trim()
+            .parse::<u16>()
+            .map_err(|_| format!("invalid port '{}'", p.trim()))
+    };
+
+    let mut ports = vec![];
+    for part in s.split(',') {
+        match part.split_once('-') {
+            Some((start, end)) => {
+                let (start, end) = (port(start)?, port(end)?);
+                if start > end {
+                    return Err(format!("empty port range '{}'", part.trim()));
+                }
+                ports.extend(start..=end);
+            }
+            None => ports.push(port(part)?),
+        }
+    }
+    Ok(ports)
+}
+
+/// Resolve `host:port` up front so a bad hostname gets a readable error instead of a raw OS one.
+pub fn resolve(addr: &str) -> std::io::Result<std::net::SocketAddr> {
+    use std::net::ToSocketAddrs;
+
+    let host = addr.rsplit_once(':').map_or(addr, |(host, _)| host);
+    let not_resolved = |detail: String| {
+        std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("could not resolve '{}': {}", host, detail),
+        )
+    };
+
+    addr.to_socket_addrs()
+        .map_err(|err| not_resolved(err.to_string()))?
+        .next()
+        .ok_or_else(|| not_resolved("no addresses found".into()))
+}
+
+/// Whether `s` is written like `host:port` or `[v6]:port`, without resolving it.
+pub fn is_address(s: &str) -> bool {
+    if s.parse::<std::net::SocketAddr>().is_ok() {
+        return true;
+    }
+    s.rsplit_once(':').is_some_and(|(host, port)| {
+        port.parse::<u16>().is_ok()
+            && !host.is_empty()
+            && host
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+    })
+}
+
+/// Bind a UDP socket, setting SO_REUSEADDR / SO_REUSEPORT first when asked.
+pub fn bind_udp(
+    addr: &str,
+    reuse_addr: bool,
+    reuse_port: bool,
+) -> std::io::Result<std::net::UdpSocket> {
+    use socket2::{Domain, Protocol, Socket, Type};
+
+    if !reuse_addr && !reuse_port {
+        return std::net::UdpSocket::bind(addr);
+    }
+    let addr = resolve(addr)?;
+    let socket = Socket::new(Domain::for_address(addr), Type::DGRAM, Some(Protocol::UDP))?;
+    if reuse_addr {
+        socket.set_reuse_address(true)?;
+    }
+    if reuse_port {
+        #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+        socket.set_reuse_port(true)?;
+        #[cfg(not(all(unix, not(any(target_os = "solaris", target_os = "illumos")))))]
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "SO_REUSEPORT is not supported on this platform",
+        ));
+    }
+    socket.bind(&addr.into())?;
+    Ok(socket.into())
+}
+
+/// Largest UDP payload over IPv4.
+pub const MAX_DATAGRAM: usize = 65507;
+
+/// Size of receive buffers: any UDP datagram fits, so none is truncated.
+pub const RECV_BUFFER: usize = 65535;
+
+/// Parse a datagram size, rejecting anything one UDP datagram can't carry.
+pub fn parse_datagram_size(s: &str) -> Result<usize, String> {
+    match s.trim().parse() {
+        Ok(size) if size <= MAX_DATAGRAM => Ok(size),
+        Ok(_) => Err(format!(
+            "{} exceeds the {} byte datagram limit",
+            s, MAX_DATAGRAM
+        )),
+        Err(_) => Err(format!("invalid size: {}", s)),
+    }
+}
+
+/// Parse a single byte written as decimal or `0x`-prefixed hex.
+pub fn parse_byte(s: &str) -> Result<u8, String> {
+    let s = s.trim();
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u8::from_str_radix(hex, 16),
+        None => s.parse(),
+    }
+    .map_err(|_| format!("invalid byte: {}", s))
+}
+
+/// UTC timestamp in RFC 3339 form, e.g. `2024-05-01T12:30:00.250Z`.
+pub fn rfc3339(time: std::time::SystemTime) -> String {
+    let since_epoch = time
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    let days = (since_epoch.as_secs() / 86_400) as i64;
+
+    // Civil-from-days (Howard Hinnant), valid for any date after 1970.
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!("{:04}-{:02}-{:02}T{}Z", year, month, day, timestamp(time))
+}
+
+/// Parse a UTC timestamp written by [`rfc3339`] back into a time.
+pub fn parse_rfc3339(s: &str) -> Result<std::time::SystemTime, String> {
+    let invalid = || format!("invalid timestamp: {}", s);
+    let (date, time) = s
+        .strip_suffix('Z')
+        .and_then(|s| s.split_once('T'))
+        .ok_or_else(invalid)?;
+    let (time, fraction) = time.split_once('.').unwrap_or((time, "0"));
+    let fields = |s: &str, sep| -> Result<Vec<i64>, String> {
+        s.split(sep)
+            .map(|part| part.parse().map_err(|_| invalid()))
+            .collect()
+    };
+    let (date, time) = (fields(date, '-')?, fields(time, ':')?);
+    let (&[year, month, day], &[hour, minute, second]) = (&date[..], &time[..]) else {
+        return Err(invalid());
+    };
+    if !(0..=9999).contains(&year)
+        || !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || !(0..24).contains(&hour)
+        || !(0..60).contains(&minute)
+        || !(0..=60).contains(&second)
+        || !fraction.bytes().all(|b| b.is_ascii_digit())
+    {
+        return Err(invalid());
+    }
+    let fraction: f64 = format!("0.{}", fraction).parse().map_err(|_| invalid())?;
+
+    // Days-from-civil (Howard Hinnant), the inverse of the conversion in `rfc3339`.
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    let secs = days * 86_400 + hour * 3600 + minute * 60 + second;
+    let secs = u64::try_from(secs).map_err(|_| invalid())?;
+    std::time::UNIX_EPOCH
+        .checked_add(std::time::Duration::from_secs(secs))
+        .and_then(|time| time.checked_add(std::time::Duration::from_secs_f64(fraction)))
+        .ok_or_else(invalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn random_bytes_follow_the_seed() {
+        assert_eq!(random_bytes(37, 42), random_bytes(37, 42));
+        assert_ne!(random_bytes(37, 42), random_bytes(37, 43));
+        assert_eq!(random_bytes(37, 42).len(), 37);
+        // A shorter run is a prefix of a longer one with the same seed.
+        assert_eq!(random_bytes(5, 42), random_bytes(37, 42)[..5]);
+        assert!(random_bytes(0, 42).is_empty());
+    }
+
+    #[test]
+    fn parse_duration_rejects_instead_of_panicking() {
+        use std::time::Duration;
+
+        assert_eq!(parse_duration("250ms"), Ok(Duration::from_millis(250)));
+        assert_eq!(parse_duration("1.5s"), Ok(Duration::from_millis(1500)));
+        let huge = format!("{}h", "9".repeat(400));
+        for bad in [
+            "",
+            "ms",
+            "-1s",
+            "1..2s",
+            "99999999999999999999999s",
+            huge.as_str(),
+        ] {
+            assert!(parse_duration(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn unresolvable_host_is_named() {
+        let err = resolve("nonexistent.invalid:9000").unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+        assert!(
+            err.to_string()
+                .starts_with("could not resolve 'nonexistent.invalid': "),
+            "{}",
+            err
+        );
+        assert_eq!(
+            resolve("127.0.0.1:9000").unwrap(),
+            std::net::SocketAddr::from(([127, 0, 0, 1], 9000))
+        );
+    }
+}